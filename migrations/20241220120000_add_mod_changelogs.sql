@@ -0,0 +1,8 @@
+CREATE TABLE mod_changelogs (
+    mod_name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    entry_order INT NOT NULL,
+    date TEXT,
+    categories TEXT NOT NULL,
+    PRIMARY KEY (mod_name, version)
+);
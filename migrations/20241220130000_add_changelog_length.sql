@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN changelog_length INT;
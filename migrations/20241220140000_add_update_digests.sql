@@ -0,0 +1,13 @@
+ALTER TABLE servers ADD COLUMN digest_interval INT;
+ALTER TABLE servers ADD COLUMN last_digest BIGINT;
+
+CREATE TABLE pending_updates (
+    server_id BIGINT NOT NULL,
+    mod_name TEXT NOT NULL,
+    title TEXT NOT NULL,
+    author TEXT NOT NULL,
+    version TEXT NOT NULL,
+    is_new BOOLEAN NOT NULL,
+    queued_at BIGINT NOT NULL,
+    PRIMARY KEY (server_id, mod_name)
+);
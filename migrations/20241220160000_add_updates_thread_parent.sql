@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN updates_thread_parent BIGINT;
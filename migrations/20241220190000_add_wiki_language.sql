@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN wiki_language TEXT;
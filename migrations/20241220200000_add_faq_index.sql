@@ -0,0 +1,4 @@
+-- Remove duplicate FAQ entries created by earlier imports, keeping the most recently inserted one
+DELETE FROM faq WHERE rowid NOT IN (SELECT MAX(rowid) FROM faq GROUP BY server_id, title);
+CREATE UNIQUE INDEX faq_server_title ON faq (server_id, title);
+-- mods.name is the primary key and already has an index
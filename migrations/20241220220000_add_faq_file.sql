@@ -0,0 +1,2 @@
+-- Url of a non-image file attached to an FAQ entry, images are stored in `image`
+ALTER TABLE faq ADD COLUMN file TEXT;
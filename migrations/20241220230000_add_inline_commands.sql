@@ -0,0 +1,7 @@
+-- Inline [[wiki]] and >>mod<< responses, so edits shortly after a restart still update the response
+CREATE TABLE inline_commands (
+    message_id BIGINT PRIMARY KEY NOT NULL,
+    channel_id BIGINT NOT NULL,
+    response_id BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
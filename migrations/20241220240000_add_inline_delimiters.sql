@@ -0,0 +1,3 @@
+-- Custom inline search delimiters, stored as the opening and closing text separated by a space
+ALTER TABLE servers ADD COLUMN wiki_delimiters TEXT;
+ALTER TABLE servers ADD COLUMN mod_delimiters TEXT;
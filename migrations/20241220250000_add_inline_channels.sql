@@ -0,0 +1,7 @@
+-- Channels where inline searches are allowed or denied. When a server allows any channel, all others are denied.
+CREATE TABLE inline_channels (
+    server_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    allowed BOOLEAN NOT NULL,
+    PRIMARY KEY (server_id, channel_id)
+);
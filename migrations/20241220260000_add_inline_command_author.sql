@@ -0,0 +1,2 @@
+-- Author of the inline search, who may delete the response
+ALTER TABLE inline_commands ADD COLUMN author_id BIGINT;
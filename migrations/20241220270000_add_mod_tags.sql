@@ -0,0 +1,2 @@
+-- Comma separated content tags of a mod on the mod portal
+ALTER TABLE mods ADD COLUMN tags TEXT NOT NULL DEFAULT '';
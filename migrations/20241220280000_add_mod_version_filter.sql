@@ -0,0 +1,2 @@
+-- Factorio version that mod autocomplete is limited to, all versions if null
+ALTER TABLE servers ADD COLUMN mod_version TEXT;
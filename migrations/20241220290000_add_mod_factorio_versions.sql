@@ -0,0 +1,3 @@
+-- Comma separated Factorio versions a mod has releases for
+ALTER TABLE mods ADD COLUMN factorio_versions TEXT NOT NULL DEFAULT '';
+UPDATE mods SET factorio_versions = COALESCE(factorio_version, '');
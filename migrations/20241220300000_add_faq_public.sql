@@ -0,0 +1,2 @@
+-- Whether the FAQ of a server can be read through the FAQ API
+ALTER TABLE servers ADD COLUMN faq_public BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,12 @@
+-- GitHub repositories whose releases are posted in the updates channel of a server
+CREATE TABLE github_subscriptions (
+    server_id BIGINT NOT NULL,
+    repo TEXT NOT NULL,
+    PRIMARY KEY (server_id, repo)
+);
+
+-- Latest release seen of each watched repository, so only new releases are posted
+CREATE TABLE github_releases (
+    repo TEXT PRIMARY KEY NOT NULL,
+    tag TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Whether API and wiki lookups with slash commands are only shown to the user who used them
+ALTER TABLE servers ADD COLUMN private_lookups BOOLEAN NOT NULL DEFAULT FALSE;
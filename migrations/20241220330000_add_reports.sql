@@ -0,0 +1,9 @@
+-- Problems with FAQ entries or command results reported by users, until a moderator dismisses them
+CREATE TABLE reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    server_id BIGINT NOT NULL,
+    reporter BIGINT NOT NULL,
+    target TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Whether messages invoking text commands are deleted after the bot responded
+ALTER TABLE servers ADD COLUMN delete_commands BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Whether the tags of a mod were read from its full info, the mod list does not include them
+ALTER TABLE mods ADD COLUMN tags_checked BOOLEAN NOT NULL DEFAULT FALSE;
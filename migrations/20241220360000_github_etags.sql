@@ -0,0 +1,7 @@
+-- ETag of the last latest release response, unchanged responses don't count towards the GitHub rate limit
+ALTER TABLE github_releases ADD COLUMN etag TEXT;
+
+-- GitHub repository names are case insensitive, store them in lowercase so each repository is only watched once
+UPDATE OR IGNORE github_subscriptions SET repo = lower(repo);
+DELETE FROM github_subscriptions WHERE repo != lower(repo);
+DELETE FROM github_releases WHERE repo != lower(repo);
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::{fmt, io::Read};
+use base64::Engine;
+use flate2::read::ZlibDecoder;
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Colour};
+use poise::CreateReply;
+use serde::Deserialize;
+
+use crate::{
+    Context,
+    custom_errors::CustomError,
+    Error,
+    formatting_tools::{DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT, EMBED_TITLE_LIMIT, EMBED_TOTAL_LIMIT},
+    wiki_commands::{wiki_image_url, wiki_page_url, wiki_title},
+};
+
+/// Largest blueprint file that will be downloaded, blueprint strings of several MB are rare.
+const MAX_FILE_SIZE: u32 = 8_000_000;
+/// Largest decompressed blueprint json, so a small string can't decompress into gigabytes.
+const MAX_JSON_BYTES: u64 = 64_000_000;
+/// Most entities listed by name in detailed mode.
+const DETAILED_ENTITY_LIMIT: usize = 15;
+/// Most book entries summarized in detailed mode, embeds can hold at most 25 fields.
+const DETAILED_BOOK_ENTRY_LIMIT: usize = 15;
+
+// Blueprint strings don't record which mods they need, so anything not in this list is assumed to come from a mod.
+const VANILLA_NAMES: &[&str] = &[
+    // Base game
+    "wooden-chest", "iron-chest", "steel-chest", "storage-tank",
+    "transport-belt", "fast-transport-belt", "express-transport-belt",
+    "underground-belt", "fast-underground-belt", "express-underground-belt",
+    "splitter", "fast-splitter", "express-splitter", "loader", "fast-loader", "express-loader",
+    "burner-inserter", "inserter", "long-handed-inserter", "fast-inserter", "bulk-inserter", "filter-inserter", "stack-inserter", "stack-filter-inserter",
+    "small-electric-pole", "medium-electric-pole", "big-electric-pole", "substation",
+    "pipe", "pipe-to-ground", "pump",
+    "straight-rail", "curved-rail", "curved-rail-a", "curved-rail-b", "half-diagonal-rail", "rail-ramp", "rail-support",
+    "elevated-straight-rail", "elevated-curved-rail-a", "elevated-curved-rail-b", "elevated-half-diagonal-rail",
+    "train-stop", "rail-signal", "rail-chain-signal", "locomotive", "cargo-wagon", "fluid-wagon", "artillery-wagon",
+    "car", "tank", "spidertron",
+    "active-provider-chest", "passive-provider-chest", "storage-chest", "buffer-chest", "requester-chest",
+    "logistic-chest-active-provider", "logistic-chest-passive-provider", "logistic-chest-storage", "logistic-chest-buffer", "logistic-chest-requester",
+    "roboport", "small-lamp", "arithmetic-combinator", "decider-combinator", "selector-combinator", "constant-combinator",
+    "power-switch", "programmable-speaker", "display-panel",
+    "stone-furnace", "steel-furnace", "electric-furnace",
+    "boiler", "steam-engine", "solar-panel", "accumulator", "nuclear-reactor", "heat-pipe", "heat-exchanger", "steam-turbine",
+    "burner-mining-drill", "electric-mining-drill", "offshore-pump", "pumpjack",
+    "assembling-machine-1", "assembling-machine-2", "assembling-machine-3", "oil-refinery", "chemical-plant", "centrifuge",
+    "lab", "beacon", "rocket-silo", "cargo-landing-pad",
+    "land-mine", "stone-wall", "gate", "gun-turret", "laser-turret", "flamethrower-turret", "artillery-turret", "radar",
+    "infinity-chest", "infinity-pipe", "electric-energy-interface", "linked-chest", "linked-belt", "heat-interface",
+    "simple-entity-with-owner", "simple-entity-with-force", "burner-generator", "lane-splitter", "proxy-container",
+    // Space Age
+    "turbo-transport-belt", "turbo-underground-belt", "turbo-splitter", "turbo-loader",
+    "foundry", "electromagnetic-plant", "cryogenic-plant", "biochamber", "recycler", "crusher",
+    "agricultural-tower", "captive-biter-spawner", "biolab", "heating-tower", "fusion-reactor", "fusion-generator",
+    "big-mining-drill", "lightning-rod", "lightning-collector", "rocket-turret", "railgun-turret", "tesla-turret",
+    "asteroid-collector", "thruster", "cargo-bay", "space-platform-hub",
+    // Tiles
+    "stone-path", "concrete", "hazard-concrete-left", "hazard-concrete-right",
+    "refined-concrete", "refined-hazard-concrete-left", "refined-hazard-concrete-right", "landfill",
+    "foundation", "space-platform-foundation", "ice-platform",
+    "artificial-yumako-soil", "overgrowth-yumako-soil", "artificial-jellynut-soil", "overgrowth-jellynut-soil",
+    "frozen-stone-path", "frozen-concrete", "frozen-hazard-concrete-left", "frozen-hazard-concrete-right",
+    "frozen-refined-concrete", "frozen-refined-hazard-concrete-left", "frozen-refined-hazard-concrete-right",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlueprintError {
+    Empty,
+    UnsupportedVersion(char),
+    InvalidBase64,
+    InvalidCompression,
+    TooLarge,
+    InvalidJson(String),
+}
+
+impl fmt::Display for BlueprintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "No blueprint string given"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported blueprint string version `{v}`, blueprint strings start with `0`"),
+            Self::InvalidBase64 => write!(f, "Invalid blueprint string: not valid base64"),
+            Self::InvalidCompression => write!(f, "Invalid blueprint string: could not decompress contents"),
+            Self::TooLarge => write!(f, "Blueprint too large: contents are over {} MB", MAX_JSON_BYTES / 1_000_000),
+            Self::InvalidJson(e) => write!(f, "Invalid blueprint string: unrecognised contents ({e})"),
+        }
+    }
+}
+
+impl std::error::Error for BlueprintError {}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum BlueprintData {
+    Blueprint(Blueprint),
+    BlueprintBook(BlueprintBook),
+    UpgradePlanner(Planner),
+    DeconstructionPlanner(Planner),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Blueprint {
+    label: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    icons: Vec<Icon>,
+    #[serde(default)]
+    entities: Vec<NamedObject>,
+    #[serde(default)]
+    tiles: Vec<NamedObject>,
+    version: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BlueprintBook {
+    label: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    icons: Vec<Icon>,
+    #[serde(default)]
+    blueprints: Vec<BookEntry>,
+    version: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BookEntry {
+    #[serde(flatten)]
+    content: BlueprintData,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Planner {
+    label: Option<String>,
+    #[serde(default)]
+    settings: PlannerSettings,
+    version: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+struct PlannerSettings {
+    description: Option<String>,
+    #[serde(default)]
+    icons: Vec<Icon>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Icon {
+    signal: NamedObject,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct NamedObject {
+    name: String,
+}
+
+impl BlueprintData {
+    const fn kind(&self) -> &'static str {
+        match self {
+            Self::Blueprint(_) => "Blueprint",
+            Self::BlueprintBook(_) => "Blueprint book",
+            Self::UpgradePlanner(_) => "Upgrade planner",
+            Self::DeconstructionPlanner(_) => "Deconstruction planner",
+        }
+    }
+
+    fn label(&self) -> Option<&str> {
+        match self {
+            Self::Blueprint(b) => b.label.as_deref(),
+            Self::BlueprintBook(b) => b.label.as_deref(),
+            Self::UpgradePlanner(p) | Self::DeconstructionPlanner(p) => p.label.as_deref(),
+        }
+    }
+
+    fn description(&self) -> Option<&str> {
+        match self {
+            Self::Blueprint(b) => b.description.as_deref(),
+            Self::BlueprintBook(b) => b.description.as_deref(),
+            Self::UpgradePlanner(p) | Self::DeconstructionPlanner(p) => p.settings.description.as_deref(),
+        }
+    }
+
+    const fn version(&self) -> Option<u64> {
+        match self {
+            Self::Blueprint(b) => b.version,
+            Self::BlueprintBook(b) => b.version,
+            Self::UpgradePlanner(p) | Self::DeconstructionPlanner(p) => p.version,
+        }
+    }
+
+    fn icons(&self) -> Vec<&str> {
+        let icons = match self {
+            Self::Blueprint(b) => &b.icons,
+            Self::BlueprintBook(b) => &b.icons,
+            Self::UpgradePlanner(p) | Self::DeconstructionPlanner(p) => &p.settings.icons,
+        };
+        icons.iter().map(|i| i.signal.name.as_str()).collect()
+    }
+
+    /// All blueprints, including those inside (nested) books.
+    fn blueprints(&self) -> Vec<&Blueprint> {
+        match self {
+            Self::Blueprint(b) => vec![b],
+            Self::BlueprintBook(b) => b.blueprints.iter().flat_map(|e| e.content.blueprints()).collect(),
+            Self::UpgradePlanner(_) | Self::DeconstructionPlanner(_) => vec![],
+        }
+    }
+
+    /// Number of each entity across all blueprints, most common first.
+    fn entity_counts(&self) -> Vec<(&str, usize)> {
+        count_names(self.blueprints().into_iter().flat_map(|b| &b.entities))
+    }
+
+    /// Number of each tile across all blueprints, most common first.
+    fn tile_counts(&self) -> Vec<(&str, usize)> {
+        count_names(self.blueprints().into_iter().flat_map(|b| &b.tiles))
+    }
+
+    /// Entities and tiles that are not part of the base game or Space Age.
+    fn modded_names(&self) -> Vec<&str> {
+        let mut names = self.entity_counts().into_iter()
+            .chain(self.tile_counts())
+            .map(|(name, _)| name)
+            .filter(|name| !VANILLA_NAMES.contains(name))
+            .collect::<Vec<&str>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+}
+
+fn count_names<'a>(objects: impl Iterator<Item = &'a NamedObject>) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for object in objects {
+        *counts.entry(object.name.as_str()).or_default() += 1;
+    }
+    let mut counts = counts.into_iter().collect::<Vec<(&str, usize)>>();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+/// Decode a blueprint string: a version byte followed by base64 encoded, zlib compressed json.
+pub fn decode_blueprint(blueprint_string: &str) -> Result<BlueprintData, BlueprintError> {
+    let trimmed = blueprint_string.trim().trim_matches('`').trim();
+    let mut chars = trimmed.chars();
+    let Some(version) = chars.next() else {
+        return Err(BlueprintError::Empty)
+    };
+    if version != '0' {
+        return Err(BlueprintError::UnsupportedVersion(version));
+    }
+    let encoded = chars.filter(|c| !c.is_whitespace()).collect::<String>();
+    let compressed = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| BlueprintError::InvalidBase64)?;
+    let json = decompress(&compressed, MAX_JSON_BYTES)?;
+    serde_json::from_str(&json).map_err(|e| BlueprintError::InvalidJson(e.to_string()))
+}
+
+/// Decompress zlib compressed text, failing once it grows beyond `limit` bytes.
+fn decompress(compressed: &[u8], limit: u64) -> Result<String, BlueprintError> {
+    let mut text = String::new();
+    ZlibDecoder::new(compressed)
+        .take(limit + 1)
+        .read_to_string(&mut text)
+        .map_err(|_| BlueprintError::InvalidCompression)?;
+    if text.len() as u64 > limit {
+        return Err(BlueprintError::TooLarge);
+    }
+    Ok(text)
+}
+
+/// Format the game version a blueprint was made in, stored as four 16 bit numbers.
+fn format_game_version(version: u64) -> String {
+    format!("{}.{}.{}", version >> 48, (version >> 32) & 0xffff, (version >> 16) & 0xffff)
+}
+
+fn format_counts(counts: &[(&str, usize)]) -> String {
+    counts.iter()
+        .map(|(name, count)| format!("{count}× {name}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+        .truncate_for_embed(EMBED_FIELD_LIMIT)
+}
+
+fn format_icons(icons: &[&str]) -> String {
+    icons.iter()
+        .map(|name| format!("[{}]({})", wiki_title(name), wiki_page_url(&wiki_title(name))))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Lists the most common entities, one per line.
+fn format_top_entities(counts: &[(&str, usize)]) -> String {
+    let mut lines = counts.iter()
+        .take(DETAILED_ENTITY_LIMIT)
+        .map(|(name, count)| format!("`{count}` × {}", wiki_title(name)))
+        .collect::<Vec<String>>();
+    if counts.len() > DETAILED_ENTITY_LIMIT {
+        lines.push(format!("*and {} more*", counts.len() - DETAILED_ENTITY_LIMIT));
+    }
+    lines.join("\n").truncate_for_embed(EMBED_FIELD_LIMIT)
+}
+
+/// One line summary of a book entry, used as the value of its field in detailed mode.
+fn summarize_book_entry(entry: &BlueprintData) -> String {
+    let counts = entry.entity_counts();
+    let total = counts.iter().map(|(_, c)| c).sum::<usize>();
+    let top = counts.iter()
+        .take(3)
+        .map(|(name, count)| format!("{count}× {name}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    match entry {
+        BlueprintData::BlueprintBook(_) => format!("{} blueprints, {total} entities", entry.blueprints().len()),
+        BlueprintData::Blueprint(_) if total == 0 => "Empty".to_owned(),
+        BlueprintData::Blueprint(_) => format!("{total} entities: {top}"),
+        BlueprintData::UpgradePlanner(_) | BlueprintData::DeconstructionPlanner(_) => entry.kind().to_owned(),
+    }.truncate_for_embed(EMBED_FIELD_LIMIT)
+}
+
+/// Name, value and whether the field is inline.
+type EmbedField = (String, String, bool);
+
+/// The icons and a summary of every book entry, shown in detailed mode.
+fn detail_fields(data: &BlueprintData) -> Vec<EmbedField> {
+    let mut fields = Vec::new();
+    let icons = data.icons();
+    if !icons.is_empty() {
+        fields.push(("Icons".to_owned(), format_icons(&icons).truncate_for_embed(EMBED_FIELD_LIMIT), false));
+    }
+    if let BlueprintData::BlueprintBook(book) = data {
+        for (i, entry) in book.blueprints.iter().take(DETAILED_BOOK_ENTRY_LIMIT).enumerate() {
+            let name = entry.content.label().map_or_else(|| format!("{} {}", entry.content.kind(), i + 1), ToOwned::to_owned);
+            fields.push((name.truncate_for_embed(EMBED_TITLE_LIMIT), summarize_book_entry(&entry.content), true));
+        }
+        if book.blueprints.len() > DETAILED_BOOK_ENTRY_LIMIT {
+            fields.push(("More entries".to_owned(), format!("and {} more", book.blueprints.len() - DETAILED_BOOK_ENTRY_LIMIT), false));
+        }
+    }
+    fields
+}
+
+/// Adds fields in order until the next one would take the embed over Discord's total size limit.
+fn add_fields_within_limit(mut embed: CreateEmbed, mut size: usize, fields: Vec<EmbedField>) -> CreateEmbed {
+    for (name, value, inline) in fields {
+        let field_size = name.chars().count() + value.chars().count();
+        if size + field_size > EMBED_TOTAL_LIMIT {
+            break;
+        }
+        size += field_size;
+        embed = embed.field(name, value, inline);
+    }
+    embed
+}
+
+fn blueprint_embed(data: &BlueprintData, detailed: bool) -> CreateEmbed {
+    let title = data.label().map_or_else(|| data.kind().to_owned(), |l| format!("{}: {l}", data.kind())).truncate_for_embed(EMBED_TITLE_LIMIT);
+    let mut size = title.chars().count();
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .color(Colour::ORANGE);
+    if let Some(description) = data.description() {
+        let description = description.truncate_for_embed(EMBED_DESCRIPTION_LIMIT);
+        size += description.chars().count();
+        embed = embed.description(description);
+    }
+    if let Some(version) = data.version() {
+        let footer = format!("Made in Factorio {}", format_game_version(version));
+        size += footer.chars().count();
+        embed = embed.footer(serenity::CreateEmbedFooter::new(footer));
+    }
+    if detailed {
+        if let Some(first) = data.icons().first() {
+            embed = embed.thumbnail(wiki_image_url(&wiki_title(first)));
+        }
+    }
+
+    let mut fields: Vec<EmbedField> = Vec::new();
+    if let BlueprintData::BlueprintBook(book) = data {
+        fields.push(("Blueprints".to_owned(), data.blueprints().len().to_string(), true));
+        if book.blueprints.len() != data.blueprints().len() {
+            fields.push(("Book entries".to_owned(), book.blueprints.len().to_string(), true));
+        }
+    }
+    let entity_counts = data.entity_counts();
+    if !entity_counts.is_empty() {
+        let total = entity_counts.iter().map(|(_, c)| c).sum::<usize>();
+        let counts = if detailed {format_top_entities(&entity_counts)} else {format_counts(&entity_counts)};
+        fields.push((format!("Entities ({total})"), counts, false));
+    }
+    let tile_counts = data.tile_counts();
+    if !tile_counts.is_empty() {
+        let total = tile_counts.iter().map(|(_, c)| c).sum::<usize>();
+        fields.push((format!("Tiles ({total})"), format_counts(&tile_counts), false));
+    }
+    let modded = data.modded_names();
+    if !modded.is_empty() {
+        fields.push(("Requires mods for".to_owned(), modded.join(", ").truncate_for_embed(EMBED_FIELD_LIMIT), false));
+    }
+    if detailed {
+        fields.extend(detail_fields(data));
+    }
+    add_fields_within_limit(embed, size, fields)
+}
+
+/// Show a summary of a blueprint string. Start with "detailed" to list entities and book contents.
+#[poise::command(prefix_command, slash_command, track_edits, aliases("bp"), install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn blueprint(
+    ctx: Context<'_>,
+    #[description = "List the most used entities, icons and the contents of books"]
+    #[flag]
+    detailed: bool,
+    #[description = "Text file containing the blueprint string"]
+    file: Option<serenity::Attachment>,
+    #[description = "Blueprint string"]
+    #[rest]
+    blueprint_string: Option<String>,
+) -> Result<(), Error> {
+    let blueprint_string = match (file, blueprint_string) {
+        (Some(f), _) => {
+            if f.size > MAX_FILE_SIZE {
+                return Err(Box::new(CustomError::new("Blueprint file too large")));
+            }
+            String::from_utf8(f.download().await?)
+                .map_err(|_| CustomError::new("Blueprint file is not a text file"))?
+        },
+        (None, Some(s)) => s,
+        (None, None) => return Err(Box::new(BlueprintError::Empty)),
+    };
+    let data = decode_blueprint(&blueprint_string)?;
+    ctx.send(CreateReply::default().embed(blueprint_embed(&data, detailed))).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // {"blueprint":{"item":"blueprint","label":"Smelting","entities":[3 stone-furnace, 2 inserter, 1 my-mod-furnace],
+    // "tiles":[2 concrete],"version":562949955256320}}
+    const SMELTING: &str = "0eNqNkd1qwzAMRt9F1y40ThyIX2OXY5QkVYfBloutjIWQd6/6y6CY5cbgzz5HElpg8BOekyMGu4BjDGD/ZAp8P6CX7COgZ0ffEiGxY4cZ7Odyv8wHmsKACWylgPqAAmSOhLvTlKgfUahzzEJFutb5BbtXMMu5rurNoV8ORxkTS/aGV2W83tqCLjualyPMuxCPZUldlpgNczRlvN06h3k6vhSw84/FPNgx0piQixuobqX//Vw9P0uNH0z59mJa3TVdZ4w2ba2lgQsSO8Me";
+
+    #[test]
+    fn decodes_blueprint() {
+        let data = decode_blueprint(SMELTING).unwrap();
+        assert_eq!(data.kind(), "Blueprint");
+        assert_eq!(data.label(), Some("Smelting"));
+        assert_eq!(data.entity_counts(), vec![("stone-furnace", 3), ("inserter", 2), ("my-mod-furnace", 1)]);
+        assert_eq!(data.tile_counts(), vec![("concrete", 2)]);
+        assert_eq!(data.modded_names(), vec!["my-mod-furnace"]);
+        assert_eq!(data.version().map(format_game_version).as_deref(), Some("2.0.28"));
+    }
+
+    // Book "Rails" with icon rail, containing "Straight" (4 straight-rail, icon rail) and an unnamed
+    // blueprint (2 rail-signal, 1 rail-chain-signal, icons rail-signal and signal-A)
+    const RAILS_BOOK: &str = "0eNqdk8FqhDAURf8l6widOA7orr/QLoci0T5mQmMiSZSK+O99RsVAayftRoj33vOeuTiSSnbQGqFcWWn9QYqRCAcNKXYh8QIlklcgUXjhQlo8i1orS4rrSKy4KS7nrOINoMWghUxoUe/wSYrT9EZ33pJZpadA+Gl4MPfVIfZ2d38fDcoJJ2BJ+MNQqq6pwKCBbkm78hOPoKTVFlNazfBl0wGf00S/MVgs43TMSGMZ7JhxjmWkGwMvpwdjvZJdWH7O8yxj2SVly4DtFh+39KCSZH0bNkNDrxva2dsL4zo+r7x9iXckz0GSxXcajv5fo55Q37lQh5yoVn/dhEX1gSKvneih3H+eY/cXc5pGHQ==";
+
+    #[test]
+    fn decodes_book() {
+        let data = decode_blueprint(RAILS_BOOK).unwrap();
+        assert_eq!(data.kind(), "Blueprint book");
+        assert_eq!(data.icons(), vec!["rail"]);
+        assert_eq!(data.blueprints().len(), 2);
+        assert_eq!(data.entity_counts(), vec![("straight-rail", 4), ("rail-signal", 2), ("rail-chain-signal", 1)]);
+        assert!(data.modded_names().is_empty());
+
+        let BlueprintData::BlueprintBook(book) = &data else {
+            panic!("not a book");
+        };
+        let summaries = book.blueprints.iter().map(|e| summarize_book_entry(&e.content)).collect::<Vec<String>>();
+        assert_eq!(summaries, vec!["4 entities: 4× straight-rail", "3 entities: 2× rail-signal, 1× rail-chain-signal"]);
+        assert_eq!(book.blueprints[1].content.icons(), vec!["rail-signal", "signal-A"]);
+    }
+
+    #[test]
+    fn top_entities() {
+        let counts = (1..=20).rev().map(|i| ("assembling-machine-1", i)).collect::<Vec<(&str, usize)>>();
+        let formatted = format_top_entities(&counts);
+        assert_eq!(formatted.lines().count(), DETAILED_ENTITY_LIMIT + 1);
+        assert!(formatted.starts_with("`20` × Assembling machine 1\n"));
+        assert!(formatted.ends_with("*and 5 more*"));
+    }
+
+    #[test]
+    fn icon_urls() {
+        assert_eq!(wiki_image_url(&wiki_title("transport-belt")), "https://wiki.factorio.com/images/Transport_belt.png");
+    }
+
+    #[test]
+    fn ignores_whitespace_and_code_blocks() {
+        let wrapped = format!("```\n{}\n{}\n```", &SMELTING[..40], &SMELTING[40..]);
+        assert_eq!(decode_blueprint(&wrapped).unwrap().label(), Some("Smelting"));
+    }
+
+    #[test]
+    fn invalid_strings() {
+        assert_eq!(decode_blueprint("  ").unwrap_err(), BlueprintError::Empty);
+        assert_eq!(decode_blueprint("1abc").unwrap_err(), BlueprintError::UnsupportedVersion('1'));
+        assert_eq!(decode_blueprint("0not base64!").unwrap_err(), BlueprintError::InvalidBase64);
+        assert_eq!(decode_blueprint("0aGVsbG8=").unwrap_err(), BlueprintError::InvalidCompression);
+    }
+
+    #[test]
+    fn decompression_limit() {
+        use std::io::Write;
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all("x".repeat(1000).as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decompress(&compressed, 1000).unwrap().len(), 1000);
+        assert_eq!(decompress(&compressed, 999).unwrap_err(), BlueprintError::TooLarge);
+    }
+
+    #[test]
+    fn embed_size_limit() {
+        let fields = (0..10).map(|i| (format!("Field {i}"), "x".repeat(EMBED_FIELD_LIMIT), false)).collect::<Vec<EmbedField>>();
+        let embed = add_fields_within_limit(CreateEmbed::new().title("Title"), 500, fields);
+        let json = serde_json::to_value(&embed).unwrap();
+        let added = json["fields"].as_array().unwrap();
+        let size = 500 + added.iter()
+            .map(|f| f["name"].as_str().unwrap().chars().count() + f["value"].as_str().unwrap().chars().count())
+            .sum::<usize>();
+        assert_eq!(added.len(), 5);
+        assert!(size <= EMBED_TOTAL_LIMIT);
+    }
+}
@@ -26,6 +26,20 @@ impl fmt::Display for CustomError {
 
 impl error::Error for CustomError {}
 
+/// Reports requests that took longer than the client timeout with the `timeout` variant of the caller's error type,
+/// other request errors are kept as they are.
+pub fn request_failed<E: error::Error + Send + Sync + 'static>(
+    api: &'static str,
+    error: reqwest::Error,
+    timeout: fn(&'static str) -> E,
+) -> Error {
+    if error.is_timeout() {
+        Box::new(timeout(api))
+    } else {
+        Box::new(error)
+    }
+}
+
 
 pub async fn send_custom_error_message(ctx: Context<'_>, msg: &str) -> Result<(), Error> {
     let embed = serenity::CreateEmbed::new()
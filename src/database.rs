@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+use sqlx::SqlitePool;
+
+use crate::Error;
+
+/// Remove everything stored about a server. Either all tables are cleared or none are.
+pub async fn clear_server_data(db: &SqlitePool, server_id: i64) -> Result<(), Error> {
+    let mut transaction = db.begin().await?;
+    sqlx::query!(r#"DELETE FROM servers WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM inline_channels WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM pending_updates WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM github_subscriptions WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM reports WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedFaqLink {
+    pub title: String,
+    pub link: String,
+}
+
+/// Find FAQ links on a server that point to an entry which no longer exists.
+pub async fn find_orphaned_faq_links(db: &SqlitePool, server_id: i64) -> Result<Vec<OrphanedFaqLink>, Error> {
+    Ok(sqlx::query_as!(OrphanedFaqLink, r#"SELECT title, link AS "link!" FROM faq AS f 
+        WHERE server_id = $1 AND link IS NOT NULL 
+        AND NOT EXISTS (SELECT 1 FROM faq AS t WHERE t.server_id = f.server_id AND t.title = f.link)
+        ORDER BY title"#, server_id)
+        .fetch_all(db)
+        .await?)
+}
+
+/// Delete all FAQ links on a server that point to an entry which no longer exists.
+pub async fn delete_orphaned_faq_links(db: &SqlitePool, server_id: i64) -> Result<u64, Error> {
+    Ok(sqlx::query!(r#"DELETE FROM faq 
+        WHERE server_id = $1 AND link IS NOT NULL 
+        AND NOT EXISTS (SELECT 1 FROM faq AS t WHERE t.server_id = faq.server_id AND t.title = faq.link)"#, server_id)
+        .execute(db)
+        .await?
+        .rows_affected())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenFaqEntry {
+    pub title: String,
+    pub link: Option<String>,
+}
+
+/// Find FAQ entries on a server that have no contents, image or file, and don't link to an existing entry either.
+pub async fn find_broken_faq_entries(db: &SqlitePool, server_id: i64) -> Result<Vec<BrokenFaqEntry>, Error> {
+    Ok(sqlx::query_as!(BrokenFaqEntry, r#"SELECT title, link FROM faq AS f 
+        WHERE server_id = $1 
+        AND (contents IS NULL OR TRIM(contents) = '') 
+        AND image IS NULL 
+        AND file IS NULL 
+        AND (link IS NULL OR NOT EXISTS (SELECT 1 FROM faq AS t WHERE t.server_id = f.server_id AND t.title = f.link))
+        ORDER BY title"#, server_id)
+        .fetch_all(db)
+        .await?)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineCommand {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub response_id: i64,
+    pub author_id: Option<i64>,
+    pub created_at: i64,
+}
+
+/// Remember which response belongs to an inline command message.
+pub async fn save_inline_command(db: &SqlitePool, command: &InlineCommand) -> Result<(), Error> {
+    sqlx::query!(r#"INSERT OR REPLACE INTO inline_commands (message_id, channel_id, response_id, author_id, created_at) 
+        VALUES ($1, $2, $3, $4, $5)"#, command.message_id, command.channel_id, command.response_id, command.author_id, command.created_at)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Forget an inline command, e.g. after its response was deleted.
+pub async fn delete_inline_command(db: &SqlitePool, message_id: i64) -> Result<(), Error> {
+    sqlx::query!(r#"DELETE FROM inline_commands WHERE message_id = $1"#, message_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Delete inline commands created before the cutoff timestamp.
+pub async fn prune_inline_commands(db: &SqlitePool, cutoff: i64) -> Result<u64, Error> {
+    Ok(sqlx::query!(r#"DELETE FROM inline_commands WHERE created_at < $1"#, cutoff)
+        .execute(db)
+        .await?
+        .rows_affected())
+}
+
+/// Load all inline commands created after the cutoff timestamp, removing older ones.
+pub async fn load_inline_commands(db: &SqlitePool, cutoff: i64) -> Result<Vec<InlineCommand>, Error> {
+    prune_inline_commands(db, cutoff).await?;
+    Ok(sqlx::query_as!(InlineCommand, r#"SELECT message_id, channel_id, response_id, author_id, created_at FROM inline_commands"#)
+        .fetch_all(db)
+        .await?)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentMod {
+    pub name: String,
+    pub title: Option<String>,
+    pub owner: String,
+    pub version: Option<String>,
+    pub released_at: i64,
+}
+
+/// The most recently released mods, newest first.
+pub async fn get_recent_mods(db: &SqlitePool, limit: i64) -> Result<Vec<RecentMod>, Error> {
+    Ok(sqlx::query_as!(RecentMod, r#"SELECT name, title, owner, version, released_at FROM mods 
+        ORDER BY released_at DESC LIMIT $1"#, limit)
+        .fetch_all(db)
+        .await?)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopMod {
+    pub name: String,
+    pub title: Option<String>,
+    pub owner: String,
+    pub downloads_count: i64,
+}
+
+/// The most downloaded mods, optionally only those for one Factorio version or of one category.
+/// Mods without a category on the portal are stored with an empty category and count as "No Category".
+pub async fn get_top_mods(db: &SqlitePool, factorio_version: Option<&str>, category: Option<&str>, limit: i64) -> Result<Vec<TopMod>, Error> {
+    Ok(sqlx::query_as!(TopMod, r#"SELECT name, title, owner, downloads_count FROM mods 
+        WHERE ($1 IS NULL OR ',' || factorio_versions || ',' LIKE '%,' || $1 || ',%') 
+        AND ($2 IS NULL OR category = $2 OR ($2 = 'No Category' AND category = ''))
+        ORDER BY downloads_count DESC LIMIT $3"#, factorio_version, category, limit)
+        .fetch_all(db)
+        .await?)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchedMod {
+    pub name: String,
+    pub title: Option<String>,
+    pub owner: String,
+    pub summary: Option<String>,
+    pub downloads_count: i64,
+    pub factorio_versions: String,
+}
+
+/// Mods whose name or title contains `query`, used when the mod portal search API can't be used.
+/// `sort` is `relevancy`, `downloads` or `updated_at`. Relevancy puts exact matches first, followed by the most downloaded mods.
+pub async fn search_mods(db: &SqlitePool, query: &str, sort: &str, limit: i64) -> Result<Vec<SearchedMod>, Error> {
+    Ok(sqlx::query_as!(SearchedMod, r#"SELECT name, title, owner, summary, downloads_count, factorio_versions FROM mods 
+        WHERE NOT deprecated AND (name LIKE '%' || $1 || '%' OR title LIKE '%' || $1 || '%')
+        ORDER BY CASE WHEN $2 = 'relevancy' THEN lower(name) = lower($1) OR lower(title) = lower($1) ELSE 0 END DESC, 
+            CASE WHEN $2 = 'updated_at' THEN released_at ELSE downloads_count END DESC 
+        LIMIT $3"#, query, sort, limit)
+        .fetch_all(db)
+        .await?)
+}
+
+/// Subscribe a server to a mod. Subscribing again has no effect.
+pub async fn add_mod_subscription(db: &SqlitePool, server_id: i64, mod_name: &str) -> Result<(), Error> {
+    sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_mods (server_id, mod_name) VALUES ($1, $2)"#, server_id, mod_name)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// All FAQ entries of a server that are not links, each with the titles of the links pointing to it.
+/// Links to entries that no longer exist are left out.
+pub async fn get_server_faqs(db: &SqlitePool, server_id: i64) -> Result<HashMap<String, Vec<String>>, Error> {
+    let db_entries = sqlx::query!(r#"SELECT title, link FROM faq WHERE server_id = $1"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let mut faq_map: HashMap<String, Vec<String>> = db_entries.iter()
+        .filter(|f| f.link.is_none())
+        .map(|f| (f.title.clone(), Vec::new()))
+        .collect();
+    for entry in db_entries {
+        if let Some(links) = entry.link.and_then(|l| faq_map.get_mut(&l)) {
+            links.push(entry.title);
+        }
+    }
+    Ok(faq_map)
+}
+
+/// Whether a server allows its FAQ to be read through the FAQ API. Servers without settings are private.
+pub async fn faq_is_public(db: &SqlitePool, server_id: i64) -> Result<bool, Error> {
+    let record = sqlx::query!(r#"SELECT faq_public FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(record.is_some_and(|r| r.faq_public))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub id: i64,
+    pub reporter: i64,
+    pub target: String,
+    pub message: String,
+    pub created_at: i64,
+}
+
+/// Stores a report and returns its ID.
+pub async fn add_report(db: &SqlitePool, server_id: i64, reporter: i64, target: &str, message: &str, created_at: i64) -> Result<i64, Error> {
+    Ok(sqlx::query!(r#"INSERT INTO reports (server_id, reporter, target, message, created_at) VALUES ($1, $2, $3, $4, $5)"#,
+        server_id, reporter, target, message, created_at)
+        .execute(db)
+        .await?
+        .last_insert_rowid())
+}
+
+/// Open reports of a server, oldest first.
+pub async fn get_reports(db: &SqlitePool, server_id: i64) -> Result<Vec<Report>, Error> {
+    Ok(sqlx::query_as!(Report, r#"SELECT id, reporter, target, message, created_at FROM reports WHERE server_id = $1 ORDER BY id"#, server_id)
+        .fetch_all(db)
+        .await?)
+}
+
+pub async fn delete_report(db: &SqlitePool, server_id: i64, id: i64) -> Result<u64, Error> {
+    Ok(sqlx::query!(r#"DELETE FROM reports WHERE server_id = $1 AND id = $2"#, server_id, id)
+        .execute(db)
+        .await?
+        .rows_affected())
+}
+
+pub async fn deletes_command_messages(db: &SqlitePool, server_id: i64) -> Result<bool, Error> {
+    let record = sqlx::query!(r#"SELECT delete_commands FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(record.is_some_and(|r| r.delete_commands))
+}
+
+pub async fn lookups_are_private(db: &SqlitePool, server_id: i64) -> Result<bool, Error> {
+    let record = sqlx::query!(r#"SELECT private_lookups FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(record.is_some_and(|r| r.private_lookups))
+}
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FaqRecord {
+    pub title: String,
+    pub contents: Option<String>,
+    pub image: Option<String>,
+    pub file: Option<String>,
+}
+
+/// A FAQ entry by its exact title. Links are followed to the entry they point to.
+pub async fn get_faq_entry(db: &SqlitePool, server_id: i64, title: &str) -> Result<Option<FaqRecord>, Error> {
+    let Some(entry) = sqlx::query!(r#"SELECT title, contents, image, file, link FROM faq WHERE server_id = $1 AND title = $2"#, server_id, title)
+        .fetch_optional(db)
+        .await? else {
+        return Ok(None)
+    };
+    let title = entry.link.unwrap_or(entry.title);
+    Ok(sqlx::query_as!(FaqRecord, r#"SELECT title, contents, image, file FROM faq WHERE server_id = $1 AND title = $2"#, server_id, title)
+        .fetch_optional(db)
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_server_data(db: &SqlitePool, server_id: i64) {
+        sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel) VALUES ($1, 1)"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name) VALUES ($1, 'some-mod')"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO subscribed_authors (server_id, author_name) VALUES ($1, 'someone')"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, edit_time, author) VALUES ($1, 'faq', 'answer', 0, 1)"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO inline_channels (server_id, channel_id, allowed) VALUES ($1, 1, TRUE)"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO pending_updates (server_id, mod_name, title, author, version, is_new, queued_at) 
+            VALUES ($1, 'some-mod', 'Some mod', 'someone', '1.0.0', TRUE, 0)"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn clears_all_tables(db: SqlitePool) {
+        insert_server_data(&db, 1).await;
+        insert_server_data(&db, 2).await;
+        clear_server_data(&db, 1).await.unwrap();
+
+        let servers = sqlx::query!(r#"SELECT server_id FROM servers"#).fetch_all(&db).await.unwrap();
+        assert!(servers.iter().all(|r| r.server_id == 2));
+        let mods = sqlx::query!(r#"SELECT server_id FROM subscribed_mods"#).fetch_all(&db).await.unwrap();
+        assert!(mods.iter().all(|r| r.server_id == 2));
+        let authors = sqlx::query!(r#"SELECT server_id FROM subscribed_authors"#).fetch_all(&db).await.unwrap();
+        assert!(authors.iter().all(|r| r.server_id == Some(2)));
+        let faqs = sqlx::query!(r#"SELECT server_id FROM faq"#).fetch_all(&db).await.unwrap();
+        assert!(faqs.iter().all(|r| r.server_id == 2));
+        let channels = sqlx::query!(r#"SELECT server_id FROM inline_channels"#).fetch_all(&db).await.unwrap();
+        assert!(channels.iter().all(|r| r.server_id == 2));
+        let pending = sqlx::query!(r#"SELECT server_id FROM pending_updates"#).fetch_all(&db).await.unwrap();
+        assert!(pending.iter().all(|r| r.server_id == 2));
+        assert_eq!(servers.len() + mods.len() + authors.len() + faqs.len() + channels.len() + pending.len(), 6);
+    }
+
+    #[sqlx::test]
+    async fn faq_titles_are_unique(db: SqlitePool) {
+        insert_server_data(&db, 1).await;
+        let duplicate = sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, edit_time, author) VALUES (1, 'faq', 'other answer', 1, 1)"#)
+            .execute(&db)
+            .await;
+        assert!(duplicate.is_err());
+
+        // Replacing an entry keeps a single row with the new contents
+        sqlx::query!(r#"INSERT OR REPLACE INTO faq (server_id, title, contents, edit_time, author) VALUES (1, 'faq', 'new answer', 2, 1)"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        let faqs = sqlx::query!(r#"SELECT contents FROM faq WHERE server_id = 1 AND title = 'faq'"#).fetch_all(&db).await.unwrap();
+        assert_eq!(faqs.len(), 1);
+        assert_eq!(faqs[0].contents.as_deref(), Some("new answer"));
+
+        // The same title is allowed on other servers
+        insert_server_data(&db, 2).await;
+    }
+
+    #[sqlx::test]
+    async fn subscriptions_are_unique(db: SqlitePool) {
+        for _ in 0..2 {
+            sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_mods (server_id, mod_name) VALUES (1, 'some-mod')"#)
+                .execute(&db)
+                .await
+                .unwrap();
+            sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_authors (server_id, author_name) VALUES (1, 'someone')"#)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let mods = sqlx::query!(r#"SELECT mod_name FROM subscribed_mods"#).fetch_all(&db).await.unwrap();
+        assert_eq!(mods.len(), 1);
+        let authors = sqlx::query!(r#"SELECT author_name FROM subscribed_authors"#).fetch_all(&db).await.unwrap();
+        assert_eq!(authors.len(), 1);
+
+        let duplicate = sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name) VALUES (1, 'some-mod')"#)
+            .execute(&db)
+            .await;
+        assert!(duplicate.is_err());
+    }
+
+    #[sqlx::test]
+    async fn orphaned_faq_links(db: SqlitePool) {
+        insert_server_data(&db, 1).await;
+        for (server_id, title, link) in [(1, "Alias", "faq"), (1, "Dangling", "Deleted"), (2, "Other server", "faq")] {
+            sqlx::query!(r#"INSERT INTO faq (server_id, title, edit_time, author, link) VALUES ($1, $2, 0, 1, $3)"#, server_id, title, link)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let orphans = find_orphaned_faq_links(&db, 1).await.unwrap();
+        assert_eq!(orphans, vec![OrphanedFaqLink { title: "Dangling".to_owned(), link: "Deleted".to_owned() }]);
+
+        assert_eq!(delete_orphaned_faq_links(&db, 1).await.unwrap(), 1);
+        assert!(find_orphaned_faq_links(&db, 1).await.unwrap().is_empty());
+        // Links on other servers are left alone
+        assert_eq!(find_orphaned_faq_links(&db, 2).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn broken_faq_entries(db: SqlitePool) {
+        insert_server_data(&db, 1).await;
+        for (title, contents, image, link) in [
+            ("Alias", None, None, Some("faq")),
+            ("Dangling", None, None, Some("Deleted")),
+            ("Empty", Some(" "), None, None),
+            ("Image only", None, Some("https://example.com/image.png"), None),
+        ] {
+            sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, image, edit_time, author, link) VALUES (1, $1, $2, $3, 0, 1, $4)"#, title, contents, image, link)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let broken = find_broken_faq_entries(&db, 1).await.unwrap();
+        assert_eq!(broken, vec![
+            BrokenFaqEntry { title: "Dangling".to_owned(), link: Some("Deleted".to_owned()) },
+            BrokenFaqEntry { title: "Empty".to_owned(), link: None },
+        ]);
+        assert!(find_broken_faq_entries(&db, 2).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn inline_commands(db: SqlitePool) {
+        for (message_id, created_at) in [(1, 100), (2, 200), (3, 300)] {
+            save_inline_command(&db, &InlineCommand { message_id, channel_id: 10, response_id: message_id + 1000, author_id: Some(20), created_at }).await.unwrap();
+        }
+        // Saving the same message again replaces the old response
+        save_inline_command(&db, &InlineCommand { message_id: 3, channel_id: 10, response_id: 5000, author_id: None, created_at: 300 }).await.unwrap();
+
+        let mut commands = load_inline_commands(&db, 200).await.unwrap();
+        commands.sort_by_key(|c| c.message_id);
+        assert_eq!(commands, vec![
+            InlineCommand { message_id: 2, channel_id: 10, response_id: 1002, author_id: Some(20), created_at: 200 },
+            InlineCommand { message_id: 3, channel_id: 10, response_id: 5000, author_id: None, created_at: 300 },
+        ]);
+        delete_inline_command(&db, 3).await.unwrap();
+        assert_eq!(prune_inline_commands(&db, 400).await.unwrap(), 1);
+    }
+
+    #[sqlx::test]
+    async fn server_faqs_group_links(db: SqlitePool) {
+        for (server_id, title, link) in [
+            (1, "Trains", None),
+            (1, "Locomotives", Some("Trains")),
+            (1, "Choo choo", Some("Trains")),
+            (1, "Belts", None),
+            (1, "Dangling", Some("Deleted")),
+            (2, "Wagons", Some("Trains")),
+        ] {
+            sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, link, edit_time, author) VALUES ($1, $2, 'answer', $3, 0, 1)"#, server_id, title, link)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let mut faqs = get_server_faqs(&db, 1).await.unwrap();
+        assert_eq!(faqs.len(), 2);
+        let links = faqs.get_mut("Trains").unwrap();
+        links.sort();
+        assert_eq!(links, &vec!["Choo choo".to_owned(), "Locomotives".to_owned()]);
+        assert_eq!(faqs.get("Belts"), Some(&Vec::new()));
+        assert!(get_server_faqs(&db, 2).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn recent_mods(db: SqlitePool) {
+        for (name, released_at) in [("old", 100), ("newest", 300), ("middle", 200)] {
+            sqlx::query!(r#"INSERT INTO mods (name, title, owner, category, downloads_count, version, released_at) 
+                VALUES ($1, $1, 'someone', 'content', 0, '1.0.0', $2)"#, name, released_at)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let names = get_recent_mods(&db, 2).await.unwrap().into_iter().map(|m| m.name).collect::<Vec<String>>();
+        assert_eq!(names, vec!["newest", "middle"]);
+        assert_eq!(get_recent_mods(&db, 10).await.unwrap().len(), 3);
+    }
+
+    #[sqlx::test]
+    async fn top_mods(db: SqlitePool) {
+        for (name, category, factorio_version, downloads) in [
+            ("small", "Content", "2.0", 10),
+            ("big", "Content", "1.1", 1000),
+            ("medium", "Tweaks", "1.1,2.0", 100),
+            ("plain", "", "2.0", 50),
+            ("unsorted", "No Category", "2.0", 5),
+        ] {
+            sqlx::query!(r#"INSERT INTO mods (name, title, owner, category, downloads_count, factorio_versions, released_at) 
+                VALUES ($1, $1, 'someone', $2, $3, $4, 0)"#, name, category, downloads, factorio_version)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let names = |mods: Vec<TopMod>| mods.into_iter().map(|m| m.name).collect::<Vec<String>>();
+        assert_eq!(names(get_top_mods(&db, None, None, 10).await.unwrap()), vec!["big", "medium", "plain", "small", "unsorted"]);
+        assert_eq!(names(get_top_mods(&db, Some("2.0"), None, 10).await.unwrap()), vec!["medium", "plain", "small", "unsorted"]);
+        assert_eq!(names(get_top_mods(&db, Some("2.0"), Some("Content"), 10).await.unwrap()), vec!["small"]);
+        assert_eq!(names(get_top_mods(&db, None, Some("No Category"), 10).await.unwrap()), vec!["plain", "unsorted"]);
+        assert_eq!(names(get_top_mods(&db, None, None, 1).await.unwrap()), vec!["big"]);
+    }
+
+    #[sqlx::test]
+    async fn local_mod_search(db: SqlitePool) {
+        for (name, title, downloads, released_at) in [
+            ("bobplates", "Bob's Metals, Chemicals and Intermediates", 1000, 1),
+            ("bobassembly", "Bob's Assembling machines", 500, 3),
+            ("bob", "Bob", 10, 2),
+            ("Krastorio2", "Krastorio 2", 2000, 4),
+        ] {
+            sqlx::query!(r#"INSERT INTO mods (name, title, owner, category, downloads_count, released_at) 
+                VALUES ($1, $2, 'someone', 'Content', $3, $4)"#, name, title, downloads, released_at)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let names = |mods: Vec<SearchedMod>| mods.into_iter().map(|m| m.name).collect::<Vec<String>>();
+        assert_eq!(names(search_mods(&db, "Bob", "relevancy", 10).await.unwrap()), vec!["bob", "bobplates", "bobassembly"]);
+        assert_eq!(names(search_mods(&db, "bob", "downloads", 10).await.unwrap()), vec!["bobplates", "bobassembly", "bob"]);
+        assert_eq!(names(search_mods(&db, "bob", "updated_at", 2).await.unwrap()), vec!["bobassembly", "bob"]);
+        assert_eq!(names(search_mods(&db, "metals", "relevancy", 10).await.unwrap()), vec!["bobplates"]);
+        assert!(search_mods(&db, "space exploration", "relevancy", 10).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn faq_visibility(db: SqlitePool) {
+        assert!(!faq_is_public(&db, 1).await.unwrap());
+        insert_server_data(&db, 1).await;
+        assert!(!faq_is_public(&db, 1).await.unwrap());
+        sqlx::query!(r#"UPDATE servers SET faq_public = TRUE WHERE server_id = 1"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(faq_is_public(&db, 1).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn reports(db: SqlitePool) {
+        let first = add_report(&db, 1, 10, "faq install", "Link is dead", 100).await.unwrap();
+        let second = add_report(&db, 1, 11, "wiki", "Wrong page", 200).await.unwrap();
+        add_report(&db, 2, 10, "faq install", "Other server", 300).await.unwrap();
+
+        let reports = get_reports(&db, 1).await.unwrap();
+        assert_eq!(reports.iter().map(|r| r.id).collect::<Vec<i64>>(), vec![first, second]);
+        assert_eq!(reports[0].message, "Link is dead");
+
+        // Reports can only be dismissed in the server they were made in
+        assert_eq!(delete_report(&db, 2, first).await.unwrap(), 0);
+        assert_eq!(delete_report(&db, 1, first).await.unwrap(), 1);
+        assert_eq!(get_reports(&db, 1).await.unwrap().len(), 1);
+        assert_eq!(get_reports(&db, 2).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn command_message_deletion(db: SqlitePool) {
+        assert!(!deletes_command_messages(&db, 1).await.unwrap());
+        insert_server_data(&db, 1).await;
+        assert!(!deletes_command_messages(&db, 1).await.unwrap());
+        sqlx::query!(r#"UPDATE servers SET delete_commands = TRUE WHERE server_id = 1"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(deletes_command_messages(&db, 1).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn lookup_visibility(db: SqlitePool) {
+        assert!(!lookups_are_private(&db, 1).await.unwrap());
+        insert_server_data(&db, 1).await;
+        assert!(!lookups_are_private(&db, 1).await.unwrap());
+        sqlx::query!(r#"UPDATE servers SET private_lookups = TRUE WHERE server_id = 1"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(lookups_are_private(&db, 1).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn faq_entry_follows_links(db: SqlitePool) {
+        insert_server_data(&db, 1).await;
+        sqlx::query!(r#"INSERT INTO faq (server_id, title, edit_time, author, link) VALUES (1, 'Alias', 0, 1, 'faq')"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        let expected = FaqRecord { title: "faq".to_owned(), contents: Some("answer".to_owned()), image: None, file: None };
+        assert_eq!(get_faq_entry(&db, 1, "faq").await.unwrap(), Some(expected.clone()));
+        assert_eq!(get_faq_entry(&db, 1, "Alias").await.unwrap(), Some(expected));
+        assert_eq!(get_faq_entry(&db, 1, "Unknown").await.unwrap(), None);
+        assert_eq!(get_faq_entry(&db, 2, "faq").await.unwrap(), None);
+    }
+}
@@ -1,27 +1,32 @@
-use log::info;
+use dashmap::DashMap;
+use log::{error, info};
 use poise::serenity_prelude as serenity;
-use regex::Regex;
-use sqlx::{Pool, Sqlite};
+use sqlx::SqlitePool;
+use tokio::time::{Duration, Instant};
 
 use crate::{
+    database::{self, InlineCommand},
+    management::{checks::user_is_mod, inline::{get_inline_settings, InlinePattern}},
     wiki_commands,
-    mods::commands,
+    mods::{commands, update_notifications},
     Error,
     Data,
 };
 
-#[allow(clippy::unnecessary_unwrap)]
 pub async fn on_message(ctx: serenity::Context, msg: &serenity::Message, data: &Data) -> Result<(), Error> {
     if msg.author.bot {return Ok(())};
-    if let Some(wikisearch) = message_wiki_search(&msg.content).await? {
-        if let Some(response) = send_wiki_message(&ctx, msg, &wikisearch).await?{
-            data.inline_command_log.insert(msg.id, (msg.channel_id, response, tokio::time::Instant::now()));
+    let settings = get_inline_settings(data, msg.guild_id).await?;
+    if !settings.allowed_in(msg.channel_id) {return Ok(())};
+    let wiki_language = settings.wiki_language.as_deref();
+    if let Some(wikisearch) = message_wiki_search(&data.http_client, &msg.content, &settings.wiki, wiki_language).await? {
+        if let Some(response) = send_wiki_message(&ctx, &data.http_client, msg, &wikisearch, wiki_language).await?{
+            log_inline_command(data, msg, response).await;
         }
         return Ok(());
     }
-    if let Some(modsearch) = message_mod_search(&msg.content) {
+    if let Some(modsearch) = settings.mods.search(&msg.content) {
         if let Some(response) = send_mod_message(&ctx, msg, data, &modsearch).await? {
-            data.inline_command_log.insert(msg.id, (msg.channel_id, response, tokio::time::Instant::now()));
+            log_inline_command(data, msg, response).await;
         }
         return Ok(());
     }
@@ -29,19 +34,21 @@ pub async fn on_message(ctx: serenity::Context, msg: &serenity::Message, data: &
 }
 
 pub async fn on_message_edit(ctx: serenity::Context, msg: &serenity::MessageUpdateEvent, data: &Data) -> Result<(), Error> {
-    if !data.inline_command_log.contains_key(&msg.id) {
+    let Some(response) = data.inline_command_log.get(&msg.id).map(|r| *r) else {
         return Ok(())
-    }
-    let (channel_id, message_id, _) = *data.inline_command_log.get(&msg.id).unwrap();
+    };
+    let (channel_id, message_id) = (response.channel_id, response.response_id);
     let Some(message_content) = &msg.content else {
         return Ok(())
     };
-    if let Some(wikisearch) = message_wiki_search(message_content).await? {
-        update_wiki_message(&ctx, channel_id, message_id, &wikisearch).await?;
+    let settings = get_inline_settings(data, msg.guild_id).await?;
+    let wiki_language = settings.wiki_language.as_deref();
+    if let Some(wikisearch) = message_wiki_search(&data.http_client, message_content, &settings.wiki, wiki_language).await? {
+        update_wiki_message(&ctx, &data.http_client, channel_id, message_id, &wikisearch, wiki_language).await?;
         return Ok(())
     };
 
-    if let Some(modsearch) = message_mod_search(message_content) {
+    if let Some(modsearch) = settings.mods.search(message_content) {
         update_mod_message(&ctx, data, channel_id, message_id, &modsearch).await?;
         return Ok(())
     };
@@ -49,19 +56,6 @@ pub async fn on_message_edit(ctx: serenity::Context, msg: &serenity::MessageUpda
     Ok(())
 }
 
-#[allow(clippy::unnecessary_unwrap)]
-fn message_mod_search(message_content: &str) -> Option<String> {
-    let mod_regex = Regex::new(r">>(.*?)<<").unwrap();
-    let neg_mod_regex = Regex::new(r"\`[\S\s]*?>>(.*?)<<[\S\s]*?\`").unwrap();
-    let mod_captures = mod_regex.captures(message_content);
-    let neg_mod_captures = neg_mod_regex.captures(message_content);
-    if mod_captures.is_none() || neg_mod_captures.is_some() {
-        None
-    } else {
-        Some(mod_captures.unwrap()[1].to_owned())
-    }
-}
-
 async fn send_mod_message(ctx: &serenity::Context, msg: &serenity::Message, data: &Data, modname: &str) -> Result<Option<serenity::MessageId>, Error> {
     let embed = commands::mod_search(modname, true, data).await?;
     let builder: serenity::CreateMessage = serenity::CreateMessage::new().embed(embed);
@@ -76,56 +70,155 @@ async fn update_mod_message(ctx: &serenity::Context, data: &Data, channel_id: se
     Ok(())
 }
 
-#[allow(clippy::unnecessary_unwrap)]
-async fn message_wiki_search(message_content: &str) -> Result<Option<String>, Error> {
-    let wiki_regex = Regex::new(r"\[\[(.*?)\]\]").unwrap();
-    let neg_wiki_regex = Regex::new(r"\`[\S\s]*?\[\[(.*?)\]\][\S\s]*?\`").unwrap();
-    if neg_wiki_regex.captures(message_content).is_some() {
-        return Ok(None)
-    }
-    let Some(wiki_captures) = wiki_regex.captures(message_content) else {return Ok(None)};
-    let wikiname = wiki_captures[1].to_owned();
-    let results = wiki_commands::opensearch_mediawiki(&wikiname).await?;
+async fn message_wiki_search(client: &reqwest::Client, message_content: &str, pattern: &InlinePattern, language: Option<&str>) -> Result<Option<String>, Error> {
+    let Some(wikiname) = pattern.search(message_content) else {return Ok(None)};
+    let results = wiki_commands::opensearch_mediawiki(client, &wikiname, language).await?;
     let Some(res) = results.first() else {
         return Ok(None)
     };
     Ok(Some(res.clone()))
 }
 
-async fn send_wiki_message(ctx: &serenity::Context, msg: &serenity::Message, wikiname: &str) -> Result<Option<serenity::MessageId>, Error> {
-    let embed = wiki_commands::get_wiki_page(wikiname).await?;
+async fn send_wiki_message(ctx: &serenity::Context, client: &reqwest::Client, msg: &serenity::Message, wikiname: &str, language: Option<&str>) -> Result<Option<serenity::MessageId>, Error> {
+    let embed = wiki_commands::get_wiki_page(client, wikiname, language).await?;
     let builder: serenity::CreateMessage = serenity::CreateMessage::new().embed(embed);
     let response = msg.channel_id.send_message(&ctx, builder).await?;
     Ok(Some(response.id))
 }
 
-async fn update_wiki_message(ctx: &serenity::Context, channel_id: serenity::ChannelId, message_id: serenity::MessageId, wikiname: &str) -> Result<(), Error> {
-    let embed = wiki_commands::get_wiki_page(wikiname).await?;
+async fn update_wiki_message(ctx: &serenity::Context, client: &reqwest::Client, channel_id: serenity::ChannelId, message_id: serenity::MessageId, wikiname: &str, language: Option<&str>) -> Result<(), Error> {
+    let embed = wiki_commands::get_wiki_page(client, wikiname, language).await?;
     let builder: serenity::EditMessage = serenity::EditMessage::new().embed(embed);
     channel_id.edit_message(&ctx, message_id, builder).await?;
     Ok(())
 }
 
-pub fn clean_inline_command_log(command_log: &dashmap::DashMap<serenity::MessageId, (serenity::ChannelId, serenity::MessageId, tokio::time::Instant)>) {
-    let cutoff_time = tokio::time::Instant::now() - tokio::time::Duration::from_secs(3600);
-    command_log.retain(|_, (_, _, t)| *t >= cutoff_time);
+/// The bot's response to an inline search, so edits and deletions of the search can be applied to it.
+#[derive(Debug, Clone, Copy)]
+pub struct InlineResponse {
+    pub channel_id: serenity::ChannelId,
+    pub response_id: serenity::MessageId,
+    /// Not known for searches saved before authors were tracked
+    pub author_id: Option<serenity::UserId>,
+    pub created: Instant,
 }
 
+pub type InlineCommandLog = DashMap<serenity::MessageId, InlineResponse>;
+
+const DELETE_EMOJI: &str = "🗑";
+
+/// How long edits to an inline command message update the response
+const INLINE_COMMAND_TTL: Duration = Duration::from_secs(3600);
+
+/// Track the response to an inline command, in memory and in the database so it survives a restart.
 #[allow(clippy::cast_possible_wrap)]
-pub async fn on_guild_leave(id: serenity::GuildId, db: Pool<Sqlite>) -> Result<(), Error> {
+async fn log_inline_command(data: &Data, msg: &serenity::Message, response: serenity::MessageId) {
+    data.inline_command_log.insert(msg.id, InlineResponse {
+        channel_id: msg.channel_id,
+        response_id: response,
+        author_id: Some(msg.author.id),
+        created: Instant::now(),
+    });
+    let command = InlineCommand {
+        message_id: msg.id.get() as i64,
+        channel_id: msg.channel_id.get() as i64,
+        response_id: response.get() as i64,
+        author_id: Some(msg.author.id.get() as i64),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    if let Err(e) = database::save_inline_command(&data.database, &command).await {
+        error!("Failed to save inline command: {e}");
+    }
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn inline_command_cutoff() -> i64 {
+    chrono::Utc::now().timestamp() - INLINE_COMMAND_TTL.as_secs() as i64
+}
+
+/// Load the inline commands that can still be edited, e.g. after a restart.
+#[allow(clippy::cast_sign_loss)]
+pub async fn load_inline_command_log(db: &SqlitePool) -> Result<InlineCommandLog, Error> {
+    let now = chrono::Utc::now().timestamp();
+    let command_log = DashMap::new();
+    for command in database::load_inline_commands(db, inline_command_cutoff()).await? {
+        let age = Duration::from_secs(now.saturating_sub(command.created_at).max(0) as u64);
+        command_log.insert(serenity::MessageId::new(command.message_id as u64), InlineResponse {
+            channel_id: serenity::ChannelId::new(command.channel_id as u64),
+            response_id: serenity::MessageId::new(command.response_id as u64),
+            author_id: command.author_id.map(|id| serenity::UserId::new(id as u64)),
+            created: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+        });
+    }
+    Ok(command_log)
+}
+
+pub async fn clean_inline_command_log(command_log: &InlineCommandLog, db: &SqlitePool) {
+    let cutoff_time = Instant::now() - INLINE_COMMAND_TTL;
+    command_log.retain(|_, response| response.created >= cutoff_time);
+    if let Err(e) = database::prune_inline_commands(db, inline_command_cutoff()).await {
+        error!("Failed to prune inline commands: {e}");
+    }
+}
+
+/// Delete an inline search response when the author of the search or a moderator reacts with a wastebasket.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn on_reaction_add(ctx: serenity::Context, reaction: &serenity::Reaction, data: &Data) -> Result<(), Error> {
+    // The emoji may be followed by a variation selector
+    let serenity::ReactionType::Unicode(emoji) = &reaction.emoji else {
+        return Ok(())
+    };
+    if emoji.trim_end_matches('\u{fe0f}') != DELETE_EMOJI {
+        return Ok(())
+    }
+    let Some(user_id) = reaction.user_id else {
+        return Ok(())
+    };
+    let Some((message_id, response)) = data.inline_command_log.iter()
+        .find(|entry| entry.response_id == reaction.message_id)
+        .map(|entry| (*entry.key(), *entry.value()))
+    else {
+        return Ok(())
+    };
+    let is_author = response.author_id == Some(user_id);
+    if !is_author {
+        let Some(guild_id) = reaction.guild_id else {
+            return Ok(())
+        };
+        if !user_is_mod(&ctx, &data.database, guild_id, reaction.channel_id, user_id).await? {
+            return Ok(())
+        }
+    }
+    response.channel_id.delete_message(&ctx, response.response_id).await?;
+    data.inline_command_log.remove(&message_id);
+    database::delete_inline_command(&data.database, message_id.get() as i64).await?;
+    Ok(())
+}
+
+/// Handles buttons that outlive the command that made them, like those on update messages.
+pub async fn on_component_interaction(ctx: serenity::Context, interaction: &serenity::ComponentInteraction, data: &Data) -> Result<(), Error> {
+    let Some((name, version)) = update_notifications::parse_changelog_button_id(&interaction.data.custom_id) else {
+        return Ok(())
+    };
+    // Fetching the changelog can take longer than Discord waits for a response
+    let defer = serenity::CreateInteractionResponse::Defer(serenity::CreateInteractionResponseMessage::new().ephemeral(true));
+    interaction.create_response(&ctx, defer).await?;
+    let followup = match update_notifications::full_changelog_embed(&data.http_client, name, version).await {
+        Ok(embed) => serenity::CreateInteractionResponseFollowup::new().embed(embed),
+        Err(e) => serenity::CreateInteractionResponseFollowup::new().content(e.to_string()),
+    };
+    interaction.create_followup(&ctx, followup.ephemeral(true)).await?;
+    Ok(())
+}
+
+#[allow(clippy::cast_possible_wrap)]
+pub async fn on_guild_leave(id: serenity::GuildId, data: &Data) -> Result<(), Error> {
     let server_id = id.get() as i64;
-    sqlx::query!(r#"DELETE FROM servers WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
+    database::clear_server_data(&data.database, server_id).await?;
+    data.prefix_cache.remove(&server_id);
+    data.inline_settings_cache.remove(&server_id);
+    data.delete_commands_cache.remove(&server_id);
+    data.private_lookups_cache.remove(&server_id);
     info!("Left guild {server_id}");
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,19 +1,21 @@
-use std::collections::HashMap;
+use std::fmt;
+use std::collections::HashSet;
 use std::time::Duration;
 use poise::ReplyHandle;
 use sqlx::{Pool, Sqlite};
 use std::sync::{Arc, RwLock};
 use poise::serenity_prelude as serenity;
-use poise::CreateReply;
+use poise::{CreateReply, Modal};
 use log::error;
 
 use crate::{
     Context, 
     custom_errors::CustomError, 
+    database, 
     Error, 
     management::{self, checks::is_mod},
     SEPARATOR, 
-    formatting_tools::DiscordFormat, 
+    formatting_tools::{DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_TITLE_LIMIT, MESSAGE_CONTENT_LIMIT}, 
 };
 
 #[derive(Debug, Clone)]
@@ -22,12 +24,30 @@ pub struct FaqCacheEntry {
     title: String,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum FaqError {
+    NotFound(String),
+}
+
+impl fmt::Display for FaqError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(f,
+                "Could not find {} or any similarly tags in FAQ tags. 
+                Would you like to search [the wiki](https://wiki.factorio.com/index.php?search={})?", name.clone().escape_formatting(), name.replace(' ', "%20")),
+        }
+    }
+}
+
+impl std::error::Error for FaqError {}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 struct FaqEntry {
     title: String,
     contents: Option<String>,
     image: Option<String>,
     link: Option<String>,
+    file: Option<String>,
 }
 
 pub async fn update_faq_cache(
@@ -90,33 +110,9 @@ async fn list_faqs(
 ) -> Result<(), Error> {
     let db = &ctx.data().database;
     let server_id = management::get_server_id(ctx)?;
-    let db_entries = sqlx::query!(r#"SELECT title, link FROM faq WHERE server_id = $1"#, server_id)
-        .fetch_all(db)
-        .await?;
-    let mut faq_map: HashMap<String, Vec<String>> = HashMap::new();
-    let base_faqs: Vec<String> = db_entries.iter().filter(|f| f.link.is_none()).map(|f| f.title.clone()).collect();
-    let link_faqs: Vec<(String, String)> = db_entries.iter()
-        .filter_map(|f| f.link
-            .clone()
-            .map(|l| (f.title.clone(), l))
-        )
-        .collect();
-    
-    for entry in &base_faqs {
-        faq_map.insert(entry.clone(), Vec::new());
-    }
-    for (faq, link) in link_faqs {
-        if let Some(map) = faq_map.get_mut(&link) {
-            map.push(faq);
-        }
-    }
+    let faq_map = database::get_server_faqs(db, server_id).await?;
     let mut faq_names = Vec::new();
-    for key in base_faqs {
-        #[allow(clippy::option_if_let_else)]
-        let link_list = match faq_map.get(&key) {
-            Some(l) => l,
-            None => &Vec::new(),
-        };
+    for (key, link_list) in faq_map {
         if link_list.is_empty() {
             faq_names.push(key);
         } else {
@@ -145,7 +141,7 @@ async fn faq_core(
     let db = &ctx.data().database;
     let server_id = management::get_server_id(ctx)?;
 
-    let (entry_final, close_match) = resolve_faq_name(db, ctx, server_id, &name_lc).await?;
+    let (entry_final, close_match) = resolve_faq_name(db, &ctx.data().faq_cache, server_id, &name_lc).await?;
 
     let embed = create_faq_embed(&name_lc, entry_final, close_match);
     ctx.send(embed).await?;
@@ -163,8 +159,8 @@ fn create_faq_embed(name: &str, faq_entry: FaqEntry, close_match: bool) -> Creat
     let mut embed = serenity::CreateEmbed::new()
         .title(title)
         .color(serenity::Colour::GOLD);
-    if let Some(content) = faq_entry.contents {
-        embed = embed.description(content);
+    if let Some(description) = faq_description(faq_entry.contents, faq_entry.file.as_deref()) {
+        embed = embed.description(description);
     };
 
     if let Some(img) = faq_entry.image {
@@ -174,7 +170,33 @@ fn create_faq_embed(name: &str, faq_entry: FaqEntry, close_match: bool) -> Creat
     CreateReply::default().embed(embed)
 }
 
-async fn resolve_faq_name(db: &Pool<Sqlite>, ctx: Context<'_>, server_id: i64, name: &str) -> Result<(FaqEntry, bool), Error> {
+/// Combine FAQ contents with a link to the attached file, shortening the contents if needed to keep the link intact.
+fn faq_description(contents: Option<String>, file: Option<&str>) -> Option<String> {
+    let Some(url) = file else {return contents};
+    let link = format!("📎 [{}]({url})", attachment_name(url).escape_formatting());
+    match contents {
+        None => Some(link),
+        Some(c) => {
+            let max_len = EMBED_DESCRIPTION_LIMIT.saturating_sub(link.chars().count() + 2);
+            Some(format!("{}\n\n{link}", c.truncate_for_embed(max_len)))
+        },
+    }
+}
+
+/// Get the file name from an attachment url, ignoring query parameters.
+fn attachment_name(url: &str) -> &str {
+    url.split('?')
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("Attachment")
+}
+
+fn is_image(attachment: &serenity::Attachment) -> bool {
+    attachment.content_type.as_deref().is_some_and(|t| t.starts_with("image/"))
+}
+
+async fn resolve_faq_name(db: &Pool<Sqlite>, faq_cache: &RwLock<Vec<FaqCacheEntry>>, server_id: i64, name: &str) -> Result<(FaqEntry, bool), Error> {
     // Find entry matching given `name`
     let entry_option = find_faq_entry_opt(db, server_id, name).await?;
 
@@ -184,25 +206,23 @@ async fn resolve_faq_name(db: &Pool<Sqlite>, ctx: Context<'_>, server_id: i64, n
         (e, false) 
     } else {
         // If no entry found, check for near matches
-        if let Some(match_name) = find_closest_faq(ctx, name, server_id)? {
+        if let Some(match_name) = find_closest_faq(faq_cache, name, server_id)? {
             (get_faq_entry(db, server_id, &match_name).await?, true)
         } else {
             // If no near matches, return no results message
-            let errmsg = format!(
-                "Could not find {} or any similarly tags in FAQ tags. 
-                Would you like to search [the wiki](https://wiki.factorio.com/index.php?search={})?", name.to_owned().escape_formatting(), name.replace(' ', "%20"));
-            return Err(Box::new(CustomError::new(&errmsg)));
+            return Err(Box::new(FaqError::NotFound(name.to_owned())));
         }
     };
 
-    // If link to other entry found, get other entry
-    let entry_final: FaqEntry = match entry.link {
-        None => entry,
-        Some(entry_link) => {
-            get_faq_entry(db, server_id, &entry_link).await?
-        }
-    };
-    Ok((entry_final, close_match))
+    Ok((follow_faq_link(db, server_id, entry).await?, close_match))
+}
+
+/// If the entry is a link to another entry, get the other entry
+async fn follow_faq_link(db: &Pool<Sqlite>, server_id: i64, entry: FaqEntry) -> Result<FaqEntry, Error> {
+    match entry.link {
+        None => Ok(entry),
+        Some(entry_link) => get_faq_entry(db, server_id, &entry_link).await,
+    }
 }
 
 async fn get_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<FaqEntry, Error> {
@@ -214,13 +234,12 @@ async fn get_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<
 
 async fn find_faq_entry_opt(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<Option<FaqEntry>, Error> {
     Ok(sqlx::query_as!(FaqEntry, 
-        r#"SELECT title, contents, image, link FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name)
+        r#"SELECT title, contents, image, link, file FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name)
         .fetch_optional(db)
         .await?)
 }
 
-fn find_closest_faq(ctx: Context<'_>, name: &str, server_id: i64) -> Result<Option<String>, Error> {
-    let cache = ctx.data().faq_cache.clone();
+fn find_closest_faq(cache: &RwLock<Vec<FaqCacheEntry>>, name: &str, server_id: i64) -> Result<Option<String>, Error> {
     let faq_cache = match cache.read() {
         Ok(c) => c,
         Err(e) => {
@@ -270,24 +289,62 @@ pub async fn faq_edit(
 }
 
 /// Add an faq entry
-#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
+#[allow(clippy::unused_async)]
 #[poise::command(prefix_command, slash_command, guild_only, track_edits, aliases("edit", "add"))]
 pub async fn new(
     ctx: Context<'_>,
     #[description = "Name of the faq"]
     name: String,
-    #[description = "Link to an image."]
-    image: Option<serenity::Attachment>,
+    #[description = "Image or file to attach"]
+    attachment: Option<serenity::Attachment>,
     #[description = "Contents of the FAQ"]
     #[rest]
     content: Option<String>,
 ) -> Result<(), Error> {
-    if name.len() > 256 {
-        return Err(Box::new(CustomError::new("FAQ title too long (must be 256 characters or shorter)")));
+    save_faq_entry(ctx, name, attachment, content).await
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "Add to FAQ"]
+struct FaqTitleModal {
+    #[name = "FAQ title"]
+    #[min_length = 1]
+    #[max_length = 256]
+    title: String,
+}
+
+/// Add a message to the FAQ
+#[allow(clippy::unused_async)]
+#[poise::command(context_menu_command = "Add to FAQ", guild_only, check="is_mod", category="Settings")]
+pub async fn add_to_faq(
+    ctx: poise::ApplicationContext<'_, crate::Data, Error>,
+    #[description = "Message to add to the FAQ"]
+    message: serenity::Message,
+) -> Result<(), Error> {
+    let content = Some(message.content.clone()).filter(|c| !c.trim().is_empty());
+    let attachment = message.attachments.iter().find(|a| is_image(a)).cloned();
+    if content.is_none() && attachment.is_none() {
+        return Err(Box::new(CustomError::new("This message has no text or image to add to the FAQ")));
+    }
+    let Some(modal) = FaqTitleModal::execute(ctx).await? else {
+        return Ok(())   // Modal timed out
+    };
+    save_faq_entry(poise::Context::Application(ctx), modal.title, attachment, content).await
+}
+
+#[allow(clippy::cast_possible_wrap)]
+async fn save_faq_entry(
+    ctx: Context<'_>,
+    name: String,
+    attachment: Option<serenity::Attachment>,
+    content: Option<String>,
+) -> Result<(), Error> {
+    if name.chars().count() > EMBED_TITLE_LIMIT {
+        return Err(Box::new(CustomError::new(&format!("FAQ title too long (must be {EMBED_TITLE_LIMIT} characters or shorter)"))));
     };
     if let Some(c) = &content {
-        if c.len() > 4096 {
-            return Err(Box::new(CustomError::new("FAQ body too long (must be 4096 characters or shorter)")));
+        if c.chars().count() > EMBED_DESCRIPTION_LIMIT {
+            return Err(Box::new(CustomError::new(&format!("FAQ body too long (must be {EMBED_DESCRIPTION_LIMIT} characters or shorter)"))));
         };
     };
     let name_lc = name.capitalize();
@@ -300,8 +357,10 @@ pub async fn new(
     // Check if name already exists
     let pre_existing = find_faq_entry_opt(db, server_id, &name_lc).await?.is_some();
 
-    // If image attached, re-upload image to generate a non-ephemeral link for storage
-    let (attachment_url, reply_handle) = get_attachment_url(image, ctx, &name_lc).await?;
+    // If a file is attached, re-upload it to generate a non-ephemeral link for storage
+    let attached_image = attachment.as_ref().is_some_and(is_image);
+    let (attachment_url, reply_handle) = get_attachment_url(attachment, ctx, &name_lc).await?;
+    let (image, file) = if attached_image {(attachment_url, None)} else {(None, attachment_url)};
 
     let timestamp = ctx.created_at().timestamp();
     let author_id = ctx.author().id.get() as i64;
@@ -310,21 +369,19 @@ pub async fn new(
     if pre_existing {
         delete_faq_entry(db, server_id, &name_lc).await?;
     };
-    sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, image, edit_time, author)
-    VALUES ($1, $2, $3, $4, $5, $6)"#, server_id, name_lc, content, attachment_url, timestamp, author_id)
-        .execute(db)
-        .await?;
+    let entry = FaqEntry{title: name_lc, contents: content, image, link: None, file};
+    add_faq_entry(db, server_id, &entry, author_id, timestamp).await?;
 
-    let title = if pre_existing {format!(r#"Successfully edited "{name_lc}""#)}
-        else {format!(r#"Successfully added "{name_lc}" to database"#)};
+    let title = if pre_existing {format!(r#"Successfully edited "{}""#, entry.title)}
+        else {format!(r#"Successfully added "{}" to database"#, entry.title)};
 
     let mut embed = serenity::CreateEmbed::new()
         .title(title)
         .colour(serenity::Colour::DARK_GREEN);
-    if let Some(c) = content {
-        embed = embed.description(c);
+    if let Some(description) = faq_description(entry.contents, entry.file.as_deref()) {
+        embed = embed.description(description);
     }
-    if let Some(url) = attachment_url {
+    if let Some(url) = entry.image {
         embed = embed.image(url);
     }
     let builder = CreateReply::default().embed(embed);
@@ -336,6 +393,14 @@ pub async fn new(
     Ok(())
 }
 
+async fn add_faq_entry(db: &Pool<Sqlite>, server_id: i64, entry: &FaqEntry, author_id: i64, timestamp: i64) -> Result<(), Error> {
+    sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, image, edit_time, author, link, file)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#, server_id, entry.title, entry.contents, entry.image, timestamp, author_id, entry.link, entry.file)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
 async fn delete_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<u64, Error> {
     Ok(sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name)
         .execute(db)
@@ -344,22 +409,33 @@ async fn delete_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Resu
 }
 
 async fn get_attachment_url<'a>(attachment: Option<serenity::Attachment>, ctx: Context<'a>, name: &str) -> Result<(Option<String>, Option<ReplyHandle<'a>>), Error> {
-    // If image attached, re-upload image to generate a non-ephemeral link for storage
-    let Some(image) = attachment else {return Ok((None, None))};
+    // If a file is attached, re-upload it to generate a non-ephemeral link for storage
+    let Some(file) = attachment else {return Ok((None, None))};
 
-    if !image.ephemeral {
-        return Ok((Some(image.url.clone()), None));
+    if !file.ephemeral {
+        return Ok((Some(file.url.clone()), None));
     }
 
-    let attachment = serenity::CreateAttachment::url(ctx.http(), &image.url).await?;
-    let embed = serenity::CreateEmbed::new()
+    let attached_image = is_image(&file);
+    let attachment = serenity::CreateAttachment::url(ctx.http(), &file.url).await?;
+    let mut embed = serenity::CreateEmbed::new()
         .title(format!("Adding FAQ entry: {name}"))
-        .description("Uploading image to Discord...")
-        .colour(serenity::Colour::DARK_GREEN)
-        .attachment(image.filename);
+        .description(if attached_image {"Uploading image to Discord..."} else {"Uploading file to Discord..."})
+        .colour(serenity::Colour::DARK_GREEN);
+    if attached_image {
+        embed = embed.attachment(file.filename);
+    }
     let builder = CreateReply::default().attachment(attachment).embed(embed);
     let reply = ctx.send(builder).await?;
     let message = reply.message().await?;
+
+    // Files other than images are not shown in the embed, only attached to the message
+    if !attached_image {
+        let Some(message_attachment) = message.attachments.first() else {
+            return Err(Box::new(CustomError::new("Could not create FAQ entry: file not found in message")))
+        };
+        return Ok((Some(message_attachment.url.clone()), Some(reply.clone())));
+    }
     
     let Some(message_embed) = message.embeds.first() else {
         return Err(Box::new(CustomError::new("Could not create FAQ entry: embed not found")))
@@ -513,8 +589,89 @@ pub async fn drop_faqs(
     Ok(())
 }
 
+/// Find FAQ links pointing to entries that no longer exist and offer to delete them
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", rename = "faqcheck", aliases("faq-check", "faq_check"))]
+pub async fn check_faq_links(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let db = &ctx.data().database;
+    let server_id = management::get_server_id(ctx)?;
+    let orphans = database::find_orphaned_faq_links(db, server_id).await?;
+    if orphans.is_empty() {
+        ctx.say("All FAQ links point to existing entries").await?;
+        return Ok(());
+    }
+    let list = orphans.iter()
+        .map(|o| format!("- {} → {}", o.title, o.link))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let button_yes = serenity::CreateButton::new("Yes").label("Delete").style(serenity::ButtonStyle::Danger);
+    let button_no = serenity::CreateButton::new("No").label("Keep").style(serenity::ButtonStyle::Primary);
+    let components = vec![serenity::CreateActionRow::Buttons(vec![button_yes, button_no])];
+    let confirmation = ctx.send(
+        CreateReply::default()
+            .content(format!("Found {} FAQ links to entries that no longer exist:\n{}\nDelete these links?", orphans.len(), list).truncate_for_embed(MESSAGE_CONTENT_LIMIT))
+            .components(components)
+        ).await?;
+
+    let Some(response) = confirmation
+        .message()
+        .await?
+        .await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(60))
+        .await 
+    else {
+        let new_message = CreateReply::default()
+            .content("Timed out")
+            .components(Vec::default());
+        confirmation.edit(ctx, new_message).await?;
+        return Ok(());
+    };
+    response.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge).await?;
+
+    let content = if response.data.custom_id == "Yes" {
+        let deleted = database::delete_orphaned_faq_links(db, server_id).await?;
+        format!("Deleted {deleted} FAQ links")
+    } else {
+        "No changes made".to_owned()
+    };
+    let new_message = CreateReply::default()
+        .content(content)
+        .components(Vec::default());
+    confirmation.edit(ctx, new_message).await?;
+    Ok(())
+}
+
+/// List FAQ entries that show nothing: no contents, no image and no working link
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", rename = "faqbroken", aliases("faq-broken", "faq_broken"))]
+pub async fn broken_faqs(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let db = &ctx.data().database;
+    let server_id = management::get_server_id(ctx)?;
+    let broken = database::find_broken_faq_entries(db, server_id).await?;
+    if broken.is_empty() {
+        ctx.say("All FAQ entries have contents or link to an existing entry").await?;
+        return Ok(());
+    }
+    let list = broken.iter()
+        .map(|b| b.link.as_ref().map_or_else(
+            || format!("- {}: no contents", b.title),
+            |link| format!("- {}: links to missing entry {link}", b.title),
+        ))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Found {} broken FAQ entries", broken.len()))
+        .description(list.truncate_for_embed(EMBED_DESCRIPTION_LIMIT))
+        .color(serenity::Colour::DARK_ORANGE);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
 async fn create_faq_dump(server_id: i64, db: &Pool<Sqlite>) -> Result<String, Error> {
-    let server_faqs = sqlx::query_as!(FaqEntry, r#"SELECT title, contents, image, link FROM faq WHERE server_id = $1"#, server_id)
+    let server_faqs = sqlx::query_as!(FaqEntry, r#"SELECT title, contents, image, link, file FROM faq WHERE server_id = $1"#, server_id)
         .fetch_all(db)
         .await?;
 
@@ -523,6 +680,41 @@ async fn create_faq_dump(server_id: i64, db: &Pool<Sqlite>) -> Result<String, Er
     Ok(faq_json)
 }
 
+/// Allow or disallow reading the FAQ and mod update feed of this server through the HTTP API, for example to show it on a website.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", rename = "faqpublic", aliases("faq-public", "faq_public"))]
+pub async fn set_faq_public(
+    ctx: Context<'_>,
+    #[description = "Whether anyone can read the FAQ and mod update feed of this server through the HTTP API"]
+    public: bool,
+) -> Result<(), Error> {
+    let server_id = management::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET faq_public = $1 WHERE server_id = $2"#,
+            public, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, faq_public) VALUES ($1, $2)"#,
+            server_id, public)
+            .execute(db)
+            .await?;
+        },
+    };
+    if public {
+        ctx.say(format!("The FAQ of this server can now be read through the HTTP API at `/guilds/{server_id}/faqs`, and its mod updates at `/guilds/{server_id}/mods.rss`.")).await?;
+    } else {
+        ctx.say("The FAQ and mod updates of this server are no longer available through the HTTP API.").await?;
+    }
+    Ok(())
+}
+
 /// Export all server FAQs to a json file
 #[poise::command(slash_command, guild_only, owners_only, hide_in_help, category="Management")]
 pub async fn export_faqs(
@@ -546,13 +738,27 @@ pub async fn export_faqs(
 }
 
 
-/// Import all FAQs from a json file. May lead to duplicate entries.
+/// How an FAQ import handles entries whose title already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum FaqImportMode {
+    #[name = "Overwrite existing entries"]
+    Overwrite,
+    #[name = "Skip existing entries"]
+    Skip,
+    #[name = "Delete all existing entries first"]
+    ReplaceAll,
+}
+
+/// Import all FAQs from a json file. By default, existing entries with the same title are overwritten.
 #[allow(clippy::cast_possible_wrap)]
 #[poise::command(slash_command, guild_only, owners_only, hide_in_help, category="Management")]
 pub async fn import_faqs(
     ctx: Context<'_>,
     faq_json: serenity::Attachment,
+    #[description = "What to do with entries that already exist (default overwrite)"]
+    mode: Option<FaqImportMode>,
 ) -> Result<(), Error> {
+    let mode = mode.unwrap_or(FaqImportMode::Overwrite);
     let server_id = management::get_server_id(ctx)?;
     // let timestamp = ctx.created_at().timestamp();
     let content = faq_json.download().await?;
@@ -561,21 +767,137 @@ pub async fn import_faqs(
     let db = &ctx.data().database;
     let timestamp = ctx.created_at().timestamp();
     let author = ctx.author().id.get() as i64;
+    // Nothing is changed unless every entry can be imported
+    let mut transaction = db.begin().await?;
+    let mut existing_titles = HashSet::new();
+    match mode {
+        FaqImportMode::ReplaceAll => {
+            sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
+                .execute(&mut *transaction)
+                .await?;
+        },
+        FaqImportMode::Skip => {
+            existing_titles = sqlx::query!(r#"SELECT title FROM faq WHERE server_id = $1"#, server_id)
+                .fetch_all(&mut *transaction)
+                .await?
+                .into_iter()
+                .map(|r| r.title)
+                .collect::<HashSet<String>>();
+        },
+        FaqImportMode::Overwrite => {},
+    }
+    let mut imported = 0;
+    let mut skipped = 0;
     for faq in faqs {
-        sqlx::query!(r#"
-            INSERT INTO faq (server_id, title, contents, image, edit_time, author, link) 
-            VALUES ($1, $2, $3, $4, $5, $6, $7)"#, 
+        if mode == FaqImportMode::Skip && !existing_titles.insert(faq.title.clone()) {
+            skipped += 1;
+            continue;
+        }
+        if let Err(e) = sqlx::query!(r#"
+            INSERT OR REPLACE INTO faq (server_id, title, contents, image, edit_time, author, link, file) 
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#, 
             server_id,
             faq.title,
             faq.contents,
             faq.image,
             timestamp,
             author,
-            faq.link
+            faq.link,
+            faq.file
         )
-            .execute(db)
-            .await?;
+            .execute(&mut *transaction)
+            .await {
+            return Err(Box::new(CustomError::new(&format!("Failed to import FAQ entry {}, no entries were imported: {e}", faq.title))));
+        };
+        imported += 1;
     };
-    ctx.say("Successfully imported all FAQ entries").await?;
+    transaction.commit().await?;
+    ctx.say(format!("Imported {imported} FAQ entries, skipped {skipped} existing entries")).await?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, contents: &str) -> FaqEntry {
+        FaqEntry{title: title.to_owned(), contents: Some(contents.to_owned()), image: None, link: None, file: None}
+    }
+
+    #[sqlx::test]
+    async fn add_find_delete(db: Pool<Sqlite>) {
+        add_faq_entry(&db, 1, &entry("Trains", "Choo choo"), 10, 0).await.unwrap();
+
+        let found = find_faq_entry_opt(&db, 1, "Trains").await.unwrap().unwrap();
+        assert_eq!(found.contents.as_deref(), Some("Choo choo"));
+        assert!(found.link.is_none());
+        // Entries belong to a single server
+        assert!(find_faq_entry_opt(&db, 2, "Trains").await.unwrap().is_none());
+
+        assert_eq!(delete_faq_entry(&db, 2, "Trains").await.unwrap(), 0);
+        assert_eq!(delete_faq_entry(&db, 1, "Trains").await.unwrap(), 1);
+        assert!(find_faq_entry_opt(&db, 1, "Trains").await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    async fn links_resolve_to_target(db: Pool<Sqlite>) {
+        add_faq_entry(&db, 1, &entry("Trains", "Choo choo"), 10, 0).await.unwrap();
+        insert_faq_link(&db, 1, "Locomotives", "Trains", 10, 0).await.unwrap();
+
+        let link = find_faq_entry_opt(&db, 1, "Locomotives").await.unwrap().unwrap();
+        assert_eq!(link.link.as_deref(), Some("Trains"));
+        assert!(link.contents.is_none());
+        let resolved = follow_faq_link(&db, 1, link).await.unwrap();
+        assert_eq!(resolved.title, "Trains");
+        assert_eq!(resolved.contents.as_deref(), Some("Choo choo"));
+
+        // A link whose target is gone can't be resolved
+        delete_faq_entry(&db, 1, "Trains").await.unwrap();
+        let link = find_faq_entry_opt(&db, 1, "Locomotives").await.unwrap().unwrap();
+        assert!(follow_faq_link(&db, 1, link).await.is_err());
+    }
+
+    async fn resolve_setup(db: &Pool<Sqlite>) -> Arc<RwLock<Vec<FaqCacheEntry>>> {
+        add_faq_entry(db, 1, &entry("Trains", "Choo choo"), 10, 0).await.unwrap();
+        add_faq_entry(db, 1, &entry("Belt balancers", "Balance belts"), 10, 0).await.unwrap();
+        insert_faq_link(db, 1, "Locomotives", "Trains", 10, 0).await.unwrap();
+        let cache = Arc::new(RwLock::new(Vec::new()));
+        update_faq_cache(cache.clone(), db.clone()).await.unwrap();
+        cache
+    }
+
+    #[sqlx::test]
+    async fn resolve_exact_match(db: Pool<Sqlite>) {
+        let cache = resolve_setup(&db).await;
+        let (entry, close_match) = resolve_faq_name(&db, &cache, 1, "Trains").await.unwrap();
+        assert_eq!(entry.title, "Trains");
+        assert!(!close_match);
+    }
+
+    #[sqlx::test]
+    async fn resolve_close_match(db: Pool<Sqlite>) {
+        let cache = resolve_setup(&db).await;
+        let (entry, close_match) = resolve_faq_name(&db, &cache, 1, "Belt balancer").await.unwrap();
+        assert_eq!(entry.title, "Belt balancers");
+        assert!(close_match);
+    }
+
+    #[sqlx::test]
+    async fn resolve_link(db: Pool<Sqlite>) {
+        let cache = resolve_setup(&db).await;
+        let (entry, close_match) = resolve_faq_name(&db, &cache, 1, "Locomotives").await.unwrap();
+        assert_eq!(entry.title, "Trains");
+        assert_eq!(entry.contents.as_deref(), Some("Choo choo"));
+        assert!(!close_match);
+    }
+
+    #[sqlx::test]
+    async fn resolve_not_found(db: Pool<Sqlite>) {
+        let cache = resolve_setup(&db).await;
+        let Err(error) = resolve_faq_name(&db, &cache, 1, "Quality modules").await else {
+            panic!("Expected an error");
+        };
+        assert_eq!(error.downcast_ref::<FaqError>(), Some(&FaqError::NotFound("Quality modules".to_owned())));
+        // Entries of other servers are not matched
+        assert!(resolve_faq_name(&db, &cache, 2, "Trains").await.is_err());
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     Context, 
     custom_errors::CustomError, 
     Error, 
-    formatting_tools::DiscordFormat
+    formatting_tools::{DiscordFormat, EMBED_TITLE_LIMIT}
 };
 
 #[derive(Debug)]
@@ -28,9 +28,9 @@ impl FFFData {
     }
 }
 
-async fn get_fff_data(number: i32) -> Result<FFFData, Error> {
+async fn get_fff_data(client: &reqwest::Client, number: i32) -> Result<FFFData, Error> {
     let url = format!("https://www.factorio.com/blog/post/fff-{number}");
-    let response = reqwest::get(&url).await?;
+    let response = client.get(&url).send().await?;
     match response.status() {
         reqwest::StatusCode::OK => (),
         reqwest::StatusCode::NOT_FOUND => {return Err(Box::new(CustomError::new("Page does not exist")))},
@@ -50,7 +50,7 @@ async fn get_fff_data(number: i32) -> Result<FFFData, Error> {
     let Some(title_element) = head.select(&title_selector).next()
         else {return Err(Box::new(CustomError::new("Failed to read FFF page: failed to read title")))};
     fff.title = title_element.value().attr("content").map(|f| {
-        f.trim_end_matches("| Factorio").to_owned().truncate_for_embed(256)
+        f.trim_end_matches("| Factorio").to_owned().truncate_for_embed(EMBED_TITLE_LIMIT)
     });
 
     let Ok(image_selector) = Selector::parse(r#"meta[property="og:image"#)
@@ -111,7 +111,7 @@ async fn fff_core(
     ctx: Context<'_>,
     number: i32,
 ) -> Result<(), Error> {
-    let fff_data = get_fff_data(number).await?;
+    let fff_data = get_fff_data(&ctx.data().http_client, number).await?;
     let embed = CreateEmbed::new()
         .title(fff_data.title.unwrap_or_default())
         .url(fff_data.url)
@@ -1,20 +1,92 @@
 use std::iter::once;
 
+// Maximum lengths of embed parts in characters, as allowed by Discord
+pub const EMBED_TITLE_LIMIT: usize = 256;
+pub const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+pub const EMBED_FIELD_LIMIT: usize = 1024;
+// Combined length of the title, description, footer and all field names and values
+pub const EMBED_TOTAL_LIMIT: usize = 6000;
+// Maximum lengths of message content and of select menu option labels and descriptions in characters
+pub const MESSAGE_CONTENT_LIMIT: usize = 2000;
+pub const SELECT_OPTION_LIMIT: usize = 100;
+
 pub trait DiscordFormat {
     fn truncate_for_embed(&self, max_len: usize) -> String;
+    fn truncate_for_embed_checked(&self, max_len: usize) -> (String, bool);
     fn capitalize(self) -> String;
     fn escape_formatting(self) -> String;
+    fn to_inline_code(self) -> String;
+    fn to_block_code(self, language: &str) -> String;
+}
+/// Formats a number with comma thousands separators, e.g. 1234567 becomes "1,234,567".
+pub fn format_thousands(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if n < 0 {
+        out.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats a unix timestamp as Discord relative timestamp markup, shown to users as e.g. "3 hours ago".
+pub fn relative_timestamp(unix: i64) -> String {
+    format!("<t:{unix}:R>")
+}
+
+/// Formats a duration as days, hours and minutes, e.g. "2d 5h 13m".
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// Truncates text for use in embeds. If anything was cut off, a link to the full text is added at the end.
+pub fn truncate_with_read_more(text: &str, max_len: usize, url: &str) -> String {
+    let (truncated, was_truncated) = text.truncate_for_embed_checked(max_len);
+    if !was_truncated {
+        return truncated;
+    }
+    let suffix = format!("\n…(truncated, [read more]({url}))");
+    let (shortened, _) = text.truncate_for_embed_checked(max_len.saturating_sub(suffix.chars().count()));
+    shortened + &suffix
 }
+
+/// Converts an RFC3339 timestamp into Discord relative timestamp markup.
+pub fn rfc3339_to_relative_timestamp(timestamp: &str) -> Option<String> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|datetime| relative_timestamp(datetime.timestamp()))
+}
+
 impl DiscordFormat for String {
     /// Truncates a String to a set length for use in embeds
     fn truncate_for_embed(&self, max_len: usize) -> String {
-        if self.len() > max_len {
-            self.chars()
-                .take(max_len - 3)
+        self.truncate_for_embed_checked(max_len).0
+    }
+
+    /// Truncates a String to a set length for use in embeds, also returning whether anything was cut off.
+    fn truncate_for_embed_checked(&self, max_len: usize) -> (String, bool) {
+        // Discord counts characters rather than bytes
+        if self.chars().count() > max_len {
+            let truncated = self.chars()
+                .take(max_len.saturating_sub(3))
                 .chain("...".chars())
-                .collect::<Self>()
+                .collect::<Self>();
+            (truncated, true)
         } else {
-            self.to_owned()
+            (self.to_owned(), false)
         }
     }
 
@@ -31,7 +103,7 @@ impl DiscordFormat for String {
         self
             .chars()
             .flat_map(|c| match c {
-                '_' | '*' | '~' => Some('\\'),
+                '\\' | '_' | '*' | '~' | '`' | '|' => Some('\\'),
                 _ => None
             }
                 .into_iter()
@@ -43,6 +115,25 @@ impl DiscordFormat for String {
             }))
             .collect::<Self>()
     }
+
+    /// Wraps a string in inline code. Backticks inside are kept from ending the code early.
+    fn to_inline_code(self) -> String {
+        if self.contains('`') {
+            // Double backticks allow single backticks inside, zero width spaces keep them from pairing up
+            format!("`` {} ``", self.replace('`', "`\u{200b}"))
+        } else {
+            format!("`{self}`")
+        }
+    }
+
+    /// Wraps a string in a code block with syntax highlighting for the given language.
+    fn to_block_code(self, language: &str) -> String {
+        let mut code = self;
+        while code.contains("```") {
+            code = code.replace("```", "`\u{200b}``");
+        }
+        format!("```{language}\n{code}\n```")
+    }
 }
 
 
@@ -51,6 +142,11 @@ impl DiscordFormat for &str {
         fn truncate_for_embed(&self, max_len: usize) -> String {
             (*self).to_string().truncate_for_embed(max_len)
         }
+
+        /// Truncates a String to a set length for use in embeds, also returning whether anything was cut off.
+        fn truncate_for_embed_checked(&self, max_len: usize) -> (String, bool) {
+            (*self).to_string().truncate_for_embed_checked(max_len)
+        }
     
         /// Capitalizes the first character in str s, lowercases the rest.
         fn capitalize(self) -> String {
@@ -61,4 +157,95 @@ impl DiscordFormat for &str {
         fn escape_formatting(self) -> String {
             self.to_owned().escape_formatting()
         }
-}
\ No newline at end of file
+
+        /// Wraps a string in inline code. Backticks inside are kept from ending the code early.
+        fn to_inline_code(self) -> String {
+            self.to_owned().to_inline_code()
+        }
+
+        /// Wraps a string in a code block with syntax highlighting for the given language.
+        fn to_block_code(self, language: &str) -> String {
+            self.to_owned().to_block_code(language)
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(7), "7");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1000), "1,000");
+        assert_eq!(format_thousands(65_536), "65,536");
+        assert_eq!(format_thousands(1_234_567), "1,234,567");
+        assert_eq!(format_thousands(-1_234), "-1,234");
+        assert_eq!(format_thousands(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn relative_timestamps() {
+        assert_eq!(relative_timestamp(0), "<t:0:R>");
+        assert_eq!(relative_timestamp(1_700_000_000), "<t:1700000000:R>");
+        assert_eq!(rfc3339_to_relative_timestamp("2023-11-14T22:13:20.000Z"), Some("<t:1700000000:R>".to_owned()));
+        assert_eq!(rfc3339_to_relative_timestamp("2023-11-15T00:13:20+02:00"), Some("<t:1700000000:R>".to_owned()));
+        assert_eq!(rfc3339_to_relative_timestamp("not a date"), None);
+        assert_eq!(rfc3339_to_relative_timestamp(""), None);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(std::time::Duration::from_secs(59)), "0m");
+        assert_eq!(format_duration(std::time::Duration::from_secs(3 * 3600 + 120)), "3h 2m");
+        assert_eq!(format_duration(std::time::Duration::from_secs(2 * 86400 + 5 * 3600 + 13 * 60 + 7)), "2d 5h 13m");
+        assert_eq!(format_duration(std::time::Duration::from_secs(86400)), "1d 0h 0m");
+    }
+
+    #[test]
+    fn truncation() {
+        let exact = "a".repeat(EMBED_TITLE_LIMIT);
+        assert_eq!(exact.truncate_for_embed(EMBED_TITLE_LIMIT), exact);
+        let long = "a".repeat(EMBED_TITLE_LIMIT + 1);
+        let truncated = long.truncate_for_embed(EMBED_TITLE_LIMIT);
+        assert_eq!(truncated.chars().count(), EMBED_TITLE_LIMIT);
+        assert!(truncated.ends_with("..."));
+    }
+
+    #[test]
+    fn truncation_multibyte() {
+        // Multibyte characters count once towards the limit and are never split
+        let fits = "é".repeat(EMBED_FIELD_LIMIT);
+        assert_eq!(fits.truncate_for_embed(EMBED_FIELD_LIMIT), fits);
+        let long = "é".repeat(EMBED_DESCRIPTION_LIMIT + 10);
+        let truncated = long.truncate_for_embed(EMBED_DESCRIPTION_LIMIT);
+        assert_eq!(truncated.chars().count(), EMBED_DESCRIPTION_LIMIT);
+        assert_eq!(truncated, "é".repeat(EMBED_DESCRIPTION_LIMIT - 3) + "...");
+    }
+
+    #[test]
+    fn read_more_links() {
+        let url = "https://wiki.factorio.com/Belt";
+        assert_eq!(truncate_with_read_more("Short text", 100, url), "Short text");
+        let truncated = truncate_with_read_more(&"a".repeat(200), 100, url);
+        assert_eq!(truncated.chars().count(), 100);
+        assert!(truncated.ends_with("...\n…(truncated, [read more](https://wiki.factorio.com/Belt))"));
+    }
+
+    #[test]
+    fn inline_code() {
+        assert_eq!("LuaEntity".to_inline_code(), "`LuaEntity`");
+        assert_eq!("a `b` c".to_inline_code(), "`` a `\u{200b}b`\u{200b} c ``");
+        assert!(!"``".to_inline_code().contains("``\u{200b}"));
+    }
+
+    #[test]
+    fn block_code() {
+        assert_eq!("game.print(1)".to_block_code("lua"), "```lua\ngame.print(1)\n```");
+        let nested = "before ``` after ```` end".to_block_code("");
+        assert_eq!(nested.matches("```").count(), 2);
+        assert!(nested.starts_with("```\nbefore "));
+        assert!(nested.ends_with(" end\n```"));
+    }
+}
@@ -0,0 +1,216 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    routing::get,
+    Json,
+    Router,
+};
+use log::{error, info};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::fmt::Write;
+
+use crate::{
+    database::{self, RecentMod},
+    Error,
+    mods::{get_subscribed_authors, get_subscribed_mods, is_subscribed},
+};
+
+// Number of updates in a mod feed, and the number of recent mods searched for them
+const FEED_LENGTH: usize = 50;
+const FEED_SEARCH_LIMIT: i64 = 1000;
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct FaqListEntry {
+    title: String,
+    /// Titles of the links pointing to this entry
+    aliases: Vec<String>,
+}
+
+fn router(db: SqlitePool) -> Router {
+    Router::new()
+        .route("/guilds/:guild_id/faqs", get(faq_list))
+        .route("/guilds/:guild_id/faqs/:title", get(faq_entry))
+        .route("/guilds/:guild_id/mods.rss", get(mod_feed))
+        .with_state(db)
+}
+
+/// Serves the FAQs of servers that made them public as read-only JSON, and their mod updates as an RSS feed.
+pub async fn serve(db: SqlitePool, port: u16) -> Result<(), Error> {
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("Serving the FAQ API on port {port}");
+    axum::serve(listener, router(db)).await?;
+    Ok(())
+}
+
+fn internal_error(e: &Error) -> StatusCode {
+    error!("Error in FAQ API: {e}");
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Private and unknown servers both give a 404, so it can't be used to find out which servers use the bot.
+async fn public_server_id(db: &SqlitePool, guild_id: u64) -> Result<i64, StatusCode> {
+    let server_id = i64::try_from(guild_id).map_err(|_| StatusCode::NOT_FOUND)?;
+    match database::faq_is_public(db, server_id).await {
+        Ok(true) => Ok(server_id),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(&e)),
+    }
+}
+
+async fn faq_list(
+    State(db): State<SqlitePool>,
+    Path(guild_id): Path<u64>,
+) -> Result<Json<Vec<FaqListEntry>>, StatusCode> {
+    let server_id = public_server_id(&db, guild_id).await?;
+    let faqs = database::get_server_faqs(&db, server_id).await.map_err(|e| internal_error(&e))?;
+    let mut entries = faqs.into_iter()
+        .map(|(title, mut aliases)| {
+            aliases.sort_unstable();
+            FaqListEntry { title, aliases }
+        })
+        .collect::<Vec<FaqListEntry>>();
+    entries.sort_unstable_by(|a, b| a.title.cmp(&b.title));
+    Ok(Json(entries))
+}
+
+async fn faq_entry(
+    State(db): State<SqlitePool>,
+    Path((guild_id, title)): Path<(u64, String)>,
+) -> Result<Json<database::FaqRecord>, StatusCode> {
+    let server_id = public_server_id(&db, guild_id).await?;
+    match database::get_faq_entry(&db, server_id, &title).await {
+        Ok(Some(entry)) => Ok(Json(entry)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(&e)),
+    }
+}
+
+async fn mod_feed(
+    State(db): State<SqlitePool>,
+    Path(guild_id): Path<u64>,
+) -> Result<([(header::HeaderName, &'static str); 1], String), StatusCode> {
+    let server_id = public_server_id(&db, guild_id).await?;
+    let subscribed_mods = get_subscribed_mods(&db, server_id).await.map_err(|e| internal_error(&e))?;
+    let subscribed_authors = get_subscribed_authors(&db, server_id).await.map_err(|e| internal_error(&e))?;
+    let updates = database::get_recent_mods(&db, FEED_SEARCH_LIMIT).await
+        .map_err(|e| internal_error(&e))?
+        .into_iter()
+        .filter(|m| is_subscribed(&subscribed_mods, &subscribed_authors, &m.name, &m.owner))
+        .take(FEED_LENGTH)
+        .collect::<Vec<RecentMod>>();
+    Ok(([(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")], mod_feed_rss(server_id, &updates)))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// RSS document with one item per mod update, newest first.
+fn mod_feed_rss(server_id: i64, updates: &[RecentMod]) -> String {
+    let mut rss = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    rss.push_str(r#"<rss version="2.0"><channel>"#);
+    let _ = write!(rss, "<title>Factorio mod updates</title><link>https://mods.factorio.com</link><description>Mod updates followed by Discord server {server_id}</description>");
+    for update in updates {
+        let title = update.title.as_deref().unwrap_or(&update.name);
+        let version = update.version.as_deref().unwrap_or_default();
+        let url = format!("https://mods.factorio.com/mod/{}", update.name.replace(' ', "%20"));
+        let _ = write!(rss, "<item><title>{} {}</title><link>{}</link><description>{} {} by {}</description><guid isPermaLink=\"false\">{}@{}</guid>",
+            escape_xml(title), escape_xml(version), escape_xml(&url), escape_xml(title), escape_xml(version), escape_xml(&update.owner), escape_xml(&update.name), escape_xml(version));
+        if let Some(released) = chrono::DateTime::from_timestamp(update.released_at, 0) {
+            let _ = write!(rss, "<pubDate>{}</pubDate>", released.to_rfc2822());
+        }
+        rss.push_str("</item>");
+    }
+    rss.push_str("</channel></rss>");
+    rss
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_faqs(db: &SqlitePool, server_id: i64, public: bool) {
+        sqlx::query!(r#"INSERT INTO servers (server_id, faq_public) VALUES ($1, $2)"#, server_id, public)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, edit_time, author) VALUES ($1, 'Trains', 'Use signals', 0, 1)"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO faq (server_id, title, edit_time, author, link) VALUES ($1, 'Rails', 0, 1, 'Trains')"#, server_id)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn public_faqs(db: SqlitePool) {
+        insert_faqs(&db, 1, true).await;
+        let Json(list) = faq_list(State(db.clone()), Path(1)).await.unwrap();
+        assert_eq!(list, vec![FaqListEntry { title: "Trains".to_owned(), aliases: vec!["Rails".to_owned()] }]);
+        let Json(entry) = faq_entry(State(db.clone()), Path((1, "Rails".to_owned()))).await.unwrap();
+        assert_eq!(entry.title, "Trains");
+        assert_eq!(entry.contents.as_deref(), Some("Use signals"));
+        assert_eq!(faq_entry(State(db), Path((1, "Belts".to_owned()))).await.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    async fn private_and_unknown_servers(db: SqlitePool) {
+        insert_faqs(&db, 1, false).await;
+        assert_eq!(faq_list(State(db.clone()), Path(1)).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert_eq!(faq_entry(State(db.clone()), Path((1, "Trains".to_owned()))).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert_eq!(faq_list(State(db.clone()), Path(2)).await.unwrap_err(), StatusCode::NOT_FOUND);
+        assert_eq!(faq_list(State(db), Path(u64::MAX)).await.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn feed_document() {
+        let updates = vec![RecentMod {
+            name: "bob's mod".to_owned(),
+            title: Some("Bob's <Mod> & more".to_owned()),
+            owner: "bob".to_owned(),
+            version: Some("1.0.1".to_owned()),
+            released_at: 1_734_696_000,
+        }];
+        let rss = mod_feed_rss(1, &updates);
+        assert!(rss.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><rss version="2.0"><channel>"#));
+        assert!(rss.contains("<item><title>Bob&apos;s &lt;Mod&gt; &amp; more 1.0.1</title><link>https://mods.factorio.com/mod/bob&apos;s%20mod</link>"));
+        assert!(rss.contains(r#"<guid isPermaLink="false">bob&apos;s mod@1.0.1</guid><pubDate>Fri, 20 Dec 2024 12:00:00 +0000</pubDate></item>"#));
+        assert!(rss.ends_with("</channel></rss>"));
+    }
+
+    #[sqlx::test]
+    async fn feed_follows_subscriptions(db: SqlitePool) {
+        insert_faqs(&db, 1, true).await;
+        for (name, owner, released_at) in [("flib", "raiguard", 3), ("Krastorio2", "raiguard", 2), ("space-exploration", "Earendel", 1)] {
+            sqlx::query!(r#"INSERT INTO mods (name, title, owner, category, downloads_count, version, released_at) 
+                VALUES ($1, $1, $2, 'Content', 0, '1.0.0', $3)"#, name, owner, released_at)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let (_, rss) = mod_feed(State(db.clone()), Path(1)).await.unwrap();
+        assert_eq!(rss.matches("<item>").count(), 3);
+
+        sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name) VALUES (1, 'flib')"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query!(r#"INSERT INTO subscribed_authors (server_id, author_name) VALUES (1, 'Earendel')"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        let (_, rss) = mod_feed(State(db.clone()), Path(1)).await.unwrap();
+        assert!(rss.contains("<title>flib 1.0.0</title>"));
+        assert!(rss.contains("<title>space-exploration 1.0.0</title>"));
+        assert!(!rss.contains("Krastorio2"));
+
+        assert_eq!(mod_feed(State(db), Path(2)).await.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,127 @@
+use log::{info, warn};
+use rand::Rng;
+use std::env::var;
+use tokio::time::{self, Duration, Instant, Interval};
+
+/// How often the background tasks run, configurable with environment variables in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Intervals {
+    /// Checking the mod portal for updated mods
+    pub mod_update: Duration,
+    /// Refreshing the mod, FAQ and subscription caches from the database
+    pub cache_refresh: Duration,
+    /// Downloading the modding API documentation
+    pub api_refresh: Duration,
+    /// Checking watched GitHub repositories for new releases
+    pub github_poll: Duration,
+}
+
+struct IntervalSetting {
+    var: &'static str,
+    default: u64,
+    /// Lower values are raised to this, to avoid hammering the mod portal or API docs
+    minimum: u64,
+}
+
+const MOD_UPDATE: IntervalSetting = IntervalSetting { var: "MOD_UPDATE_INTERVAL", default: 60, minimum: 30 };
+const CACHE_REFRESH: IntervalSetting = IntervalSetting { var: "CACHE_REFRESH_INTERVAL", default: 5*60, minimum: 60 };
+const API_REFRESH: IntervalSetting = IntervalSetting { var: "API_REFRESH_INTERVAL", default: 60*60*24, minimum: 60*60 };
+// Unauthenticated requests to the GitHub API are limited to 60 per hour, unchanged releases and GITHUB_TOKEN help
+const GITHUB_POLL: IntervalSetting = IntervalSetting { var: "GITHUB_POLL_INTERVAL", default: 15*60, minimum: 5*60 };
+
+impl Intervals {
+    /// Reads the intervals from the environment and logs the result.
+    pub fn from_env() -> Self {
+        let intervals = Self::from_vars(|name| var(name).ok());
+        info!(
+            "Intervals: mod updates every {}s, cache refresh every {}s, API refresh every {}s, GitHub releases every {}s",
+            intervals.mod_update.as_secs(), intervals.cache_refresh.as_secs(), intervals.api_refresh.as_secs(), intervals.github_poll.as_secs()
+        );
+        intervals
+    }
+
+    fn from_vars(get: impl Fn(&str) -> Option<String>) -> Self {
+        let read = |setting: &IntervalSetting| setting.read(get(setting.var).as_deref());
+        Self {
+            mod_update: read(&MOD_UPDATE),
+            cache_refresh: read(&CACHE_REFRESH),
+            api_refresh: read(&API_REFRESH),
+            github_poll: read(&GITHUB_POLL),
+        }
+    }
+}
+
+// Largest random delay added before the first run of a task, as a fraction of its interval
+const JITTER_FRACTION: u32 = 10;
+const MAX_JITTER: Duration = Duration::from_secs(60);
+
+/// Random delay of up to a tenth of the period, at most a minute.
+fn random_jitter(period: Duration) -> Duration {
+    let max_jitter = (period / JITTER_FRACTION).min(MAX_JITTER);
+    let max_millis = u64::try_from(max_jitter.as_millis()).unwrap_or(u64::MAX);
+    Duration::from_millis(rand::thread_rng().gen_range(0..=max_millis))
+}
+
+/// An interval whose first tick is delayed by a small random amount,
+/// so tasks started at the same time don't keep running at the same moment.
+/// With `skip_first`, the first tick is a whole period later instead of right away.
+pub fn jittered_interval(task: &str, period: Duration, skip_first: bool) -> Interval {
+    let jitter = random_jitter(period);
+    let first_tick = if skip_first { period + jitter } else { jitter };
+    info!("First {task} in {:.1}s, then every {}s", first_tick.as_secs_f64(), period.as_secs());
+    time::interval_at(Instant::now() + first_tick, period)
+}
+
+impl IntervalSetting {
+    fn read(&self, value: Option<&str>) -> Duration {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+            return Duration::from_secs(self.default);
+        };
+        let Ok(seconds) = value.parse::<u64>() else {
+            warn!("{} must be a number of seconds, using the default of {}s instead of `{value}`", self.var, self.default);
+            return Duration::from_secs(self.default);
+        };
+        if seconds < self.minimum {
+            warn!("{} is set to {seconds}s, using the minimum of {}s instead", self.var, self.minimum);
+            return Duration::from_secs(self.minimum);
+        }
+        Duration::from_secs(seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let intervals = Intervals::from_vars(|_| None);
+        assert_eq!(intervals.mod_update, Duration::from_secs(60));
+        assert_eq!(intervals.cache_refresh, Duration::from_secs(300));
+        assert_eq!(intervals.api_refresh, Duration::from_secs(86400));
+        assert_eq!(intervals.github_poll, Duration::from_secs(900));
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        for _ in 0..100 {
+            assert!(random_jitter(Duration::from_secs(60)) <= Duration::from_secs(6));
+            assert!(random_jitter(Duration::from_secs(86400)) <= MAX_JITTER);
+        }
+        assert_eq!(random_jitter(Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn configured_values() {
+        let intervals = Intervals::from_vars(|name| match name {
+            "MOD_UPDATE_INTERVAL" => Some("120".to_owned()),
+            "CACHE_REFRESH_INTERVAL" => Some("5".to_owned()),
+            "API_REFRESH_INTERVAL" => Some("daily".to_owned()),
+            _ => None,
+        });
+        assert_eq!(intervals.mod_update, Duration::from_secs(120));
+        // Too short and invalid values fall back to the minimum and default
+        assert_eq!(intervals.cache_refresh, Duration::from_secs(60));
+        assert_eq!(intervals.api_refresh, Duration::from_secs(86400));
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use log::warn;
+use serde::Deserialize;
+
+use crate::{Data, Error};
+
+// Translated command names and descriptions, by command name and Discord locale
+const COMMAND_TRANSLATIONS: &str = include_str!("../locales/commands.json");
+
+#[derive(Deserialize, Debug, Default)]
+struct Translation {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+type Translations = HashMap<String, HashMap<String, Translation>>;
+
+fn load_translations() -> Result<Translations, serde_json::Error> {
+    serde_json::from_str(COMMAND_TRANSLATIONS)
+}
+
+/// Adds the embedded translations to the commands they belong to. Discord only shows them for slash commands.
+pub fn localize_commands(commands: &mut [poise::Command<Data, Error>]) {
+    let translations = match load_translations() {
+        Ok(t) => t,
+        Err(e) => {
+            warn!("Could not read command translations: {e}");
+            return
+        },
+    };
+    for command in commands {
+        let Some(locales) = translations.get(&command.name) else {
+            continue;
+        };
+        for (locale, translation) in locales {
+            if let Some(name) = &translation.name {
+                command.name_localizations.insert(locale.clone(), name.clone());
+            }
+            if let Some(description) = &translation.description {
+                command.description_localizations.insert(locale.clone(), description.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Locales accepted by Discord
+    const DISCORD_LOCALES: [&str; 32] = ["id", "da", "de", "en-GB", "en-US", "es-ES", "es-419", "fr", "hr", "it", "lt", "hu",
+        "nl", "no", "pl", "pt-BR", "ro", "fi", "sv-SE", "vi", "tr", "cs", "el", "bg", "ru", "uk", "hi", "th", "zh-CN", "ja", "zh-TW", "ko"];
+
+    #[test]
+    fn translations_are_valid() {
+        let translations = load_translations().unwrap();
+        for (command, locales) in &translations {
+            for (locale, translation) in locales {
+                assert!(DISCORD_LOCALES.contains(&locale.as_str()), "{command}: unknown locale {locale}");
+                if let Some(name) = &translation.name {
+                    assert!(!name.is_empty() && name.chars().count() <= 32, "{command}: name too long in {locale}");
+                    assert!(name.chars().all(|c| c.is_lowercase() || c.is_numeric() || c == '-' || c == '_'), "{command}: invalid name in {locale}");
+                }
+                if let Some(description) = &translation.description {
+                    assert!((1..=100).contains(&description.chars().count()), "{command}: description length in {locale}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn translations_are_applied() {
+        let mut commands = vec![
+            crate::faq_commands::faq(),
+            crate::mods::commands::find_mod(),
+            crate::wiki_commands::wiki(),
+            crate::modding_api::api(),
+        ];
+        localize_commands(&mut commands);
+        let translations = load_translations().unwrap();
+        assert!(translations.keys().all(|name| commands.iter().any(|c| &c.name == name)), "translation for a command that doesn't exist");
+        for command in &commands {
+            assert!(command.description_localizations.contains_key("de"), "{} has no German description", command.name);
+        }
+    }
+}
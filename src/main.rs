@@ -1,18 +1,24 @@
 #![allow(clippy::unwrap_used, clippy::expect_used)]
 
+mod blueprint;
 mod mods;
+mod database;
 mod events;
 mod faq_commands;
+mod http_api;
 mod fff_commands;
 mod management;
 mod modding_api;
+mod ratio;
 mod wiki_commands;
 mod custom_errors;
 mod formatting_tools;
+mod intervals;
+mod localization;
 
 use dashmap::DashMap;
 use tokio::time;
-use log::{error, info};
+use log::{error, info, warn};
 use dotenv::dotenv;
 use poise::serenity_prelude as serenity;
 use std::{
@@ -26,7 +32,9 @@ use crate::{
     mods::{
         update_notifications::{
             get_mod_count, 
+            send_update_digests, 
             update_database, 
+            backfill_mod_tags, 
             update_mod_cache, 
             update_sub_cache, 
             update_author_cache, 
@@ -43,6 +51,16 @@ type Context<'a> = poise::Context<'a, Data, Error>;
 // Command separator for adding comments
 const SEPARATOR: char = '|';
 
+// Sent with every request so the mod portal, wiki and API docs can tell who is calling and how to reach us
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), " (+", env!("CARGO_PKG_REPOSITORY"), ")");
+// Most requests answer a user, so they should fail quickly rather than stall the command
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+// Downloading the entire mod list or the API docs can take a while, these requests override the default timeout
+const LARGE_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// How long to wait before downloading the API docs again when they were unavailable at startup
+const API_RETRY_INTERVAL: Duration = Duration::from_secs(10*60);
+
 // Custom user data passed to all command functions
 pub struct Data {
     database: sqlx::SqlitePool,
@@ -51,9 +69,22 @@ pub struct Data {
     mod_subscription_cache: Arc<RwLock<Vec<SubCacheEntry>>>,
     mod_author_cache: Arc<RwLock<Vec<String>>>,
     runtime_api_cache: Arc<RwLock<modding_api::runtime::ApiResponse>>,
+    runtime_api_versions: Arc<DashMap<String, Arc<modding_api::runtime::ApiNames>>>,
+    prefix_cache: Arc<DashMap<i64, String>>,
+    inline_settings_cache: Arc<DashMap<i64, Arc<management::inline::InlineSettings>>>,
+    delete_commands_cache: Arc<DashMap<i64, bool>>,
+    private_lookups_cache: Arc<DashMap<i64, bool>>,
+    start_time: time::Instant,
     data_api_cache: Arc<RwLock<modding_api::data::ApiResponse>>,
-    mod_portal_credentials: Arc<ModPortalCredentials>,
-    inline_command_log: Arc<DashMap<serenity::MessageId, (serenity::ChannelId, serenity::MessageId, time::Instant)>>,
+    runtime_api_validators: Arc<RwLock<modding_api::CacheValidators>>,
+    data_api_validators: Arc<RwLock<modding_api::CacheValidators>>,
+    mod_portal_credentials: Option<Arc<ModPortalCredentials>>,
+    /// Raises the GitHub API rate limit from 60 to 5000 requests per hour
+    github_token: Option<String>,
+    inline_command_log: Arc<events::InlineCommandLog>,
+    /// False when the mod portal isn't polled for updates, see `DISABLE_MOD_UPDATES`
+    mod_updates_enabled: bool,
+    http_client: reqwest::Client,
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
@@ -69,6 +100,10 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
         poise::FrameworkError::CommandCheckFailed { ctx, .. } => {
             let _ = custom_errors::send_custom_error_message(ctx, "invalid permissions").await;
         }
+        poise::FrameworkError::GuildOnly { ctx, .. } => {
+            // Server specific commands can't do anything useful in DMs
+            let _ = custom_errors::send_custom_error_message(ctx, "This command only works in a server").await;
+        }
         error => {
             if let Err(e) = poise::builtins::on_error(error).await {
                 error!("Error while handling error: {e}");
@@ -77,6 +112,27 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     }
 }
 
+/// Refreshes both modding API caches, returning whether both succeeded.
+async fn update_api_caches(
+    client: &reqwest::Client,
+    runtime_api_cache: &Arc<RwLock<modding_api::runtime::ApiResponse>>,
+    runtime_api_validators: &Arc<RwLock<modding_api::CacheValidators>>,
+    data_api_cache: &Arc<RwLock<modding_api::data::ApiResponse>>,
+    data_api_validators: &Arc<RwLock<modding_api::CacheValidators>>,
+) -> bool {
+    let runtime_result = modding_api::runtime::update_api_cache(client, runtime_api_cache.clone(), runtime_api_validators.clone()).await;
+    match &runtime_result {
+        Ok(()) => info!("Updated API cache"),
+        Err(error) => error!("Error while updating runtime api cache: {error}"),
+    };
+    let data_result = modding_api::data::update_api_cache(client, data_api_cache.clone(), data_api_validators.clone()).await;
+    match &data_result {
+        Ok(()) => info!("Updated API cache"),
+        Err(error) => error!("Error while updating data api cache: {error}"),
+    };
+    runtime_result.is_ok() && data_result.is_ok()
+}
+
 #[allow(clippy::too_many_lines, clippy::unreadable_literal)]
 #[tokio::main]
 async fn main() {
@@ -95,6 +151,15 @@ async fn main() {
 
     let db_clone = db.clone();
 
+    // Shared by all requests so connections are reused
+    let http_client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(HTTP_TIMEOUT)
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .build()
+        .expect("Couldn't create HTTP client");
+    let http_client_clone = http_client.clone();
+
     let mods_cache = Arc::new(RwLock::new(Vec::new()));
     let mods_cache_clone = mods_cache.clone();
 
@@ -107,62 +172,137 @@ async fn main() {
     let authorname_cache = Arc::new(RwLock::new(Vec::new()));
     let authorname_cache_clone = authorname_cache.clone();
     
-    let runtime_api: modding_api::runtime::ApiResponse = match modding_api::runtime::get_runtime_api().await {
-        Ok(a) => a,
+    // The bot can run without the API docs, commands using them just won't find anything until they are downloaded
+    let mut api_loaded = true;
+    let (runtime_api, runtime_api_validators) = match modding_api::runtime::get_runtime_api(&http_client, &modding_api::CacheValidators::default()).await {
+        Ok(Some(a)) => a,
+        // Nothing was sent to compare against, so only a misbehaving server or proxy reports it as unchanged
+        Ok(None) => {
+            error!("Modding runtime api was reported as unchanged before it was downloaded, starting without it");
+            api_loaded = false;
+            Default::default()
+        },
         Err(e) => {
-            error!("Failed to get modding runtime api: {e}");
-            return
+            error!("Failed to get modding runtime api, starting without it: {e}");
+            api_loaded = false;
+            Default::default()
         },
     };
     let runtime_api_cache = Arc::new(RwLock::new(runtime_api));
     let runtime_api_cache_clone = runtime_api_cache.clone();
+    let runtime_api_validators = Arc::new(RwLock::new(runtime_api_validators));
+    let runtime_api_validators_clone = runtime_api_validators.clone();
 
-    let datastage_api: modding_api::data::ApiResponse = match modding_api::data::get_data_api().await {
-        Ok(a) => a,
+    let (datastage_api, data_api_validators) = match modding_api::data::get_data_api(&http_client, &modding_api::CacheValidators::default()).await {
+        Ok(Some(a)) => a,
+        // Nothing was sent to compare against, so only a misbehaving server or proxy reports it as unchanged
+        Ok(None) => {
+            error!("Modding data api was reported as unchanged before it was downloaded, starting without it");
+            api_loaded = false;
+            Default::default()
+        },
         Err(e) => {
-            error!("Failed to get modding data api: {e}");
-            return
+            error!("Failed to get modding data api, starting without it: {e}");
+            api_loaded = false;
+            Default::default()
         },
     };
     let data_api_cache = Arc::new(RwLock::new(datastage_api));
     let data_api_cache_clone = data_api_cache.clone();
+    let data_api_validators = Arc::new(RwLock::new(data_api_validators));
+    let data_api_validators_clone = data_api_validators.clone();
 
-    let mod_portal_credentials = {
-        let username = var("MOD_PORTAL_USERNAME").expect("Could not find mod portal username in .env file");
-        let token = var("MOD_PORTAL_TOKEN").expect("Could not find mod portal token in .env file");
-        Arc::new(ModPortalCredentials::new(username, token))
+    let mod_portal_credentials = match (var("MOD_PORTAL_USERNAME"), var("MOD_PORTAL_TOKEN")) {
+        (Ok(username), Ok(token)) => Some(Arc::new(ModPortalCredentials::new(username, token))),
+        _ => {
+            warn!("Mod portal credentials not found, mod searches will use the local mod database instead of the mod portal search");
+            None
+        },
     };
 
-    let inline_command_log = Arc::new(DashMap::new());
+    let github_token = var("GITHUB_TOKEN").ok().filter(|t| !t.trim().is_empty());
+
+    let inline_command_log = Arc::new(events::load_inline_command_log(&db).await.unwrap_or_else(|e| {
+        error!("Failed to load inline commands: {e}");
+        DashMap::new()
+    }));
     let inline_command_log_clone = inline_command_log.clone();
 
-    // FrameworkOptions contains all of poise's configuration option in one struct
-    // Every option can be omitted to use its default value
-    let options = poise::FrameworkOptions {
-        commands: vec![
-            management::commands::help(),
-            management::commands::info(),
-            management::commands::get_server_info(),
-            management::commands::reset_server_settings(),
-            mods::commands::find_mod(),
+    // Deployments that only need the FAQ, wiki and API commands can turn off polling the mod portal
+    let mod_updates_enabled = !var("DISABLE_MOD_UPDATES").is_ok_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"));
+    if !mod_updates_enabled {
+        warn!("Mod updates are disabled, the mod database and subscriptions will not be updated");
+    }
+
+    let mut commands = vec![
+        management::commands::help(),
+        management::commands::info(),
+        management::commands::get_server_info(),
+        management::commands::set_prefix(),
+        management::commands::set_private_lookups(),
+        management::commands::set_delete_commands(),
+        management::reports::report(),
+        management::reports::reports(),
+        management::commands::diagnose(),
+        management::commands::reset_server_settings(),
+        management::commands::refresh_caches(),
+        management::commands::register(),
+        mods::commands::find_mod(),
+        mods::commands::mod_info(),
+        mods::commands::mod_image(),
+        mods::commands::search_mods(),
+        mods::commands::recent_mods(),
+        mods::commands::top_mods(),
+        mods::commands::compare_mod_versions(),
+        mods::commands::mod_changelog(),
+        mods::commands::set_modrole(),
+        mods::commands::set_mod_version(),
+        faq_commands::faq(),
+        faq_commands::faq_edit(),
+        faq_commands::add_to_faq(),
+        faq_commands::drop_faqs(),
+        faq_commands::check_faq_links(),
+        faq_commands::broken_faqs(),
+        faq_commands::export_faqs(),
+        faq_commands::import_faqs(),
+        faq_commands::set_faq_public(),
+        fff_commands::fff(),
+        modding_api::api(),
+        modding_api::lua::lua(),
+        wiki_commands::wiki(),
+        wiki_commands::set_wiki_language(),
+        wiki_commands::wiki_search_message(),
+        management::inline::set_inline_delimiters(),
+        management::inline::inline_channels(),
+        wiki_commands::wiki_raw(),
+        blueprint::blueprint(),
+        ratio::ratio(),
+    ];
+    // Subscriptions do nothing without mod updates
+    if mod_updates_enabled {
+        commands.extend([
             mods::commands::show_subscriptions(),
             mods::commands::subscribe(),
             mods::commands::unsubscribe(),
             mods::commands::set_updates_channel(),
-            mods::commands::set_modrole(),
+            mods::commands::set_updates_webhook(),
             mods::commands::show_changelogs(),
-            faq_commands::faq(),
-            faq_commands::faq_edit(),
-            faq_commands::drop_faqs(),
-            faq_commands::export_faqs(),
-            faq_commands::import_faqs(),
-            fff_commands::fff(),
-            modding_api::api(),
-            modding_api::lua::lua(),
-            wiki_commands::wiki(),
-        ],
+            mods::commands::set_changelog_length(),
+            mods::commands::set_update_mode(),
+            mods::github_releases::github(),
+        ]);
+    }
+    management::restrict_guild_only_commands(&mut commands);
+    localization::localize_commands(&mut commands);
+
+    // FrameworkOptions contains all of poise's configuration option in one struct
+    // Every option can be omitted to use its default value
+    let options = poise::FrameworkOptions {
+        commands,
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("+".into()),
+            dynamic_prefix: Some(|ctx| Box::pin(management::get_prefix(ctx))),
+            // Already poise's default, set explicitly to document that "@ρBot command" works regardless of the server's prefix
+            mention_as_prefix: true,
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                 Duration::from_secs(3600),
             ))),
@@ -170,6 +310,8 @@ async fn main() {
         },
         // The global error handler for all error cases that may occur
         on_error: |error| Box::pin(on_error(error)),
+        // Runs after every successful command
+        post_command: |ctx| Box::pin(management::delete_command_message(ctx)),
         // Every command invocation must pass this check to continue execution
         command_check: Some(|ctx| {
             Box::pin(async move {
@@ -186,7 +328,10 @@ async fn main() {
             Box::pin(async move {
                 if let serenity::FullEvent::GuildDelete { incomplete, full: _} = event {
                     if !incomplete.unavailable {
-                        events::on_guild_leave(incomplete.id, data.database.clone()).await?;
+                        // Failing to clean up shouldn't stop other events from being handled
+                        if let Err(e) = events::on_guild_leave(incomplete.id, data).await {
+                            error!("Error while removing data of server {}: {e}", incomplete.id);
+                        }
                     }
                 }
                 if let serenity::FullEvent::Message { new_message } = event {
@@ -195,17 +340,34 @@ async fn main() {
                 if let serenity::FullEvent::MessageUpdate { event, .. } = event {
                     events::on_message_edit(ctx.clone(), event, data).await?;
                 }
+                if let serenity::FullEvent::ReactionAdd { add_reaction } = event {
+                    events::on_reaction_add(ctx.clone(), add_reaction, data).await?;
+                }
+                if let serenity::FullEvent::InteractionCreate { interaction: serenity::Interaction::Component(interaction) } = event {
+                    events::on_component_interaction(ctx.clone(), interaction, data).await?;
+                }
                 Ok(())
             })
         },
         ..Default::default()
     };
 
+    let test_guild_id = var("TEST_GUILD_ID")
+        .ok()
+        .and_then(|id| id.trim().parse::<u64>().ok())
+        .map(serenity::GuildId::new);
+
     let framework = poise::Framework::builder()
         .setup(move |ctx, ready, framework| {
             Box::pin(async move {
                 println!("Logged in as {}", ready.user.name);
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                // Registering in a single server takes effect immediately, which is useful when testing
+                if let Some(guild_id) = test_guild_id {
+                    poise::builtins::register_in_guild(ctx, &framework.options().commands, guild_id).await?;
+                    info!("Registered commands in test server {guild_id}");
+                } else {
+                    poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                }
                 Ok(Data {
                     database: db_clone,
                     mod_cache: mods_cache_clone,
@@ -213,9 +375,20 @@ async fn main() {
                     mod_subscription_cache: subscription_cache_clone,
                     mod_author_cache: authorname_cache_clone,
                     runtime_api_cache: runtime_api_cache_clone,
+                    runtime_api_versions: Arc::new(DashMap::new()),
+                    prefix_cache: Arc::new(DashMap::new()),
+                    inline_settings_cache: Arc::new(DashMap::new()),
+                    delete_commands_cache: Arc::new(DashMap::new()),
+                    private_lookups_cache: Arc::new(DashMap::new()),
+                    start_time: time::Instant::now(),
                     data_api_cache: data_api_cache_clone,
+                    runtime_api_validators: runtime_api_validators_clone,
+                    data_api_validators: data_api_validators_clone,
                     mod_portal_credentials,
+                    github_token: github_token.clone(),
                     inline_command_log,
+                    mod_updates_enabled,
+                    http_client: http_client_clone,
                 })
             })
         })
@@ -233,10 +406,25 @@ async fn main() {
 
     let http_clone = client.as_ref().unwrap().http.clone();
 
+    // Servers opt in to having their FAQ and mod update feed readable through the HTTP API with /faqpublic
+    if let Ok(port) = var("FAQ_API_PORT") {
+        match port.trim().parse::<u16>() {
+            Ok(port) => {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = http_api::serve(db, port).await {
+                        error!("HTTP API stopped: {e}");
+                    }
+                });
+            },
+            Err(e) => error!("Invalid FAQ_API_PORT {port}: {e}"),
+        }
+    }
+
     let mods_count = get_mod_count(db.clone()).await;
-    if mods_count == 0 {
+    if mods_count == 0 && mod_updates_enabled {
         println!("Start initializing mod database");
-        let result = update_database(db.clone(), &http_clone, true).await;
+        let result = update_database(db.clone(), &http_client, &http_clone, true).await;
         match result {
             Ok(()) => info!{"Initialized mod database"},
             Err(error) => error!("Error while updating mod database: {error}")
@@ -244,56 +432,96 @@ async fn main() {
     }
     
     let db_clone_2 = db.clone();
-    let mut mod_update_interval = time::interval(time::Duration::from_secs(60));    // Update every minute
+    let db_clone_3 = db.clone();
+    let http_clone_2 = http_clone.clone();
+    let http_clone_3 = http_clone.clone();
+    let http_client_2 = http_client.clone();
+    let intervals = intervals::Intervals::from_env();
+    let mut mod_update_interval = intervals::jittered_interval("mod update", intervals.mod_update, false);
     tokio::spawn(async move {
         loop {
             mod_update_interval.tick().await;
-            let result = update_database(db_clone_2.clone(), &http_clone, false).await;
-            match result {
-                Ok(()) => info!{"Updated mod database"},
-                Err(error) => error!("Error while updating mod database: {error}")
+            if mod_updates_enabled {
+                let result = update_database(db_clone_2.clone(), &http_client_2, &http_clone, false).await;
+                match result {
+                    Ok(()) => info!{"Updated mod database"},
+                    Err(error) => error!("Error while updating mod database: {error}")
+                }
+                if let Err(error) = backfill_mod_tags(&db_clone_2, &http_client_2).await {
+                    error!("Error while backfilling mod tags: {error}");
+                }
             }
-            events::clean_inline_command_log(&inline_command_log_clone);
+            events::clean_inline_command_log(&inline_command_log_clone, &db_clone_2).await;
         }
     });
 
-    let mut cache_update_interval = time::interval(time::Duration::from_secs(5*60));    // Update every 5 minutes
+    if mod_updates_enabled {
+        let mut digest_interval = intervals::jittered_interval("digest check", time::Duration::from_secs(5*60), false);  // Check for due digests every 5 minutes
+        tokio::spawn(async move {
+            loop {
+                digest_interval.tick().await;
+                match send_update_digests(db_clone_3.clone(), &http_clone_2).await {
+                    Ok(()) => info!("Sent due update digests"),
+                    Err(error) => error!("Error while sending update digests: {error}"),
+                };
+            }
+        });
+    }
+
+    if mod_updates_enabled {
+        let db_clone_4 = db.clone();
+        let http_client_3 = http_client.clone();
+        let mut github_interval = intervals::jittered_interval("GitHub release check", intervals.github_poll, false);
+        tokio::spawn(async move {
+            loop {
+                github_interval.tick().await;
+                match mods::github_releases::check_github_releases(db_clone_4.clone(), &http_client_3, github_token.as_deref(), &http_clone_3).await {
+                    Ok(()) => info!("Checked GitHub releases"),
+                    Err(error) => error!("Error while checking GitHub releases: {error}"),
+                };
+            }
+        });
+    }
+
+    let mut cache_update_interval = intervals::jittered_interval("cache refresh", intervals.cache_refresh, false);
     tokio::spawn(async move {
         loop {
             cache_update_interval.tick().await;
-            match update_mod_cache(mods_cache.clone(), db.clone()).await {
-                Ok(()) => info!("Updated mod cache"),
-                Err(error) => error!("Error while updating mod cache: {error}"),
-            };
             match update_faq_cache(faq_cache.clone(), db.clone()).await {
                 Ok(()) => info!("Updated faq cache"),
                 Err(error) => error!("Error while updating faq cache: {error}"),
             };
-            match update_sub_cache(subscription_cache.clone(), db.clone()).await {
-                Ok(()) => info!("Updated subscription cache"),
-                Err(error) => error!("Error while updating subscription cache: {error}"),
-            };
-            match update_author_cache(authorname_cache.clone(), db.clone()).await {
-                Ok(()) => info!("Updated subscription cache"),
-                Err(error) => error!("Error while updating author name cache: {error}"),
+            // Mod search uses the local database, which is also useful when it is no longer updated
+            match update_mod_cache(mods_cache.clone(), db.clone()).await {
+                Ok(()) => info!("Updated mod cache"),
+                Err(error) => error!("Error while updating mod cache: {error}"),
             };
+            if mod_updates_enabled {
+                match update_sub_cache(subscription_cache.clone(), db.clone()).await {
+                    Ok(()) => info!("Updated subscription cache"),
+                    Err(error) => error!("Error while updating subscription cache: {error}"),
+                };
+                match update_author_cache(authorname_cache.clone(), db.clone()).await {
+                    Ok(()) => info!("Updated subscription cache"),
+                    Err(error) => error!("Error while updating author name cache: {error}"),
+                };
+            }
             info!("Caches updated");
         };
     });
 
-    let mut api_update_interval = time::interval(time::Duration::from_secs(60*60*24));  // Update once per day
-    api_update_interval.tick().await;   // First tick happens instantly
+    // The API was just downloaded during startup
+    let mut api_update_interval = intervals::jittered_interval("API refresh", intervals.api_refresh, true);
     tokio::spawn(async move {
+        // Retry more often until the docs could be downloaded once
+        while !api_loaded {
+            warn!("Running without (part of) the modding API docs, retrying in {}s", API_RETRY_INTERVAL.as_secs());
+            time::sleep(API_RETRY_INTERVAL).await;
+            api_loaded = update_api_caches(&http_client, &runtime_api_cache, &runtime_api_validators, &data_api_cache, &data_api_validators).await;
+        }
         loop {
             api_update_interval.tick().await;
-            match modding_api::runtime::update_api_cache(runtime_api_cache.clone()).await {
-                Ok(()) => info!("Updated API cache"),
-                Err(error) => error!("Error while updating runtime api cache: {error}"),
-            };
-            match modding_api::data::update_api_cache(data_api_cache.clone()).await {
-                Ok(()) => info!("Updated API cache"),
-                Err(error) => error!("Error whille updating data api cache: {error}")
-            }
+            update_api_caches(&http_client, &runtime_api_cache, &runtime_api_validators, &data_api_cache, &data_api_validators).await;
         };
     });
 
@@ -1,42 +1,86 @@
+use log::{error, warn};
 use poise::serenity_prelude as serenity;
 
 use crate::{
     Context,
-    custom_errors::CustomError,
     Error,
 };
 
-#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+/// Command check for administrators and members with the server's mod role.
+/// The member comes with the command and the guild and channel from the cache, so this rarely needs a request.
 pub async fn is_mod(ctx: Context<'_>) -> Result<bool, Error> {
-
-    let Some(channel) = &ctx.guild_channel().await
-        else {return Ok(false)} ;
-    let Some(member) = &ctx.author_member().await
+    let Some(channel) = ctx.guild_channel().await
+        else {return Ok(false)};
+    let Some(member) = ctx.author_member().await
         else {return Ok(false)};
     let Some(guild) = ctx.partial_guild().await
         else {return Ok(false)};
-    let user_permissions = guild.user_permissions_in(channel, member);
-    if user_permissions.contains(serenity::Permissions::ADMINISTRATOR) {
+    if guild.user_permissions_in(&channel, &member).contains(serenity::Permissions::ADMINISTRATOR) {
         return Ok(true);
+    }
+    Ok(has_mod_role(&ctx.data().database, guild.id, &member.roles).await)
+}
+
+/// Check whether a server member is an administrator or has the mod role, outside of commands.
+/// Uses the cache where possible, failed lookups count as not being a moderator.
+pub async fn user_is_mod(
+    ctx: &serenity::Context, 
+    db: &sqlx::SqlitePool, 
+    guild_id: serenity::GuildId, 
+    channel_id: serenity::ChannelId, 
+    user_id: serenity::UserId,
+) -> Result<bool, Error> {
+    let cached = ctx.cache.guild(guild_id).and_then(|guild| {
+        let member = guild.members.get(&user_id)?;
+        let channel = guild.channels.get(&channel_id)
+            .or_else(|| guild.threads.iter().find(|t| t.id == channel_id))?;
+        Some((guild.user_permissions_in(channel, member), member.roles.clone()))
+    });
+    let (permissions, roles) = match cached {
+        Some(cached) => cached,
+        None => match fetch_member_permissions(ctx, guild_id, channel_id, user_id).await {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => return Ok(false),
+            Err(e) => {
+                warn!("Could not check moderator status of user {user_id} in server {guild_id}: {e}");
+                return Ok(false)
+            },
+        },
     };
-    let db = &ctx.data().database;
-    let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
-    };
-    let server_id = server.get() as i64;
-    let modrole = match sqlx::query!(r#"SELECT modrole FROM servers WHERE server_id = $1"#, server_id)
-        .fetch_one(db)
+    if permissions.contains(serenity::Permissions::ADMINISTRATOR) {
+        return Ok(true);
+    }
+    Ok(has_mod_role(db, guild_id, &roles).await)
+}
+
+/// Whether one of the roles is the server's mod role. Database errors are logged and count as no.
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+async fn has_mod_role(db: &sqlx::SqlitePool, guild_id: serenity::GuildId, roles: &[serenity::RoleId]) -> bool {
+    let server_id = guild_id.get() as i64;
+    match sqlx::query!(r#"SELECT modrole FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
         .await {
-            Ok(role) => {match role.modrole {
-                Some(role) => role,
-                None => {
-                    return Ok(false)
-                },
-            }},
-            Err(_) => {
-                return Ok(false)
+            Ok(record) => record
+                .and_then(|r| r.modrole)
+                .is_some_and(|role| roles.contains(&serenity::RoleId::new(role as u64))),
+            Err(e) => {
+                error!("Error reading mod role of server {server_id}: {e}");
+                false
             },
-        };
-    let has_role = ctx.author().has_role(ctx.http(), server, serenity::RoleId::from(modrole as u64)).await?;
-    Ok(has_role)
-}
\ No newline at end of file
+        }
+}
+
+/// Permissions and roles of a member that is not in the cache. None if the channel is not in a server.
+async fn fetch_member_permissions(
+    ctx: &serenity::Context, 
+    guild_id: serenity::GuildId, 
+    channel_id: serenity::ChannelId, 
+    user_id: serenity::UserId,
+) -> Result<Option<(serenity::Permissions, Vec<serenity::RoleId>)>, Error> {
+    let guild = guild_id.to_partial_guild(ctx).await?;
+    let member = guild.member(ctx, user_id).await?;
+    let Some(channel) = channel_id.to_channel(ctx).await?.guild() else {
+        return Ok(None)
+    };
+    Ok(Some((guild.user_permissions_in(&channel, &member), member.roles.clone())))
+}
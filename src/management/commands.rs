@@ -1,10 +1,17 @@
 use poise::serenity_prelude as serenity;
 use poise::CreateReply;
+use std::time::Instant;
 
 use crate::{
     Context,
+    custom_errors::CustomError,
     Error,
-    management::{get_server_id, checks::is_mod},
+    faq_commands::update_faq_cache,
+    formatting_tools::{format_duration, format_thousands, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT},
+    management::{get_bot_permissions, get_server_id, missing_update_permissions, checks::is_mod, DEFAULT_PREFIX, MAX_PREFIX_LENGTH},
+    modding_api,
+    mods::{get_subscribed_authors, get_subscribed_mods},
+    mods::update_notifications::{get_mod_count, update_author_cache, update_mod_cache, update_sub_cache, DEFAULT_CHANGELOG_LENGTH},
 };
 
 /// Remove all stored data for this server, resetting all settings.
@@ -17,20 +24,151 @@ pub async fn reset_server_settings(
     sqlx::query!(r#"DELETE FROM servers WHERE server_id = $1"#, server_id)
         .execute(db)
         .await?;
+    ctx.data().prefix_cache.remove(&server_id);
+    ctx.data().inline_settings_cache.remove(&server_id);
+    ctx.data().delete_commands_cache.remove(&server_id);
+    ctx.data().private_lookups_cache.remove(&server_id);
     ctx.say("Server data reset").await?;
     Ok(())
 }
 
+/// Set the prefix for text commands on this server. Leave empty to reset to the default.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_prefix(
+    ctx: Context<'_>,
+    #[description = "New command prefix"]
+    prefix: Option<String>,
+) -> Result<(), Error> {
+    let prefix = prefix.map(|p| p.trim().to_owned()).filter(|p| p != DEFAULT_PREFIX);
+    if let Some(p) = &prefix {
+        if p.is_empty() || p.chars().count() > MAX_PREFIX_LENGTH || p.contains(char::is_whitespace) {
+            return Err(Box::new(CustomError::new(&format!("Prefix must be 1 to {MAX_PREFIX_LENGTH} characters long and can not contain spaces"))));
+        }
+    }
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET prefix = $1 WHERE server_id = $2"#,
+            prefix, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, prefix) VALUES ($1, $2)"#,
+            server_id, prefix)
+            .execute(db)
+            .await?;
+        },
+    };
+    let prefix = prefix.unwrap_or_else(|| DEFAULT_PREFIX.to_owned());
+    ctx.data().prefix_cache.insert(server_id, prefix.clone());
+    ctx.say(format!("Command prefix set to `{prefix}`")).await?;
+    Ok(())
+}
+
+/// Show the results of API and wiki slash commands only to the user who used them, to keep busy channels readable.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", rename = "privatelookups", aliases("private-lookups", "private_lookups"))]
+pub async fn set_private_lookups(
+    ctx: Context<'_>,
+    #[description = "Whether API and wiki lookups are only shown to the user who asked for them"]
+    private: bool,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET private_lookups = $1 WHERE server_id = $2"#,
+            private, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, private_lookups) VALUES ($1, $2)"#,
+            server_id, private)
+            .execute(db)
+            .await?;
+        },
+    };
+    ctx.data().private_lookups_cache.insert(server_id, private);
+    if private {
+        ctx.say("API and wiki slash commands will now only be shown to the user who used them. Text commands are always public.").await?;
+    } else {
+        ctx.say("API and wiki slash commands will now be shown to everyone.").await?;
+    }
+    Ok(())
+}
+
+/// Delete the messages of text commands after responding to them, to keep channels clean. Needs the Manage Messages permission.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", rename = "deletecommands", aliases("delete-commands", "delete_commands"))]
+pub async fn set_delete_commands(
+    ctx: Context<'_>,
+    #[description = "Whether messages with text commands are deleted after the bot responded"]
+    delete: bool,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET delete_commands = $1 WHERE server_id = $2"#,
+            delete, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, delete_commands) VALUES ($1, $2)"#,
+            server_id, delete)
+            .execute(db)
+            .await?;
+        },
+    };
+    ctx.data().delete_commands_cache.insert(server_id, delete);
+    if delete {
+        ctx.say("Messages with text commands will be deleted after the bot responded, in channels where it has the Manage Messages permission.").await?;
+    } else {
+        ctx.say("Messages with text commands will no longer be deleted.").await?;
+    }
+    Ok(())
+}
+
 /// Print bot info
 #[poise::command(prefix_command, slash_command, install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
 pub async fn info(
     ctx: Context<'_>
 ) -> Result<(), Error> {
+    let data = ctx.data();
+    let faq_count = match data.faq_cache.read() {
+        Ok(c) => c.len(),
+        Err(e) => return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+    };
+    let api_version = match data.runtime_api_cache.read() {
+        Ok(c) => c.application_version.clone(),
+        Err(e) => return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+    };
+    let mod_count = get_mod_count(data.database.clone()).await;
     let embed = serenity::CreateEmbed::new()
         .title("ρBot")
         .field("Creator", "SpeckledFleebeedoo (<@247640901805932544>)", false)
         .field("Source", "[GitHub](https://www.github.com/SpeckledFleebeedoo/rhobot)", true)
-        .field("Invite link", "[Invite](https://discord.com/api/oauth2/authorize?client_id=872540831599456296&permissions=274877925376&scope=bot%20applications.commands)", true);
+        .field("Invite link", "[Invite](https://discord.com/api/oauth2/authorize?client_id=872540831599456296&permissions=274877925376&scope=bot%20applications.commands)", true)
+        .field("Version", env!("CARGO_PKG_VERSION"), true)
+        .field("Uptime", format_duration(data.start_time.elapsed()), true)
+        .field("Servers", ctx.cache().guild_count().to_string(), true)
+        .field("Mods", format_thousands(i64::from(mod_count)), true)
+        .field("FAQ entries", faq_count.to_string(), true)
+        .field("Factorio API version", api_version, true);
     let builder = CreateReply::default().embed(embed);
     ctx.send(builder).await?;
     Ok(())
@@ -44,15 +182,99 @@ pub async fn help(
     #[autocomplete = "poise::builtins::autocomplete_command"]
     command: Option<String>,
 ) -> Result<(), Error> {
-    poise::builtins::help(
-        ctx,
-        command.as_deref(),
-        poise::builtins::HelpConfiguration::default(),
-    )
-    .await?;
+    let commands = &ctx.framework().options().commands;
+    let embed = match command {
+        Some(query) => {
+            let Some(command) = find_help_command(commands, &query) else {
+                return Err(Box::new(CustomError::new(&format!("No command named {query}"))));
+            };
+            command_help_embed(command)
+        },
+        None => help_overview_embed(commands),
+    };
+    let builder = CreateReply::default().embed(embed).ephemeral(true);
+    ctx.send(builder).await?;
     Ok(())
 }
 
+type Command = poise::Command<crate::Data, Error>;
+
+/// Finds a command by name or alias. Subcommands are found by separating their names with spaces.
+fn find_help_command<'a>(commands: &'a [Command], query: &str) -> Option<&'a Command> {
+    let mut words = query.split_whitespace();
+    let first = words.next()?.trim_start_matches(['/', '+']).to_lowercase();
+    let mut command = commands.iter()
+        .find(|c| c.name == first || c.aliases.iter().any(|a| *a == first))?;
+    for word in words {
+        let word = word.to_lowercase();
+        command = command.subcommands.iter()
+            .find(|c| c.name == word || c.aliases.iter().any(|a| *a == word))?;
+    }
+    Some(command)
+}
+
+/// Lists all visible commands, grouped by their category.
+fn help_overview_embed(commands: &[Command]) -> serenity::CreateEmbed {
+    let mut categories: Vec<(&str, Vec<String>)> = Vec::new();
+    for command in commands.iter().filter(|c| !c.hide_in_help && (c.slash_action.is_some() || c.prefix_action.is_some())) {
+        let category = command.category.as_deref().unwrap_or("General");
+        let line = format!("`{}` {}", command.name, command.description.as_deref().unwrap_or_default());
+        if let Some((_, lines)) = categories.iter_mut().find(|(name, _)| *name == category) {
+            lines.push(line);
+        } else {
+            categories.push((category, vec![line]));
+        }
+    }
+    let mut embed = serenity::CreateEmbed::new()
+        .title("ρBot commands")
+        .color(serenity::Colour::GOLD)
+        .footer(serenity::CreateEmbedFooter::new("Use /help <command> to show details of a command"));
+    for (category, lines) in categories {
+        embed = embed.field(category, lines.join("\n").truncate_for_embed(EMBED_FIELD_LIMIT), false);
+    }
+    embed
+}
+
+/// Shows the description, parameters and subcommands of a single command.
+fn command_help_embed(command: &Command) -> serenity::CreateEmbed {
+    let description = [command.description.as_deref(), command.help_text.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<&str>>()
+        .join("\n\n");
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("/{}", command.qualified_name))
+        .description(if description.is_empty() { "No description".to_owned() } else { description.truncate_for_embed(EMBED_DESCRIPTION_LIMIT) })
+        .color(serenity::Colour::GOLD);
+    if !command.parameters.is_empty() {
+        let parameters = command.parameters.iter()
+            .map(|p| format!(
+                "`{}`{} {}",
+                p.name,
+                if p.required { "" } else { " (optional)" },
+                p.description.as_deref().unwrap_or_default()
+            ))
+            .collect::<Vec<String>>()
+            .join("\n");
+        embed = embed.field("Parameters", parameters.truncate_for_embed(EMBED_FIELD_LIMIT), false);
+    }
+    if !command.subcommands.is_empty() {
+        let subcommands = command.subcommands.iter()
+            .filter(|c| !c.hide_in_help)
+            .map(|c| format!("`{}` {}", c.name, c.description.as_deref().unwrap_or_default()))
+            .collect::<Vec<String>>()
+            .join("\n");
+        embed = embed.field("Subcommands", subcommands.truncate_for_embed(EMBED_FIELD_LIMIT), false);
+    }
+    if !command.aliases.is_empty() {
+        embed = embed.field("Aliases", command.aliases.join(", "), false);
+    }
+    if let Some(category) = &command.category {
+        embed = embed.field("Category", category, true);
+    }
+    embed
+}
+
 /// Show stored information about this server
 #[poise::command(prefix_command, slash_command, guild_only, ephemeral, category="Settings")]
 pub async fn get_server_info(
@@ -69,8 +291,19 @@ pub async fn get_server_info(
             let updates_channel = data.updates_channel.map_or_else(|| "Not set".to_owned(), |ch| format!("<#{ch}>"));
             let modrole = data.modrole.map_or_else(|| "Not set".to_owned(), |role| format!("<@&{role}>"));
             let show_changelog = data.show_changelog.map_or_else(|| "Not set (default to true)".to_owned(), |b| b.to_string());
-            let response = format!("**Stored information for this server:**\nServer ID: {:?}\nUpdates channel: {}\nmodrole: {}\nShow changelogs: {}",
-                data.server_id, updates_channel, modrole, show_changelog);
+            let changelog_length = data.changelog_length.map_or_else(|| format!("Not set (default to {DEFAULT_CHANGELOG_LENGTH})"), |l| l.to_string());
+            let updates_webhook = if data.updates_webhook.is_some() { "Set" } else { "Not set" };
+            let update_mode = data.digest_interval.map_or_else(|| "Live".to_owned(), |h| format!("Digest every {h} hours"));
+            let prefix = data.prefix.map_or_else(|| format!("Not set (default to {DEFAULT_PREFIX})"), |p| format!("`{p}`"));
+            let wiki_language = data.wiki_language.unwrap_or_else(|| "Not set (default to en)".to_owned());
+            let wiki_delimiters = data.wiki_delimiters.map_or_else(|| "Not set (default to [[ ]])".to_owned(), |d| format!("`{d}`"));
+            let mod_delimiters = data.mod_delimiters.map_or_else(|| "Not set (default to >> <<)".to_owned(), |d| format!("`{d}`"));
+            let mod_version = data.mod_version.unwrap_or_else(|| "Not set (default to all versions)".to_owned());
+            let faq_public = if data.faq_public { "Public" } else { "Private" };
+            let private_lookups = if data.private_lookups { "Private" } else { "Public" };
+            let delete_commands = if data.delete_commands { "Delete" } else { "Keep" };
+            let response = format!("**Stored information for this server:**\nServer ID: {:?}\nUpdates channel: {}\nUpdates webhook: {}\nmodrole: {}\nShow changelogs: {}\nChangelog length: {}\nUpdate mode: {}\nPrefix: {}\nWiki language: {}\nInline wiki search: {}\nInline mod search: {}\nMod version filter: {}\nHTTP API (FAQ and mod feed): {}\nAPI and wiki lookups: {}\nText command messages: {}",
+                data.server_id, updates_channel, updates_webhook, modrole, show_changelog, changelog_length, update_mode, prefix, wiki_language, wiki_delimiters, mod_delimiters, mod_version, faq_public, private_lookups, delete_commands);
             ctx.say(response).await?;
         },
         None => {
@@ -78,4 +311,148 @@ pub async fn get_server_info(
         },
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Register slash commands again after they were changed
+#[poise::command(prefix_command, slash_command, owners_only, hide_in_help, category="Management")]
+pub async fn register(
+    ctx: Context<'_>,
+    #[description = "Only register in this server, which takes effect immediately"]
+    server_only: Option<bool>,
+) -> Result<(), Error> {
+    let commands = &ctx.framework().options().commands;
+    if server_only.unwrap_or(false) {
+        let Some(guild_id) = ctx.guild_id() else {
+            return Err(Box::new(CustomError::new("Server registration only works in a server")));
+        };
+        poise::builtins::register_in_guild(ctx, commands, guild_id).await?;
+        ctx.say(format!("Registered {} commands in this server", commands.len())).await?;
+    } else {
+        poise::builtins::register_globally(ctx, commands).await?;
+        ctx.say(format!("Registered {} commands globally. It may take up to an hour for changes to show up.", commands.len())).await?;
+    }
+    Ok(())
+}
+
+/// Refresh all caches immediately instead of waiting for the scheduled update
+#[poise::command(prefix_command, slash_command, owners_only, hide_in_help, category="Management")]
+pub async fn refresh_caches(
+    ctx: Context<'_>
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let db = &data.database;
+    let mut results: Vec<(&str, Result<(), Error>, u128)> = Vec::new();
+
+    let start = Instant::now();
+    let res = update_mod_cache(data.mod_cache.clone(), db.clone()).await;
+    results.push(("Mod cache", res, start.elapsed().as_millis()));
+
+    let start = Instant::now();
+    let res = update_faq_cache(data.faq_cache.clone(), db.clone()).await;
+    results.push(("FAQ cache", res, start.elapsed().as_millis()));
+
+    let start = Instant::now();
+    let res = update_sub_cache(data.mod_subscription_cache.clone(), db.clone()).await;
+    results.push(("Subscription cache", res, start.elapsed().as_millis()));
+
+    let start = Instant::now();
+    let res = update_author_cache(data.mod_author_cache.clone(), db.clone()).await;
+    results.push(("Author cache", res, start.elapsed().as_millis()));
+
+    let start = Instant::now();
+    let res = modding_api::runtime::update_api_cache(&data.http_client, data.runtime_api_cache.clone(), data.runtime_api_validators.clone()).await;
+    results.push(("Runtime API cache", res, start.elapsed().as_millis()));
+
+    let start = Instant::now();
+    let res = modding_api::data::update_api_cache(&data.http_client, data.data_api_cache.clone(), data.data_api_validators.clone()).await;
+    results.push(("Data API cache", res, start.elapsed().as_millis()));
+
+    let all_ok = results.iter().all(|(_, res, _)| res.is_ok());
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Cache refresh")
+        .color(if all_ok { serenity::Colour::DARK_GREEN } else { serenity::Colour::RED });
+    for (name, res, elapsed) in results {
+        let value = match res {
+            Ok(()) => format!("Updated in {elapsed} ms"),
+            Err(e) => format!("Failed after {elapsed} ms: {e}"),
+        };
+        embed = embed.field(name, value, false);
+    }
+    let builder = CreateReply::default().embed(embed);
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Check whether mod update notifications are set up correctly for this server
+#[allow(clippy::cast_sign_loss)]
+#[poise::command(prefix_command, slash_command, guild_only, ephemeral, check="is_mod", category="Settings")]
+pub async fn diagnose(
+    ctx: Context<'_>
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let data = ctx.data();
+    let db = &data.database;
+    let mut checks: Vec<(&str, bool, String)> = Vec::new();
+
+    let serverdata = sqlx::query!(r#"SELECT updates_channel, updates_webhook FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    let (updates_channel, updates_webhook) = serverdata
+        .map_or((None, None), |s| (s.updates_channel, s.updates_webhook));
+    let destination_check = if let Some(url) = updates_webhook {
+        match serenity::Webhook::from_url(ctx, &url).await {
+            Ok(_) => (true, "Updates are sent through a webhook, which is reachable".to_owned()),
+            Err(e) => (false, format!("Updates webhook could not be reached: {e}")),
+        }
+    } else if let Some(channel_id) = updates_channel {
+        let channel = serenity::ChannelId::new(channel_id as u64).to_channel(ctx).await.ok().and_then(serenity::Channel::guild);
+        match channel {
+            Some(channel) if channel.thread_metadata.is_some_and(|t| t.locked) => (false, format!("{channel} is a locked thread")),
+            Some(channel) => {
+                let permissions = get_bot_permissions(ctx, &channel).await?;
+                let missing = missing_update_permissions(permissions, channel.thread_metadata.is_some());
+                if missing.is_empty() {
+                    (true, format!("Updates are sent to {channel}"))
+                } else {
+                    (false, format!("Missing permissions in {channel}: {}", missing.join(", ")))
+                }
+            },
+            None => (false, format!("Updates channel <#{channel_id}> no longer exists or is not visible to the bot")),
+        }
+    } else {
+        (false, "No updates channel set, use `set_updates_channel` to choose one".to_owned())
+    };
+    checks.push(("Updates channel", destination_check.0, destination_check.1));
+
+    let subscribed_mods = get_subscribed_mods(db, server_id).await?;
+    let subscribed_authors = get_subscribed_authors(db, server_id).await?;
+    let subscriptions = if subscribed_mods.is_empty() && subscribed_authors.is_empty() {
+        "No subscriptions, updates for all mods are sent".to_owned()
+    } else {
+        format!("Subscribed to {} mods and {} authors", subscribed_mods.len(), subscribed_authors.len())
+    };
+    checks.push(("Subscriptions", true, subscriptions));
+
+    let mod_count = match data.mod_cache.read() {
+        Ok(cache) => cache.len(),
+        Err(e) => return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+    };
+    let cache_check = if mod_count > 0 {
+        (true, format!("{mod_count} mods cached"))
+    } else {
+        (false, "Mod cache is empty, updates can not be checked yet".to_owned())
+    };
+    checks.push(("Mod cache", cache_check.0, cache_check.1));
+
+    let all_ok = checks.iter().all(|(_, ok, _)| *ok);
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Update notification diagnostics")
+        .color(if all_ok { serenity::Colour::DARK_GREEN } else { serenity::Colour::RED });
+    for (name, ok, value) in checks {
+        let status = if ok { "✅" } else { "❌" };
+        embed = embed.field(format!("{status} {name}"), value, false);
+    }
+    let builder = CreateReply::default().embed(embed);
+    ctx.send(builder).await?;
+    Ok(())
+}
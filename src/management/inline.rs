@@ -0,0 +1,397 @@
+use std::sync::Arc;
+use log::error;
+use poise::ChoiceParameter;
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId};
+use regex::Regex;
+
+use crate::{
+    Context,
+    custom_errors::CustomError,
+    Data,
+    Error,
+    management::{get_server_id, checks::is_mod},
+    wiki_commands::get_wiki_language,
+};
+
+pub const MAX_DELIMITER_LENGTH: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum InlineTrigger {
+    Wiki,
+    Mod,
+}
+
+/// Opening and closing text around an inline command, e.g. `[[` and `]]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delimiters {
+    pub open: String,
+    pub close: String,
+}
+
+impl Delimiters {
+    fn new(open: &str, close: &str) -> Self {
+        Self { open: open.to_owned(), close: close.to_owned() }
+    }
+
+    /// Parse delimiters stored as "open close", falling back to the default if unset or invalid.
+    fn parse_or(stored: Option<&str>, default: Self) -> Self {
+        stored.and_then(|s| s.split_once(' '))
+            .map_or(default, |(open, close)| Self::new(open, close))
+    }
+
+    fn to_stored(&self) -> String {
+        format!("{} {}", self.open, self.close)
+    }
+
+    /// Regex capturing the text between the delimiters.
+    pub fn regex(&self) -> Result<Regex, regex::Error> {
+        Regex::new(&format!("{}(.*?){}", regex::escape(&self.open), regex::escape(&self.close)))
+    }
+
+    /// Regex matching delimiters inside inline code, which should not trigger a response.
+    pub fn code_regex(&self) -> Result<Regex, regex::Error> {
+        Regex::new(&format!(r"\`[\S\s]*?{}(.*?){}[\S\s]*?\`", regex::escape(&self.open), regex::escape(&self.close)))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for delimiter in [&self.open, &self.close] {
+            if delimiter.is_empty() || delimiter.chars().count() > MAX_DELIMITER_LENGTH {
+                return Err(format!("Delimiters must be 1 to {MAX_DELIMITER_LENGTH} characters long"));
+            }
+            if delimiter.contains(char::is_whitespace) || delimiter.contains('`') {
+                return Err("Delimiters can not contain spaces or backticks".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Delimiters conflict if one opening could be mistaken for the other.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        self.open.contains(&other.open) || other.open.contains(&self.open)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineDelimiters {
+    pub wiki: Delimiters,
+    pub mods: Delimiters,
+}
+
+impl Default for InlineDelimiters {
+    fn default() -> Self {
+        Self {
+            wiki: Delimiters::new("[[", "]]"),
+            mods: Delimiters::new(">>", "<<"),
+        }
+    }
+}
+
+/// Get the inline command delimiters of a server, or the defaults outside of servers.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn get_inline_delimiters(db: &sqlx::SqlitePool, guild_id: Option<GuildId>) -> InlineDelimiters {
+    let default = InlineDelimiters::default();
+    let Some(guild_id) = guild_id else {
+        return default
+    };
+    let server_id = guild_id.get() as i64;
+    match sqlx::query!(r#"SELECT wiki_delimiters, mod_delimiters FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await {
+        Ok(Some(record)) => InlineDelimiters {
+            wiki: Delimiters::parse_or(record.wiki_delimiters.as_deref(), default.wiki),
+            mods: Delimiters::parse_or(record.mod_delimiters.as_deref(), default.mods),
+        },
+        Ok(None) => default,
+        Err(e) => {
+            error!("Error reading inline delimiters of server {server_id}: {e}");
+            default
+        },
+    }
+}
+
+/// Set the text around inline wiki or mod searches on this server. Leave empty to reset to the default.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_inline_delimiters(
+    ctx: Context<'_>,
+    #[description = "Inline search to change"]
+    trigger: InlineTrigger,
+    #[description = "Text before the search, e.g. [["]
+    open: Option<String>,
+    #[description = "Text after the search, e.g. ]]"]
+    close: Option<String>,
+) -> Result<(), Error> {
+    let db = &ctx.data().database;
+    let current = get_inline_delimiters(db, ctx.guild_id()).await;
+    let default = InlineDelimiters::default();
+    let (previous, other, default) = match trigger {
+        InlineTrigger::Wiki => (current.wiki, current.mods, default.wiki),
+        InlineTrigger::Mod => (current.mods, current.wiki, default.mods),
+    };
+    let delimiters = match (open, close) {
+        (None, None) => default.clone(),
+        (Some(open), Some(close)) => Delimiters::new(open.trim(), close.trim()),
+        _ => return Err(Box::new(CustomError::new("Give both the opening and closing delimiter, or neither to reset them"))),
+    };
+    delimiters.validate().map_err(|e| CustomError::new(&e))?;
+    if delimiters.conflicts_with(&other) {
+        return Err(Box::new(CustomError::new(&format!("`{}` conflicts with the other inline search, which starts with `{}`", delimiters.open, other.open))));
+    }
+    if delimiters == previous {
+        ctx.say(format!("Inline {} searches already use `{}search{}`", trigger.name().to_lowercase(), delimiters.open, delimiters.close)).await?;
+        return Ok(());
+    }
+    let stored = (delimiters != default).then(|| delimiters.to_stored());
+    let server_id = get_server_id(ctx)?;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            match trigger {
+                InlineTrigger::Wiki => sqlx::query!(r#"UPDATE servers SET wiki_delimiters = $1 WHERE server_id = $2"#, stored, server_id)
+                    .execute(db)
+                    .await?,
+                InlineTrigger::Mod => sqlx::query!(r#"UPDATE servers SET mod_delimiters = $1 WHERE server_id = $2"#, stored, server_id)
+                    .execute(db)
+                    .await?,
+            };
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            match trigger {
+                InlineTrigger::Wiki => sqlx::query!(r#"INSERT INTO servers (server_id, wiki_delimiters) VALUES ($1, $2)"#, server_id, stored)
+                    .execute(db)
+                    .await?,
+                InlineTrigger::Mod => sqlx::query!(r#"INSERT INTO servers (server_id, mod_delimiters) VALUES ($1, $2)"#, server_id, stored)
+                    .execute(db)
+                    .await?,
+            };
+        },
+    };
+    ctx.data().inline_settings_cache.remove(&server_id);
+    ctx.say(format!("Inline {} searches now use `{}search{}`", trigger.name().to_lowercase(), delimiters.open, delimiters.close)).await?;
+    Ok(())
+}
+
+/// Check whether inline searches are processed in a channel. Denied channels are always blocked,
+/// and once any channel is allowed only allowed channels are processed.
+fn is_channel_allowed(channel_lists: &[(i64, bool)], channel_id: i64) -> bool {
+    match channel_lists.iter().find(|(id, _)| *id == channel_id) {
+        Some((_, allowed)) => *allowed,
+        None => !channel_lists.iter().any(|(_, allowed)| *allowed),
+    }
+}
+
+/// Compiled regexes of one kind of inline search.
+#[derive(Debug, Clone)]
+pub struct InlinePattern {
+    delimiters: Delimiters,
+    regex: Regex,
+    code_regex: Regex,
+}
+
+impl InlinePattern {
+    fn new(delimiters: Delimiters) -> Result<Self, regex::Error> {
+        Ok(Self { regex: delimiters.regex()?, code_regex: delimiters.code_regex()?, delimiters })
+    }
+
+    /// The text of the first inline search in a message, unless it is inside inline code.
+    pub fn search(&self, message_content: &str) -> Option<String> {
+        // Most messages don't contain the delimiters, so skip the regexes for those
+        if !message_content.contains(&self.delimiters.open) || self.code_regex.is_match(message_content) {
+            return None
+        }
+        self.regex.captures(message_content).map(|c| c[1].to_owned())
+    }
+}
+
+/// Inline search settings of a server, cached so messages can be checked without database queries.
+#[derive(Debug, Clone)]
+pub struct InlineSettings {
+    pub wiki: InlinePattern,
+    pub mods: InlinePattern,
+    pub wiki_language: Option<String>,
+    channel_lists: Vec<(i64, bool)>,
+}
+
+impl InlineSettings {
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn allowed_in(&self, channel_id: ChannelId) -> bool {
+        is_channel_allowed(&self.channel_lists, channel_id.get() as i64)
+    }
+}
+
+/// Get the inline search settings of a server, reading them from the database the first time.
+/// Outside of servers the defaults are used and inline searches are always allowed.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn get_inline_settings(data: &Data, guild_id: Option<GuildId>) -> Result<Arc<InlineSettings>, Error> {
+    // Server ID 0 doesn't exist and holds the defaults
+    let server_id = guild_id.map_or(0, |g| g.get() as i64);
+    if let Some(settings) = data.inline_settings_cache.get(&server_id) {
+        return Ok(settings.clone())
+    }
+    let db = &data.database;
+    let delimiters = get_inline_delimiters(db, guild_id).await;
+    let channel_lists = match guild_id {
+        Some(_) => sqlx::query!(r#"SELECT channel_id, allowed FROM inline_channels WHERE server_id = $1"#, server_id)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|r| (r.channel_id, r.allowed))
+            .collect(),
+        None => vec![],
+    };
+    let settings = Arc::new(InlineSettings {
+        wiki: InlinePattern::new(delimiters.wiki)?,
+        mods: InlinePattern::new(delimiters.mods)?,
+        wiki_language: get_wiki_language(db, guild_id).await,
+        channel_lists,
+    });
+    data.inline_settings_cache.insert(server_id, settings.clone());
+    Ok(settings)
+}
+
+#[allow(clippy::cast_possible_wrap)]
+async fn set_inline_channel(ctx: Context<'_>, channel: &serenity::GuildChannel, allowed: bool) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let channel_id = channel.id.get() as i64;
+    sqlx::query!(r#"INSERT OR REPLACE INTO inline_channels (server_id, channel_id, allowed) VALUES ($1, $2, $3)"#, server_id, channel_id, allowed)
+        .execute(&ctx.data().database)
+        .await?;
+    ctx.data().inline_settings_cache.remove(&server_id);
+    Ok(())
+}
+
+/// Limit in which channels inline wiki and mod searches work
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", subcommands("allow_inline_channel", "deny_inline_channel", "remove_inline_channel", "list_inline_channels"), subcommand_required)]
+pub async fn inline_channels(
+    _ctx: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Allow inline searches in a channel. Once a channel is allowed, all other channels are blocked.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="allow")]
+pub async fn allow_inline_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to allow inline searches in"]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    set_inline_channel(ctx, &channel, true).await?;
+    ctx.say(format!("Inline searches are allowed in {channel}. Channels that are not allowed will not respond to inline searches.")).await?;
+    Ok(())
+}
+
+/// Block inline searches in a channel
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="deny")]
+pub async fn deny_inline_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to block inline searches in"]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    set_inline_channel(ctx, &channel, false).await?;
+    ctx.say(format!("Inline searches are blocked in {channel}")).await?;
+    Ok(())
+}
+
+/// Remove a channel from the allow or deny list
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="remove")]
+pub async fn remove_inline_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to remove from the lists"]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let channel_id = channel.id.get() as i64;
+    let removed = sqlx::query!(r#"DELETE FROM inline_channels WHERE server_id = $1 AND channel_id = $2"#, server_id, channel_id)
+        .execute(&ctx.data().database)
+        .await?
+        .rows_affected();
+    ctx.data().inline_settings_cache.remove(&server_id);
+    if removed == 0 {
+        ctx.say(format!("{channel} is not on the allow or deny list")).await?;
+    } else {
+        ctx.say(format!("Removed {channel} from the inline search channel lists")).await?;
+    }
+    Ok(())
+}
+
+/// Show the channels where inline searches are allowed or blocked
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="list")]
+pub async fn list_inline_channels(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let records = sqlx::query!(r#"SELECT channel_id, allowed FROM inline_channels WHERE server_id = $1"#, server_id)
+        .fetch_all(&ctx.data().database)
+        .await?;
+    let format_channels = |allowed: bool| records.iter()
+        .filter(|r| r.allowed == allowed)
+        .map(|r| format!("<#{}>", r.channel_id))
+        .collect::<Vec<String>>();
+    let allowed = format_channels(true);
+    let denied = format_channels(false);
+    let response = match (allowed.is_empty(), denied.is_empty()) {
+        (true, true) => "Inline searches work in all channels".to_owned(),
+        (false, _) => format!("Inline searches only work in: {}", allowed.join(", ")),
+        (true, false) => format!("Inline searches work everywhere except: {}", denied.join(", ")),
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_delimiters() {
+        let default = InlineDelimiters::default().wiki;
+        assert_eq!(Delimiters::parse_or(None, default.clone()), default);
+        assert_eq!(Delimiters::parse_or(Some("invalid"), default.clone()), default);
+        let custom = Delimiters::parse_or(Some("{{ }}"), default);
+        assert_eq!(custom, Delimiters::new("{{", "}}"));
+        assert_eq!(custom.to_stored(), "{{ }}");
+    }
+
+    #[test]
+    fn validation() {
+        assert!(Delimiters::new("((", "))").validate().is_ok());
+        assert!(Delimiters::new("", "]]").validate().is_err());
+        assert!(Delimiters::new("[[", "]]]]]]").validate().is_err());
+        assert!(Delimiters::new("` ", "]]").validate().is_err());
+
+        let mods = InlineDelimiters::default().mods;
+        assert!(Delimiters::new(">", "<").conflicts_with(&mods));
+        assert!(Delimiters::new(">>>", "<<<").conflicts_with(&mods));
+        assert!(!Delimiters::new("{{", "}}").conflicts_with(&mods));
+    }
+
+    #[test]
+    fn channel_lists() {
+        assert!(is_channel_allowed(&[], 1));
+        assert!(is_channel_allowed(&[(2, false)], 1));
+        assert!(!is_channel_allowed(&[(1, false)], 1));
+        assert!(is_channel_allowed(&[(1, true), (2, false)], 1));
+        assert!(!is_channel_allowed(&[(1, true)], 3));
+    }
+
+    #[test]
+    fn regexes() {
+        let delimiters = Delimiters::new("((", "))");
+        let captures = delimiters.regex().unwrap().captures("see ((iron plate)) here").unwrap();
+        assert_eq!(&captures[1], "iron plate");
+        assert!(delimiters.code_regex().unwrap().is_match("`((iron plate))`"));
+        assert!(!delimiters.code_regex().unwrap().is_match("((iron plate))"));
+    }
+
+    #[test]
+    fn pattern_search() {
+        let pattern = InlinePattern::new(Delimiters::new("[[", "]]")).unwrap();
+        assert_eq!(pattern.search("see [[iron plate]] here").as_deref(), Some("iron plate"));
+        assert_eq!(pattern.search("no search here"), None);
+        assert_eq!(pattern.search("`[[iron plate]]`"), None);
+    }
+}
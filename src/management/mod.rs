@@ -1,16 +1,182 @@
 pub mod checks;
 pub mod commands;
+pub mod inline;
+pub mod reports;
+
+use log::{debug, error};
+use poise::serenity_prelude as serenity;
 
 use crate::{
     Context,
+    Data,
     Error,
     custom_errors::CustomError,
+    database,
 };
 
+pub const DEFAULT_PREFIX: &str = "+";
+pub const MAX_PREFIX_LENGTH: usize = 5;
+
+/// Only offers server specific commands in servers the bot has been added to.
+/// Without this, Discord also lists them in DMs and for user installs, where they can only fail.
+pub fn restrict_guild_only_commands(commands: &mut [poise::Command<Data, Error>]) {
+    for command in commands.iter_mut().filter(|c| c.guild_only) {
+        command.install_context = Some(vec![serenity::InstallationContext::Guild]);
+        command.interaction_context = Some(vec![serenity::InteractionContext::Guild]);
+    }
+}
+
+/// Whether the response to a lookup should only be shown to the user who asked for it.
+/// Only slash commands can respond privately.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn lookup_is_private(ctx: Context<'_>) -> bool {
+    let (poise::Context::Application(_), Some(guild_id)) = (ctx, ctx.guild_id()) else {
+        return false
+    };
+    let server_id = guild_id.get() as i64;
+    if let Some(private) = ctx.data().private_lookups_cache.get(&server_id) {
+        return *private
+    }
+    match database::lookups_are_private(&ctx.data().database, server_id).await {
+        Ok(private) => {
+            ctx.data().private_lookups_cache.insert(server_id, private);
+            private
+        },
+        Err(e) => {
+            error!("Error reading lookup visibility of server {guild_id}: {e}");
+            false
+        },
+    }
+}
+
+/// Deletes the message that invoked a text command, if the server enabled this and the bot has Manage Messages.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn delete_command_message(ctx: Context<'_>) {
+    let (poise::Context::Prefix(prefix_ctx), Some(guild_id)) = (ctx, ctx.guild_id()) else {
+        return
+    };
+    let server_id = guild_id.get() as i64;
+    let delete = match ctx.data().delete_commands_cache.get(&server_id) {
+        Some(delete) => *delete,
+        None => match database::deletes_command_messages(&ctx.data().database, server_id).await {
+            Ok(delete) => {
+                ctx.data().delete_commands_cache.insert(server_id, delete);
+                delete
+            },
+            Err(e) => {
+                error!("Error reading command deletion setting of server {guild_id}: {e}");
+                return
+            },
+        },
+    };
+    if !delete {
+        return
+    }
+    let Some(channel) = ctx.guild_channel().await else {
+        return
+    };
+    match get_bot_permissions(ctx, &channel).await {
+        Ok(permissions) if permissions.manage_messages() => {},
+        Ok(_) => {
+            debug!("Not deleting command message in channel {}: missing Manage Messages permission", channel.id);
+            return
+        },
+        Err(e) => {
+            error!("Error getting permissions in channel {}: {e}", channel.id);
+            return
+        },
+    }
+    // Forget the message first, otherwise poise deletes the response along with the command once it sees the deletion
+    if let Some(Ok(mut edit_tracker)) = ctx.framework().options().prefix_options.edit_tracker.as_ref().map(|t| t.write()) {
+        edit_tracker.process_message_delete(prefix_ctx.msg.id);
+    }
+    if let Err(e) = prefix_ctx.msg.delete(ctx).await {
+        error!("Error deleting command message in channel {}: {e}", channel.id);
+    }
+}
+
 #[allow(clippy::cast_possible_wrap)]
 pub fn get_server_id(ctx: Context<'_>) -> Result<i64, Error> {
     let Some(server) = ctx.guild_id() else {
         return Err(Box::new(CustomError::new("Could not get server ID")))
     };
     Ok(server.get() as i64)
-}
\ No newline at end of file
+}
+/// Get the bot's permissions in a channel. Threads use the permission overwrites of their parent channel.
+pub async fn get_bot_permissions(ctx: Context<'_>, channel: &serenity::GuildChannel) -> Result<serenity::Permissions, Error> {
+    let Some(guild) = ctx.partial_guild().await else {
+        return Err(Box::new(CustomError::new("Could not get server information")))
+    };
+    let member = guild.member(ctx, ctx.framework().bot_id).await?;
+    let permissions = if channel.thread_metadata.is_some() {
+        let Some(parent_id) = channel.parent_id else {
+            return Err(Box::new(CustomError::new("Could not find the parent channel of this thread")))
+        };
+        let Some(parent) = parent_id.to_channel(ctx).await?.guild() else {
+            return Err(Box::new(CustomError::new("Could not find the parent channel of this thread")))
+        };
+        guild.user_permissions_in(&parent, &member)
+    } else {
+        guild.user_permissions_in(channel, &member)
+    };
+    Ok(permissions)
+}
+
+/// List the permissions the bot lacks to post update messages in a channel or thread.
+pub fn missing_update_permissions(permissions: serenity::Permissions, is_thread: bool) -> Vec<&'static str> {
+    let send_permission = if is_thread {
+        (serenity::Permissions::SEND_MESSAGES_IN_THREADS, "Send Messages in Threads")
+    } else {
+        (serenity::Permissions::SEND_MESSAGES, "Send Messages")
+    };
+    [
+        (serenity::Permissions::VIEW_CHANNEL, "View Channel"),
+        send_permission,
+        (serenity::Permissions::EMBED_LINKS, "Embed Links"),
+    ]
+        .into_iter()
+        .filter(|(permission, _)| !permissions.contains(*permission))
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Get the command prefix of the server a message was sent in. Prefixes are cached after the first lookup.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn get_prefix(ctx: poise::PartialContext<'_, Data, Error>) -> Result<Option<String>, Error> {
+    let Some(guild_id) = ctx.guild_id else {
+        return Ok(Some(DEFAULT_PREFIX.to_owned()))
+    };
+    let server_id = guild_id.get() as i64;
+    if let Some(prefix) = ctx.data.prefix_cache.get(&server_id) {
+        return Ok(Some(prefix.clone()))
+    }
+    let prefix = sqlx::query!(r#"SELECT prefix FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(&ctx.data.database)
+        .await?
+        .and_then(|s| s.prefix)
+        .unwrap_or_else(|| DEFAULT_PREFIX.to_owned());
+    ctx.data.prefix_cache.insert(server_id, prefix.clone());
+    Ok(Some(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guild_only_commands_are_restricted() {
+        let mut commands = vec![
+            crate::faq_commands::faq(),
+            crate::faq_commands::add_to_faq(),
+            crate::mods::commands::subscribe(),
+            crate::wiki_commands::wiki(),
+            crate::mods::commands::find_mod(),
+        ];
+        restrict_guild_only_commands(&mut commands);
+        for command in &commands {
+            let dm_capable = command.interaction_context.as_ref()
+                .is_some_and(|c| c.iter().any(|i| *i != serenity::InteractionContext::Guild));
+            assert_eq!(dm_capable, !command.guild_only, "{} has the wrong interaction contexts", command.name);
+        }
+    }
+}
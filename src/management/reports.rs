@@ -0,0 +1,115 @@
+use poise::serenity_prelude::{Colour, CreateEmbed};
+use poise::CreateReply;
+
+use crate::{
+    Context,
+    custom_errors::CustomError,
+    database,
+    Error,
+    formatting_tools::{DiscordFormat, EMBED_DESCRIPTION_LIMIT},
+    management::{get_server_id, checks::is_mod},
+};
+
+const MAX_TARGET_LENGTH: usize = 100;
+const MAX_MESSAGE_LENGTH: usize = 1000;
+// Open reports a single user can have in a server, so moderators aren't flooded
+const MAX_OPEN_REPORTS: usize = 5;
+
+fn check_report(target: &str, message: &str) -> Result<(), Error> {
+    if target.is_empty() || target.chars().count() > MAX_TARGET_LENGTH {
+        return Err(Box::new(CustomError::new(&format!("What you report must be 1 to {MAX_TARGET_LENGTH} characters long"))));
+    }
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(Box::new(CustomError::new(&format!("Report message must be 1 to {MAX_MESSAGE_LENGTH} characters long"))));
+    }
+    Ok(())
+}
+
+/// Report a broken FAQ entry, or a wiki or mod command that showed something wrong, to the moderators
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only, ephemeral)]
+pub async fn report(
+    ctx: Context<'_>,
+    #[description = "FAQ entry or command that is wrong, for example \"faq install\" or \"wiki belt\""]
+    target: String,
+    #[description = "What is wrong with it"]
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    let (target, message) = (target.trim(), message.trim());
+    check_report(target, message)?;
+    let server_id = get_server_id(ctx)?;
+    let reporter = ctx.author().id.get() as i64;
+    let db = &ctx.data().database;
+    let open_reports = database::get_reports(db, server_id).await?
+        .iter()
+        .filter(|r| r.reporter == reporter)
+        .count();
+    if open_reports >= MAX_OPEN_REPORTS {
+        return Err(Box::new(CustomError::new("You already have several open reports, please wait until the moderators have looked at them")));
+    }
+    database::add_report(db, server_id, reporter, target, message, ctx.created_at().timestamp()).await?;
+    ctx.say("Thanks, your report was sent to the moderators").await?;
+    Ok(())
+}
+
+/// Review problems reported by users
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("list", "dismiss"), subcommand_required, category="Settings")]
+pub async fn reports(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show open reports
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", ephemeral)]
+pub async fn list(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let reports = database::get_reports(&ctx.data().database, server_id).await?;
+    let description = if reports.is_empty() {
+        "There are no open reports.".to_owned()
+    } else {
+        reports.iter()
+            .map(|r| format!("**#{}** {} by <@{}> <t:{}:R>\n{}", r.id, r.target.as_str().escape_formatting(), r.reporter, r.created_at, r.message))
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    };
+    let embed = CreateEmbed::new()
+        .title(format!("{} open reports", reports.len()))
+        .description(description.truncate_for_embed(EMBED_DESCRIPTION_LIMIT))
+        .color(Colour::DARK_ORANGE);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Dismiss a report once it has been handled
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", ephemeral)]
+pub async fn dismiss(
+    ctx: Context<'_>,
+    #[description = "Number of the report, as shown in the report list"]
+    id: i64,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    if database::delete_report(&ctx.data().database, server_id, id).await? == 0 {
+        return Err(Box::new(CustomError::new(&format!("There is no open report #{id}"))));
+    }
+    ctx.say(format!("Dismissed report #{id}")).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lengths() {
+        assert!(check_report("faq install", "The link is dead").is_ok());
+        assert!(check_report("", "The link is dead").is_err());
+        assert!(check_report("faq install", "").is_err());
+        assert!(check_report(&"a".repeat(MAX_TARGET_LENGTH + 1), "The link is dead").is_err());
+        assert!(check_report("faq install", &"a".repeat(MAX_MESSAGE_LENGTH + 1)).is_err());
+    }
+}
@@ -5,7 +5,7 @@ use std::{fmt, sync::{Arc, RwLock}};
 use log::{error, info};
 
 use crate::{
-    custom_errors::CustomError, formatting_tools::DiscordFormat, modding_api::resolve_internal_links, Context, Data, Error 
+    formatting_tools::{truncate_with_read_more, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_TITLE_LIMIT}, modding_api::{fetch_api, member_embed, ApiError, CacheValidators, resolve_internal_links}, Context, Data, Error, management::lookup_is_private,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -18,7 +18,7 @@ pub struct BasicMember {
     pub images: Option<Vec<Image>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ApiResponse {
     pub application: String,
     pub stage: String,
@@ -114,22 +114,25 @@ pub enum TypeOrPrototype<'a> {
 
 impl BasicMember {
     pub fn create_embed(&self, data: &Data) -> serenity::CreateEmbed {
-        serenity::CreateEmbed::new()
-            .title(&self.name)
-            .description(resolve_internal_links(data, &self.description)
-                .truncate_for_embed(4096)
-            )
-            .color(serenity::Colour::GOLD)
+        let image = self.images.as_ref()
+            .and_then(|images| images.first())
+            .map(|image| (image.filename.as_str(), image.caption.as_deref()));
+        member_embed(data, &self.name, &self.description, image, self.lists.as_deref())
     }
 }
 
 impl Prototype {
     pub fn to_embed(&self, data: &Data) -> serenity::CreateEmbed {
         let url = format!("https://lua-api.factorio.com/latest/prototypes/{}.html", &self.common.name);
-        self.common.create_embed(data)
+        let embed = self.common.create_embed(data)
         .author(serenity::CreateEmbedAuthor::new("Prototype")
             .url("https://lua-api.factorio.com/latest/prototypes.html"))
-        .url(url)
+        .url(url);
+        if self.deprecated {
+            add_deprecation_warning(embed, "This prototype is deprecated and may be removed in a future version.")
+        } else {
+            embed
+        }
     }
 }
 
@@ -141,11 +144,14 @@ impl Property {
                 let optional = if self.optional {" (optional)"} else {""};
                 let parent_name = &t.common.name;
                 let t_name = &self.common.name;
-                let description = format!("`{}{}`\n{}", &self.r#type, optional, resolve_internal_links(data, &self.common.description))
-                    .truncate_for_embed(4096);
+                let description = truncate_with_read_more(
+                    &format!("{}\n{}", format!("{}{optional}", &self.r#type).to_inline_code(), resolve_internal_links(data, &self.common.description)),
+                    EMBED_DESCRIPTION_LIMIT,
+                    &url
+                );
 
                 serenity::CreateEmbed::new()
-                    .title(format!("{parent_name}::{t_name}").truncate_for_embed(256))
+                    .title(format!("{parent_name}::{t_name}").truncate_for_embed(EMBED_TITLE_LIMIT))
                     .description(description)
                     .color(serenity::Colour::GOLD)
                     .url(url)
@@ -155,19 +161,34 @@ impl Property {
                 let optional = if self.optional {" (optional)"} else {""};
                 let parent_name = &p.common.name;
                 let p_name = &self.common.name;
-                let description = format!("`{}{}`\n{}", &self.r#type, optional, resolve_internal_links(data, &self.common.description))
-                    .truncate_for_embed(4096);
-
-                serenity::CreateEmbed::new()
-                    .title(format!("{parent_name}::{p_name}").truncate_for_embed(256))
+                let description = truncate_with_read_more(
+                    &format!("{}\n{}", format!("{}{optional}", &self.r#type).to_inline_code(), resolve_internal_links(data, &self.common.description)),
+                    EMBED_DESCRIPTION_LIMIT,
+                    &url
+                );
+
+                let embed = serenity::CreateEmbed::new()
+                    .title(format!("{parent_name}::{p_name}").truncate_for_embed(EMBED_TITLE_LIMIT))
                     .description(description)
                     .color(serenity::Colour::GOLD)
-                    .url(url)
+                    .url(url);
+                if p.deprecated {
+                    add_deprecation_warning(embed, &format!("This property belongs to the deprecated prototype `{parent_name}`."))
+                } else {
+                    embed
+                }
             },
         }
     }
 }
 
+/// Marks an embed as documenting something deprecated.
+fn add_deprecation_warning(embed: serenity::CreateEmbed, message: &str) -> serenity::CreateEmbed {
+    embed
+        .color(serenity::Colour::DARK_ORANGE)
+        .field("⚠️ Deprecated", message, false)
+}
+
 impl DataStageType {
     pub fn to_embed(&self, data: &Data) -> serenity::CreateEmbed {
         let url = format!("https://lua-api.factorio.com/latest/types/{}.html", &self.common.name);
@@ -218,26 +239,35 @@ impl fmt::Display for ComplexType {
 }
 
 pub async fn update_api_cache(
+    client: &reqwest::Client,
     cache: Arc<RwLock<ApiResponse>>,
+    validators: Arc<RwLock<CacheValidators>>,
 ) -> Result<(), Error> {
     info!("Updating data stage API cache");
-    let new_data_api = get_data_api().await?;
+    let old_validators = match validators.read() {
+        Ok(v) => v.clone(),
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
+    let Some((new_data_api, new_validators)) = get_data_api(client, &old_validators).await? else {
+        info!("Data stage API is unchanged");
+        return Ok(())
+    };
     match cache.write() {
         Ok(mut c) => *c = new_data_api,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     };
+    match validators.write() {
+        Ok(mut v) => *v = new_validators,
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
     Ok(())
 }
 
-pub async fn get_data_api() -> Result<ApiResponse, Error> {
-    let response = reqwest::get("https://lua-api.factorio.com/latest/prototype-api.json").await?;
-    match response.status() {
-        reqwest::StatusCode::OK => (),
-        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing Lua prototype API", response.status().as_str()))))
-    };
-    Ok(response.json::<ApiResponse>().await?)
+/// Retrieves the latest prototype API, or None if it did not change since `validators` were received.
+pub async fn get_data_api(client: &reqwest::Client, validators: &CacheValidators) -> Result<Option<(ApiResponse, CacheValidators)>, Error> {
+    fetch_api(client, "https://lua-api.factorio.com/latest/prototype-api.json", "Lua prototype API", validators).await
 }
 
 /// Link a modding API prototype
@@ -253,19 +283,22 @@ pub async fn api_prototype (
     #[autocomplete = "autocomplete_prototype_property"]
     #[rename = "property"]
     property_search: Option<String>,
+    #[description = "Include deprecated prototypes in search suggestions"]
+    #[rename = "include_deprecated"]
+    _include_deprecated: Option<bool>,  // Only read by the autocomplete functions
 ) -> Result<(), Error> {
     let cache = ctx.data().data_api_cache.clone();
     let api = match cache.read() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
     
     let Some(search_result) = api.prototypes.iter()
         .find(|p| prototype_search.eq_ignore_ascii_case(&p.common.name)) 
     else {
-        return Err(Box::new(CustomError::new(&format!("Could not find prototype `{prototype_search}` in API documentation"))));
+        return Err(Box::new(ApiError::NotFound{kind: "prototype", name: prototype_search, docs: "API documentation"}));
     };
 
     let embed = if let Some(property_name) = property_search {
@@ -276,23 +309,32 @@ pub async fn api_prototype (
         if let Some(p) = property {
             p.to_embed(ctx.data(), &TypeOrPrototype::Prototype(search_result))
         } else {
-            return Err(Box::new(CustomError::new(&format!("Could not find property `{property_name}`"))));
+            return Err(Box::new(ApiError::PropertyNotFound(property_name)));
         }
     } else {
         search_result.to_embed(ctx.data())
     };
-
     let builder = CreateReply::default()
-        .embed(embed);
+        .embed(embed)
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
 
+/// Reads whether the user asked to include deprecated entries while autocompleting a slash command.
+fn include_deprecated_option(ctx: Context<'_>) -> bool {
+    let poise::Context::Application(appcontext) = ctx else {return false};
+    appcontext.args.iter()
+        .find(|arg| arg.name == "include_deprecated")
+        .is_some_and(|arg| matches!(arg.value, serenity::ResolvedValue::Boolean(true)))
+}
+
 #[allow(clippy::unused_async)]
 async fn autocomplete_prototype<'a>(
     ctx: Context<'_>,
     partial: &'a str,
-) -> Vec<String>{
+) -> Vec<serenity::AutocompleteChoice>{
+    let include_deprecated = include_deprecated_option(ctx);
     let cache = ctx.data().data_api_cache.clone();
     let api = match cache.read(){
         Ok(c) => c,
@@ -302,9 +344,17 @@ async fn autocomplete_prototype<'a>(
         },
     }.clone();
     api.prototypes.iter()
+        .filter(|p| include_deprecated || !p.deprecated)
         .filter(|p| p.common.name.to_lowercase().contains(&partial.to_lowercase()))
-        .map(|p| p.common.name.clone())
-        .collect::<Vec<String>>()
+        .map(|p| {
+            let label = if p.deprecated {
+                format!("{} (deprecated)", p.common.name)
+            } else {
+                p.common.name.clone()
+            };
+            serenity::AutocompleteChoice::new(label, p.common.name.clone())
+        })
+        .collect::<Vec<serenity::AutocompleteChoice>>()
 }
 
 #[allow(clippy::unused_async)]
@@ -330,6 +380,9 @@ async fn autocomplete_prototype_property<'a>(
     let Some(prototype) = api.prototypes.iter()
         .find(|p| p.common.name.eq_ignore_ascii_case(prototype_name)) 
     else {return vec![]};    // Happens when invalid class is used
+    if prototype.deprecated && !include_deprecated_option(ctx) {
+        return vec![];
+    }
 
     prototype.properties.clone()
         .into_iter()
@@ -356,18 +409,18 @@ pub async fn api_type (
     let api = match cache.read(){
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
     let Some(search_result) = api.types.iter()
         .find(|t| type_search.eq_ignore_ascii_case(&t.common.name)) 
         else {
-            return Err(Box::new(CustomError::new(&format!("Could not find type `{type_search}` in API documentation"))));
+            return Err(Box::new(ApiError::NotFound{kind: "type", name: type_search, docs: "API documentation"}));
         };
     
     let embed = if let Some(property_name) = property_search {
         if search_result.properties.is_none() {
-            return Err(Box::new(CustomError::new("Type has no properties")));
+            return Err(Box::new(ApiError::NoProperties));
         }
         let properties = &search_result.properties.clone().unwrap();
         let property = properties
@@ -376,14 +429,14 @@ pub async fn api_type (
         if let Some(p) = property {
             p.to_embed(ctx.data(), &TypeOrPrototype::Type(search_result))
         } else {
-            return Err(Box::new(CustomError::new(&format!("Could not find property `{property_name}`"))));
+            return Err(Box::new(ApiError::PropertyNotFound(property_name)));
         }
     } else {
         search_result.to_embed(ctx.data())
     };
-
     let builder = CreateReply::default()
-        .embed(embed);
+        .embed(embed)
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
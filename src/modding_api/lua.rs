@@ -1,7 +1,7 @@
 use poise::serenity_prelude as serenity;
 use poise::reply::CreateReply;
 
-use crate::{custom_errors::CustomError, Context, Error,};
+use crate::{management::lookup_is_private, modding_api::ApiError, Context, Error,};
 use crate::modding_api::lua_constants::{CHAPTERS, FUNCTIONS};
 
 /// Link items in the Lua 5.2 manual
@@ -33,10 +33,11 @@ pub async fn chapter (
             .author(serenity::CreateEmbedAuthor::new("Lua 5.2 Reference Manual"))
             .color(serenity::Colour::BLUE);
         let builder = CreateReply::default()
-            .embed(embed);
+            .embed(embed)
+            .ephemeral(lookup_is_private(ctx).await);
         ctx.send(builder).await?;
     } else {
-        return Err(Box::new(CustomError::new(&format!(r#"Could not find chapter "{chapter_name}" in lua manual"#))))
+        return Err(Box::new(ApiError::NotFound{kind: "chapter", name: chapter_name, docs: "lua manual"}))
     }
     
     Ok(())
@@ -73,10 +74,11 @@ pub async fn function (
             .author(serenity::CreateEmbedAuthor::new("Lua 5.2 Reference Manual"))
             .color(serenity::Colour::BLUE);
         let builder = CreateReply::default()
-            .embed(embed);
+            .embed(embed)
+            .ephemeral(lookup_is_private(ctx).await);
         ctx.send(builder).await?;
     } else {
-        return Err(Box::new(CustomError::new(&format!(r#"Could not find function "{function_name}" in lua manual"#))))
+        return Err(Box::new(ApiError::NotFound{kind: "function", name: function_name, docs: "lua manual"}))
     }
     Ok(())
 }
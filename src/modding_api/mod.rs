@@ -4,26 +4,113 @@ pub mod lua;
 mod lua_constants;
 
 use data::{api_prototype, api_type};
-use runtime::{api_class, api_event, api_define, api_concept};
+use runtime::{api_changelog, api_class, api_event, api_define, api_concept};
 
-use core::fmt;
+use std::fmt;
 use log::warn;
 use regex::Regex;
 use poise::serenity_prelude as serenity;
 use poise::reply::CreateReply;
+use reqwest::{header, StatusCode};
+use serde::de::DeserializeOwned;
 use std::sync::{Arc, RwLock};
 
 use crate::{
     Context, 
-    custom_errors::CustomError, 
     Data, 
     Error, 
+    LARGE_DOWNLOAD_TIMEOUT,
+    custom_errors::request_failed,
+    formatting_tools::{DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT},
+    management::lookup_is_private,
 };
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum ApiError {
+    Cache(String),
+    HttpStatus{api: &'static str, status: String},
+    Timeout(&'static str),
+    /// A page of the given kind was not found in the given documentation
+    NotFound{kind: &'static str, name: String, docs: &'static str},
+    PropertyNotFound(String),
+    NoProperties,
+    VersionUnavailable(String),
+    InvalidVersion(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cache(e) => write!(f, "Error acquiring cache: {e}"),
+            Self::HttpStatus{api, status} => write!(f, "Received HTTP status code {status} while accessing {api}"),
+            Self::Timeout(api) => write!(f, "Timed out while accessing {api}, try again later"),
+            Self::NotFound{kind, name, docs} => write!(f, "Could not find {kind} `{name}` in {docs}"),
+            Self::PropertyNotFound(name) => write!(f, "Could not find property `{name}`"),
+            Self::NoProperties => write!(f, "Type has no properties"),
+            Self::VersionUnavailable(version) => write!(f, "Could not retrieve the runtime API of version `{version}`"),
+            Self::InvalidVersion(version) => write!(f, "`{version}` is not a valid Factorio version"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// `ETag` and `Last-Modified` headers of a downloaded API file.
+/// Sent along with the next download so the server can tell us the file did not change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn from_response(response: &reqwest::Response) -> Self {
+        let header_value = |name: header::HeaderName| response.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        Self {
+            etag: header_value(header::ETAG),
+            last_modified: header_value(header::LAST_MODIFIED),
+        }
+    }
+
+    fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// Downloads an API JSON file unless it is unchanged since `validators` were received, in which case None is returned.
+async fn fetch_api<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    api: &'static str,
+    validators: &CacheValidators,
+) -> Result<Option<(T, CacheValidators)>, Error> {
+    let response = validators.apply(client.get(url).timeout(LARGE_DOWNLOAD_TIMEOUT))
+        .send()
+        .await
+        .map_err(|e| request_failed(api, e, ApiError::Timeout))?;
+    match response.status() {
+        StatusCode::OK => (),
+        StatusCode::NOT_MODIFIED => return Ok(None),
+        status => return Err(Box::new(ApiError::HttpStatus{api, status: status.as_str().to_owned()})),
+    };
+    let new_validators = CacheValidators::from_response(&response);
+    let parsed = response.json::<T>().await.map_err(|e| request_failed(api, e, ApiError::Timeout))?;
+    Ok(Some((parsed, new_validators)))
+}
+
 /// Link a page in the mod making API. Slash commands only.
 #[allow(clippy::unused_async)]
 #[poise::command(prefix_command, slash_command, track_edits, 
-    subcommands("api_class", "api_event", "api_define", "api_concept", "api_prototype", "api_type", "api_page"), 
+    subcommands("api_class", "api_event", "api_define", "api_concept", "api_prototype", "api_type", "api_page", "api_changelog", "api_version"), 
     install_context = "Guild|User", 
     interaction_context = "Guild|BotDm|PrivateChannel")]
 pub async fn api(
@@ -84,11 +171,55 @@ pub async fn api_page (
         .description(url)
         .color(serenity::Colour::GOLD);
     let builder = CreateReply::default()
-        .embed(embed);
+        .embed(embed)
+        .ephemeral(lookup_is_private(ctx).await);
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Show which Factorio version the API documentation is based on.
+#[poise::command(prefix_command, slash_command, track_edits, rename="version", install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn api_version (
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let runtime_version = match data.runtime_api_cache.read() {
+        Ok(c) => format!("{} (API version {})", c.application_version, c.api_version),
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
+    let data_version = match data.data_api_cache.read() {
+        Ok(c) => format!("{} (API version {})", c.application_version, c.api_version),
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
+    let portal_version = match data.mod_cache.read() {
+        Ok(c) => c.iter()
+            .map(|m| m.factorio_version.as_str())
+            .max_by_key(|v| version_key(v))
+            .unwrap_or("Unknown")
+            .to_owned(),
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title("API documentation version")
+        .url("https://lua-api.factorio.com/latest/")
+        .field("Runtime API", runtime_version, true)
+        .field("Data API", data_version, true)
+        .field("Newest version on the mod portal", portal_version, true)
+        .footer(serenity::CreateEmbedFooter::new("The documentation follows the latest release, which may be an experimental version."))
+        .color(serenity::Colour::GOLD);
+    let builder = CreateReply::default()
+        .embed(embed)
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
 
+/// Splits a version like "1.1.110" into its numbers so versions can be compared.
+fn version_key(version: &str) -> Vec<u32> {
+    version.split('.').map(|part| part.parse().unwrap_or_default()).collect()
+}
+
 #[derive(Debug)]
 struct ReMatch {
     full: String,
@@ -155,11 +286,62 @@ pub fn resolve_internal_links(data: &Data, s: &str) -> String {
     output
 }
 
+/// Builds the URL of an image hosted on the API documentation site, if the filename is usable.
+pub fn docs_image_url(filename: &str) -> Option<String> {
+    let filename = filename.trim();
+    if filename.is_empty() || filename.contains("..") || filename.chars().any(char::is_whitespace) {
+        return None;
+    }
+    if filename.starts_with("https://") {
+        return Some(filename.to_owned());
+    }
+    Some(format!("https://lua-api.factorio.com/latest/static/images/{}", filename.trim_start_matches('/')))
+}
+
+/// Formats the notes lists of an API member as bullet points, one item per line.
+pub fn format_member_lists(lists: &[String]) -> Option<String> {
+    let items = lists.iter()
+        .flat_map(|list| list.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let item = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).unwrap_or(line);
+            format!("- {item}")
+        })
+        .collect::<Vec<String>>();
+    if items.is_empty() {
+        return None;
+    }
+    Some(items.join("\n"))
+}
+
+/// Embed of an API member with its description, first image and notes lists. `image` is the filename and caption.
+pub fn member_embed(data: &Data, name: &str, description: &str, image: Option<(&str, Option<&str>)>, lists: Option<&[String]>) -> serenity::CreateEmbed {
+    let image = image.and_then(|(filename, caption)| docs_image_url(filename).map(|url| (url, caption)));
+    let description = match &image {
+        Some((_, Some(caption))) => format!("{description}\n\n*{caption}*"),
+        _ => description.to_owned(),
+    };
+    let mut embed = serenity::CreateEmbed::new()
+        .title(name)
+        .description(resolve_internal_links(data, &description)
+            .truncate_for_embed(EMBED_DESCRIPTION_LIMIT)
+        )
+        .color(serenity::Colour::GOLD);
+    if let Some((url, _)) = image {
+        embed = embed.image(url);
+    }
+    match lists.and_then(format_member_lists) {
+        Some(lists) => embed.field("Notes", resolve_internal_links(data, &lists).truncate_for_embed(EMBED_FIELD_LIMIT), false),
+        None => embed,
+    }
+}
+
 fn get_prototype_category(prototype_api_cache: &Arc<RwLock<data::ApiResponse>>, name: &str) -> Result<ApiSection, Error> {
     let api = match prototype_api_cache.read() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
 
@@ -178,4 +360,25 @@ fn get_prototype_category(prototype_api_cache: &Arc<RwLock<data::ApiResponse>>,
         return Ok(ApiSection::Type);
     };
     Ok(ApiSection::default())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditional_request_headers() {
+        let client = reqwest::Client::new();
+        let request = CacheValidators::default().apply(client.get("https://lua-api.factorio.com/latest/runtime-api.json")).build().unwrap();
+        assert!(request.headers().get(header::IF_NONE_MATCH).is_none());
+        assert!(request.headers().get(header::IF_MODIFIED_SINCE).is_none());
+
+        let validators = CacheValidators {
+            etag: Some("\"6752f5a1-2d4b1c\"".to_owned()),
+            last_modified: Some("Fri, 06 Dec 2024 13:02:57 GMT".to_owned()),
+        };
+        let request = validators.apply(client.get("https://lua-api.factorio.com/latest/runtime-api.json")).build().unwrap();
+        assert_eq!(request.headers()[header::IF_NONE_MATCH], "\"6752f5a1-2d4b1c\"");
+        assert_eq!(request.headers()[header::IF_MODIFIED_SINCE], "Fri, 06 Dec 2024 13:02:57 GMT");
+    }
+}
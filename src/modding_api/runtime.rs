@@ -1,16 +1,18 @@
 use serde::{Deserialize, Serialize};
 use poise::serenity_prelude as serenity;
 use poise::reply::CreateReply;
-use std::{fmt, sync::{Arc, RwLock}};
+use std::{collections::HashSet, fmt, sync::{Arc, RwLock}};
 use log::{error, info};
 
 use crate::{
     Context, 
-    custom_errors::CustomError, 
     Data, 
     Error,
-    formatting_tools::DiscordFormat, 
-    modding_api::resolve_internal_links, 
+    LARGE_DOWNLOAD_TIMEOUT,
+    custom_errors::request_failed,
+    management::lookup_is_private,
+    formatting_tools::{truncate_with_read_more, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT, EMBED_TITLE_LIMIT}, 
+    modding_api::{fetch_api, member_embed, ApiError, CacheValidators, resolve_internal_links}, 
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -29,7 +31,7 @@ pub struct Image {
     caption: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct ApiResponse {
     pub application: String,
     pub application_version: String,
@@ -201,10 +203,32 @@ pub struct GlobalObject {
 impl Class {
     pub fn to_embed(&self, data: &Data) -> serenity::CreateEmbed {
         let url = format!("https://lua-api.factorio.com/latest/classes/{}.html", &self.common.name);
-        self.common.create_embed(data)
+        let embed = self.common.create_embed(data)
         .author(serenity::CreateEmbedAuthor::new("Class")
             .url("https://lua-api.factorio.com/latest/classes.html"))
-        .url(url)
+        .url(url);
+        if self.operators.is_empty() {
+            return embed;
+        }
+        let operators = self.operators.iter()
+            .map(|o| o.name().to_inline_code())
+            .collect::<Vec<String>>()
+            .join(", ");
+        embed.field("Operators", operators, false)
+    }
+
+    /// Finds an operator of this class by name, such as `index`, `length` or `call`.
+    pub fn find_operator(&self, name: &str) -> Option<&Operator> {
+        self.operators.iter().find(|o| o.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Operator {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Method(m) => &m.common.name,
+            Self::Attribute(a) => &a.common.name,
+        }
     }
 }
 
@@ -244,13 +268,17 @@ impl Method {
         };
 
         let url = format!("https://lua-api.factorio.com/latest/classes/{}.html#{}", &parent.common.name, &self.common.name);
-        let description = format!("{}{}", returns_str, resolve_internal_links(data, &self.common.description))
-            .truncate_for_embed(4096);
-        serenity::CreateEmbed::new()
-            .title(format!("{}::{}{}", &parent.common.name, &self.common.name, parameters_str).truncate_for_embed(256))
+        let description = truncate_with_read_more(
+            &format!("{}{}", returns_str, resolve_internal_links(data, &self.common.description)),
+            EMBED_DESCRIPTION_LIMIT,
+            &url
+        );
+        let embed = serenity::CreateEmbed::new()
+            .title(format!("{}::{}{}", &parent.common.name, &self.common.name, parameters_str).truncate_for_embed(EMBED_TITLE_LIMIT))
             .description(description)
             .color(serenity::Colour::GOLD)
-            .url(url)
+            .url(url);
+        self.common.add_example(embed)
     }
 }
 
@@ -258,23 +286,40 @@ impl Attribute {
     pub fn to_embed(&self, parent: &Class, data: &Data) -> serenity::CreateEmbed {
         let optional = if self.optional { "?" } else { "" };
         let url = format!("https://lua-api.factorio.com/latest/classes/{}.html#{}", &parent.common.name, &self.common.name);
-        let description = format!("```{}{}```{}", &self.types, optional, resolve_internal_links(data, &self.common.description))
-            .truncate_for_embed(4096);
-        serenity::CreateEmbed::new()
-            .title(format!("{}::{}", &parent.common.name, &self.common.name).truncate_for_embed(256))
+        let description = truncate_with_read_more(
+            &format!("{}{}", format!("{}{optional}", &self.types).to_block_code(""), resolve_internal_links(data, &self.common.description)),
+            EMBED_DESCRIPTION_LIMIT,
+            &url
+        );
+        let embed = serenity::CreateEmbed::new()
+            .title(format!("{}::{}", &parent.common.name, &self.common.name).truncate_for_embed(EMBED_TITLE_LIMIT))
             .description(description)
             .color(serenity::Colour::GOLD)
-            .url(url)
+            .url(url);
+        match self.types.read_type.as_ref().and_then(format_struct_attributes) {
+            Some(attributes) => embed.field("Attributes", attributes.truncate_for_embed(EMBED_FIELD_LIMIT), false),
+            None => embed,
+        }
     }
 }
 
 impl Event {
     pub fn to_embed(&self, data: &Data) -> serenity::CreateEmbed {
         let url = format!("https://lua-api.factorio.com/latest/events.html#{}", &self.common.name);
-        self.common.create_embed(data)
+        let embed = self.common.create_embed(data)
         .author(serenity::CreateEmbedAuthor::new("Event")
             .url("https://lua-api.factorio.com/latest/events.html"))
-        .url(url)
+        .url(url);
+        match self.format_filter() {
+            Some(filter) => embed.field("Filters", filter, false),
+            None => embed,
+        }
+    }
+
+    /// Describes the filter type this event can be registered with, if it supports filters.
+    fn format_filter(&self) -> Option<String> {
+        let filter = self.filter.as_ref()?;
+        Some(format!("Supports filtering with [{filter}](https://lua-api.factorio.com/latest/concepts.html#{filter})"))
     }
 }
 
@@ -291,21 +336,89 @@ impl Define {
 impl Concept {
     pub fn to_embed(&self, data: &Data) -> serenity::CreateEmbed {
         let url = format!("https://lua-api.factorio.com/latest/concepts.html#{}", &self.common.name);
-        self.common.create_embed(data)
+        let embed = self.common.create_embed(data)
         .author(serenity::CreateEmbedAuthor::new("Concept")
             .url("https://lua-api.factorio.com/latest/concepts.html"))
         .url(url)
+        .field("Type", self.r#type.to_string().to_inline_code().truncate_for_embed(EMBED_FIELD_LIMIT), false);
+        match &self.r#type {
+            Type::Complex(complex) => match complex.as_ref() {
+                ComplexType::Table { parameters, .. } if !parameters.is_empty() => {
+                    embed.field("Parameters", format_parameters(parameters).truncate_for_embed(EMBED_FIELD_LIMIT), false)
+                },
+                ComplexType::LuaStruct { .. } => match format_struct_attributes(&self.r#type) {
+                    Some(attributes) => embed.field("Attributes", attributes.truncate_for_embed(EMBED_FIELD_LIMIT), false),
+                    None => embed,
+                },
+                _ => embed,
+            },
+            Type::Simple(_) => embed,
+        }
     }
 }
 
+/// Lists the attributes of a `LuaStruct` type in documentation order, one per line with their type.
+fn format_struct_attributes(t: &Type) -> Option<String> {
+    let Type::Complex(complex) = t else { return None };
+    let ComplexType::LuaStruct { attributes } = complex.as_ref() else { return None };
+    if attributes.is_empty() {
+        return None;
+    }
+    let mut sorted_attributes = attributes.clone();
+    sorted_attributes.sort_unstable_by_key(|attr| attr.common.order);
+    let lines = sorted_attributes.iter()
+        .map(|attr| {
+            let optional = if attr.optional { "?" } else { "" };
+            let attr_type = attr.types.read_type.as_ref()
+                .or(attr.types.write_type.as_ref())
+                .map_or_else(String::new, |t| format!(" :: {}", t.to_string().to_inline_code()));
+            format!("{}{attr_type}", format!("{}{optional}", attr.common.name).to_inline_code())
+        })
+        .collect::<Vec<String>>();
+    Some(lines.join("\n"))
+}
+
+/// Lists parameters in documentation order, one per line with their type.
+fn format_parameters(parameters: &[Parameter]) -> String {
+    let mut sorted_params = parameters.to_vec();
+    sorted_params.sort_unstable_by_key(|par| par.order);
+    sorted_params.iter()
+        .map(|par| {
+            let optional = if par.optional { "?" } else { "" };
+            format!("{} :: {}", format!("{}{optional}", par.name).to_inline_code(), par.r#type.to_string().to_inline_code())
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 impl BasicMember {
     pub fn create_embed(&self, data: &Data) -> serenity::CreateEmbed {
-        serenity::CreateEmbed::new()
-            .title(&self.name)
-            .description(resolve_internal_links(data, &self.description)
-                .truncate_for_embed(4096)
-            )
-            .color(serenity::Colour::GOLD)
+        let image = self.images.as_ref()
+            .and_then(|images| images.first())
+            .map(|image| (image.filename.as_str(), image.caption.as_deref()));
+        let embed = member_embed(data, &self.name, &self.description, image, self.lists.as_deref());
+        self.add_example(embed)
+    }
+
+    /// Adds the first documented example to an embed, if there is one.
+    pub fn add_example(&self, embed: serenity::CreateEmbed) -> serenity::CreateEmbed {
+        match self.format_example() {
+            Some(example) => embed.field("Example", example, false),
+            None => embed,
+        }
+    }
+
+    /// Formats the first documented example as a lua code block that fits in an embed field.
+    fn format_example(&self) -> Option<String> {
+        let example = self.examples.as_ref()?.first()?.trim();
+        if example.is_empty() {
+            return None;
+        }
+        if example.contains("```") {
+            // Examples that contain their own code blocks can't be cut without breaking them
+            return (example.chars().count() <= EMBED_FIELD_LIMIT).then(|| example.to_owned());
+        }
+        Some(example.truncate_for_embed(EMBED_FIELD_LIMIT - 11).to_block_code("lua"))
     }
 }
 
@@ -350,9 +463,34 @@ impl fmt::Display for ComplexType {
                 }
             },
             Self::LuaLazyLoadedValue { value } => write!(f, "LuaLazyLoadedValue({value})"),
-            Self::LuaStruct { .. } => write!(f, "LuaStruct"),
-            Self::Table { .. } => write!(f, "table"),
-            Self::Tuple { .. } => write!(f, "tuple"),
+            Self::LuaStruct { attributes } => {
+                let mut sorted_attributes = attributes.clone();
+                sorted_attributes.sort_unstable_by_key(|attr| attr.common.order);
+                let attribute_names = sorted_attributes.iter()
+                    .map(|attr| attr.common.name.clone())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "LuaStruct{{{attribute_names}}}")
+            },
+            Self::Table { parameters, .. } => {
+                let mut sorted_params = parameters.clone();
+                sorted_params.sort_unstable_by_key(|par| par.order);
+                let table_parameters = sorted_params.iter()
+                    .map(|par| {
+                        let optional = if par.optional { "?" } else { "" };
+                        format!("{}{optional}", par.name)
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "table{{{table_parameters}}}")
+            },
+            Self::Tuple { values } => {
+                let tuple_values = values.iter()
+                    .map(|t| format!("{t}"))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "tuple[{tuple_values}]")
+            },
         }
     }
 }
@@ -378,30 +516,53 @@ impl fmt::Display for AttributeTypes {
 }
 
 pub async fn update_api_cache(
+    client: &reqwest::Client,
     cache: Arc<RwLock<ApiResponse>>,
+    validators: Arc<RwLock<CacheValidators>>,
 ) -> Result<(), Error> {
     info!("Updating API cache");
+    let old_validators = match validators.read() {
+        Ok(v) => v.clone(),
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
+    let Some((new_runtime_api, new_validators)) = get_runtime_api(client, &old_validators).await? else {
+        info!("Runtime API is unchanged");
+        return Ok(())
+    };
     {
-    let new_runtime_api = get_runtime_api().await?;
     let mut c = match cache.write() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     };
     *c = new_runtime_api;
     }
+    match validators.write() {
+        Ok(mut v) => *v = new_validators,
+        Err(e) => return Err(Box::new(ApiError::Cache(e.to_string()))),
+    };
     Ok(())
 }
 
-pub async fn get_runtime_api() -> Result<ApiResponse, Error> {
-    let response = reqwest::get("https://lua-api.factorio.com/latest/runtime-api.json").await?;
+/// Retrieves the latest runtime API, or None if it did not change since `validators` were received.
+pub async fn get_runtime_api(client: &reqwest::Client, validators: &CacheValidators) -> Result<Option<(ApiResponse, CacheValidators)>, Error> {
+    fetch_api(client, "https://lua-api.factorio.com/latest/runtime-api.json", "Lua runtime API", validators).await
+}
+
+/// Retrieves the runtime API of a specific Factorio version, or `latest`.
+/// Downloads the names in the runtime API of an older version, for comparing it with another version.
+pub async fn get_runtime_api_version(client: &reqwest::Client, version: &str) -> Result<ApiNames, Error> {
+    let response = client.get(format!("https://lua-api.factorio.com/{version}/runtime-api.json"))
+        .timeout(LARGE_DOWNLOAD_TIMEOUT)
+        .send()
+        .await.map_err(|e| request_failed("Lua runtime API", e, ApiError::Timeout))?;
 
     match response.status() {
         reqwest::StatusCode::OK => (),
-        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing Lua runtime API", response.status().as_str()))))
+        _ => return Err(Box::new(ApiError::HttpStatus{api: "Lua runtime API", status: response.status().as_str().to_owned()}))
     };
-    Ok(response.json::<ApiResponse>().await?)
+    Ok(response.json::<ApiNames>().await.map_err(|e| request_failed("Lua runtime API", e, ApiError::Timeout))?)
 }
 
 /// Link a runtime modding API class.
@@ -423,13 +584,13 @@ pub async fn api_class (
     let api = match cache.read() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
     let Some(search_result) = api.classes.iter()
         .find(|class| class_search.eq_ignore_ascii_case(&class.common.name)) 
     else {
-        return Err(Box::new(CustomError::new(&format!("Could not find class `{class_search}` in runtime API documentation"))));
+        return Err(Box::new(ApiError::NotFound{kind: "class", name: class_search, docs: "runtime API documentation"}));
     };
 
     let embed = if let Some(property_name) = property_search {
@@ -445,15 +606,20 @@ pub async fn api_class (
         }
         else if let Some(a) = attribute {
             a.to_embed(search_result, ctx.data())
+        } else if let Some(operator) = search_result.find_operator(&property_name) {
+            match operator {
+                Operator::Method(m) => m.to_embed(search_result, ctx.data()),
+                Operator::Attribute(a) => a.to_embed(search_result, ctx.data()),
+            }
         } else {
-            return Err(Box::new(CustomError::new(&format!("Could not find property `{property_name}`"))));
+            return Err(Box::new(ApiError::PropertyNotFound(property_name)));
         }
     } else {
         search_result.to_embed(ctx.data())
     };
-
     let builder = CreateReply::default()
-        .embed(embed);
+        .embed(embed)
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
@@ -501,16 +667,187 @@ async fn autocomplete_class_property<'a>(
         .find(|c| c.common.name.eq_ignore_ascii_case(classname))
     else {return vec![]};    // Happens when invalid class is used
     
-    let methods = class.methods.clone().into_iter().map(|m| m.common);
-    let attributes = class.attributes.clone().into_iter().map(|a| a.common);
-    let properties = methods.chain(attributes);
+    let methods = class.methods.clone().into_iter().map(|m| m.common.name);
+    let attributes = class.attributes.clone().into_iter().map(|a| a.common.name);
+    let operators = class.operators.iter().map(|o| o.name().to_owned());
+    let properties = methods.chain(attributes).chain(operators);
     
-    properties.map(|p| p.name)
+    properties
         .filter(|n| n.to_lowercase().contains(&partial.to_lowercase()))
         .collect::<Vec<String>>()
 }
 
 
+// Number of older API versions kept in memory for comparisons
+const MAX_CACHED_API_VERSIONS: usize = 4;
+
+/// Classes, events and methods that were added or removed between two API versions.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ApiDiff {
+    pub added_classes: Vec<String>,
+    pub removed_classes: Vec<String>,
+    pub added_events: Vec<String>,
+    pub removed_events: Vec<String>,
+    pub added_methods: Vec<String>,
+    pub removed_methods: Vec<String>,
+}
+
+impl ApiDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_classes.is_empty() && self.removed_classes.is_empty()
+            && self.added_events.is_empty() && self.removed_events.is_empty()
+            && self.added_methods.is_empty() && self.removed_methods.is_empty()
+    }
+}
+
+/// The names that are compared between runtime API versions.
+/// Parsed separately from `ApiResponse`, so versions documented in older formats, like 1.1, can be compared too.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiNames {
+    pub application_version: String,
+    classes: Vec<ClassNames>,
+    events: Vec<MemberName>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct ClassNames {
+    name: String,
+    methods: Vec<MemberName>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct MemberName {
+    name: String,
+}
+
+impl From<&ApiResponse> for ApiNames {
+    fn from(api: &ApiResponse) -> Self {
+        Self {
+            application_version: api.application_version.clone(),
+            classes: api.classes.iter()
+                .map(|c| ClassNames {
+                    name: c.common.name.clone(),
+                    methods: c.methods.iter().map(|m| MemberName { name: m.common.name.clone() }).collect(),
+                })
+                .collect(),
+            events: api.events.iter().map(|e| MemberName { name: e.common.name.clone() }).collect(),
+        }
+    }
+}
+
+/// Returns the names in `new` that are not in `old`, and the names in `old` that are not in `new`.
+fn diff_names(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let old_set = old.iter().collect::<HashSet<&String>>();
+    let new_set = new.iter().collect::<HashSet<&String>>();
+    let added = new.iter().filter(|n| !old_set.contains(n)).cloned().collect::<Vec<String>>();
+    let removed = old.iter().filter(|n| !new_set.contains(n)).cloned().collect::<Vec<String>>();
+    (added, removed)
+}
+
+fn method_names(api: &ApiNames) -> Vec<String> {
+    api.classes.iter()
+        .flat_map(|c| c.methods.iter().map(move |m| format!("{}::{}", c.name, m.name)))
+        .collect()
+}
+
+/// Compares the classes, events and methods of two runtime API versions.
+pub fn diff_runtime_apis(old: &ApiNames, new: &ApiNames) -> ApiDiff {
+    let class_names = |api: &ApiNames| api.classes.iter().map(|c| c.name.clone()).collect::<Vec<String>>();
+    let event_names = |api: &ApiNames| api.events.iter().map(|e| e.name.clone()).collect::<Vec<String>>();
+    let (added_classes, removed_classes) = diff_names(&class_names(old), &class_names(new));
+    let (added_events, removed_events) = diff_names(&event_names(old), &event_names(new));
+    let (added_methods, removed_methods) = diff_names(&method_names(old), &method_names(new));
+    ApiDiff { added_classes, removed_classes, added_events, removed_events, added_methods, removed_methods }
+}
+
+/// Gets the runtime API names of a version from the version cache, downloading them if needed.
+/// The latest version is read from the runtime API cache.
+async fn get_cached_runtime_api(data: &Data, version: &str) -> Result<Arc<ApiNames>, Error> {
+    if version == "latest" {
+        return match data.runtime_api_cache.read() {
+            Ok(c) => Ok(Arc::new(ApiNames::from(&*c))),
+            Err(e) => Err(Box::new(ApiError::Cache(e.to_string()))),
+        };
+    }
+    if let Some(api) = data.runtime_api_versions.get(version) {
+        return Ok(api.clone());
+    }
+    let api = match get_runtime_api_version(&data.http_client, version).await {
+        Ok(api) => Arc::new(api),
+        // Versions that don't exist have no documentation
+        Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::HttpStatus{status, ..}) if status == "404") => {
+            return Err(Box::new(ApiError::VersionUnavailable(version.to_owned())));
+        },
+        Err(e) => return Err(e),
+    };
+    if data.runtime_api_versions.len() >= MAX_CACHED_API_VERSIONS {
+        // Versions are rarely compared twice, so dropping an arbitrary one is good enough
+        let evicted = data.runtime_api_versions.iter().next().map(|entry| entry.key().clone());
+        if let Some(key) = evicted {
+            data.runtime_api_versions.remove(&key);
+        }
+    }
+    data.runtime_api_versions.insert(version.to_owned(), api.clone());
+    Ok(api)
+}
+
+fn is_valid_api_version(version: &str) -> bool {
+    version == "latest" || (!version.is_empty() && version.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())))
+}
+
+/// Show which classes, events and methods were added or removed between two API versions.
+#[poise::command(prefix_command, slash_command, track_edits, rename="changelog", install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn api_changelog (
+    ctx: Context<'_>,
+    #[description = "Factorio version to compare from, e.g. 1.1.110"]
+    from_version: String,
+    #[description = "Factorio version to compare to (default latest)"]
+    to_version: Option<String>,
+) -> Result<(), Error> {
+    let to_version = to_version.unwrap_or_else(|| "latest".to_owned());
+    for version in [&from_version, &to_version] {
+        if !is_valid_api_version(version) {
+            return Err(Box::new(ApiError::InvalidVersion(version.clone())));
+        }
+    }
+    let private = lookup_is_private(ctx).await;
+    if private {
+        ctx.defer_ephemeral().await?;
+    } else {
+        ctx.defer().await?;
+    }
+    let old = get_cached_runtime_api(ctx.data(), &from_version).await?;
+    let new = get_cached_runtime_api(ctx.data(), &to_version).await?;
+    let diff = diff_runtime_apis(&old, &new);
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Runtime API changes: {} → {}", old.application_version, new.application_version))
+        .url("https://lua-api.factorio.com/latest/changelog.html")
+        .color(serenity::Colour::GOLD);
+    if diff.is_empty() {
+        embed = embed.description("No classes, events or methods were added or removed.");
+    }
+    let sections = [
+        ("Added classes", &diff.added_classes),
+        ("Removed classes", &diff.removed_classes),
+        ("Added events", &diff.added_events),
+        ("Removed events", &diff.removed_events),
+        ("Added methods", &diff.added_methods),
+        ("Removed methods", &diff.removed_methods),
+    ];
+    for (name, names) in sections {
+        if !names.is_empty() {
+            let value = names.iter().map(|n| n.as_str().to_inline_code()).collect::<Vec<String>>().join(", ");
+            embed = embed.field(format!("{name} ({})", names.len()), value.truncate_for_embed(EMBED_FIELD_LIMIT), false);
+        }
+    }
+    let builder = CreateReply::default()
+        .embed(embed)
+        .ephemeral(private);
+    ctx.send(builder).await?;
+    Ok(())
+}
+
 /// Link a runtime modding API event
 #[allow(clippy::unused_async)]
 #[poise::command(prefix_command, slash_command, track_edits, rename="event", install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
@@ -526,18 +863,18 @@ pub async fn api_event (
     let api = match cache.read() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
 
     let Some(search_result) = api.events.iter()
         .find(|event| event_search.eq_ignore_ascii_case(&event.common.name)) 
         else {
-            return Err(Box::new(CustomError::new(&format!("Could not find event `{event_search}` in runtime API documentation"))));
+            return Err(Box::new(ApiError::NotFound{kind: "event", name: event_search, docs: "runtime API documentation"}));
         };
-
     let builder = CreateReply::default()
-        .embed(search_result.to_embed(ctx.data()));
+        .embed(search_result.to_embed(ctx.data()))
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
@@ -576,17 +913,18 @@ pub async fn api_define (
     let api = match cache.read() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
 
     let Some(search_result) = api.defines.iter()
         .find(|define| define_search.eq_ignore_ascii_case(&define.common.name)) 
     else {
-        return Err(Box::new(CustomError::new(&format!("Could not find define `{define_search}` in runtime API documentation"))));
+        return Err(Box::new(ApiError::NotFound{kind: "define", name: define_search, docs: "runtime API documentation"}));
     };
     let builder = CreateReply::default()
-        .embed(search_result.to_embed(ctx.data()));
+        .embed(search_result.to_embed(ctx.data()))
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
@@ -625,18 +963,18 @@ pub async fn api_concept (
     let api = match cache.read() {
         Ok(c) => c,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ApiError::Cache(e.to_string())));
         },
     }.clone();
 
     let Some(search_result) = api.concepts.iter()
         .find(|concept| concept_search.eq_ignore_ascii_case(&concept.common.name)) 
     else {
-        return Err(Box::new(CustomError::new(&format!("Could not find concept `{concept_search}` in runtime API documentation"))))
+        return Err(Box::new(ApiError::NotFound{kind: "concept", name: concept_search, docs: "runtime API documentation"}))
     };
-
     let builder = CreateReply::default()
-        .embed(search_result.to_embed(ctx.data()));
+        .embed(search_result.to_embed(ctx.data()))
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 }
@@ -660,12 +998,246 @@ async fn autocomplete_concept<'a>(
         .collect::<Vec<String>>()
 }
 
+#[cfg(test)]
 #[allow(unused_imports)]
 mod tests {
 
     use super::*;
+    use crate::modding_api::{docs_image_url, format_member_lists};
     use std::io::Read;
     
+    fn test_api(classes: &str, events: &str) -> ApiResponse {
+        let json = format!(r#"{{"application": "factorio", "application_version": "2.0.0", "api_version": 6, "stage": "runtime",
+            "classes": [{classes}], "events": [{events}], "defines": [], "concepts": [], "global_objects": [], "global_functions": []}}"#);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn test_class(name: &str, methods: &[&str]) -> String {
+        let methods = methods.iter()
+            .map(|m| format!(r#"{{"name": "{m}", "order": 0, "description": "", "parameters": [], "format": {{"takes_table": false}}, "return_values": []}}"#))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(r#"{{"name": "{name}", "order": 0, "description": "", "methods": [{methods}], "attributes": [], "operators": [], "abstract": false}}"#)
+    }
+
+    fn test_event(name: &str) -> String {
+        format!(r#"{{"name": "{name}", "order": 0, "description": "", "data": []}}"#)
+    }
+
+    #[test]
+    fn runtime_api_diff() {
+        let old = test_api(
+            &[test_class("LuaEntity", &["destroy", "die"]), test_class("LuaOldThing", &[])].join(", "),
+            &[test_event("on_tick"), test_event("on_old_event")].join(", "),
+        );
+        let new = test_api(
+            &[test_class("LuaEntity", &["destroy", "clone"]), test_class("LuaNewThing", &["get"])].join(", "),
+            &[test_event("on_tick"), test_event("on_new_event")].join(", "),
+        );
+        let diff = diff_runtime_apis(&ApiNames::from(&old), &ApiNames::from(&new));
+        assert_eq!(diff, ApiDiff {
+            added_classes: vec!["LuaNewThing".to_owned()],
+            removed_classes: vec!["LuaOldThing".to_owned()],
+            added_events: vec!["on_new_event".to_owned()],
+            removed_events: vec!["on_old_event".to_owned()],
+            added_methods: vec!["LuaEntity::clone".to_owned(), "LuaNewThing::get".to_owned()],
+            removed_methods: vec!["LuaEntity::die".to_owned()],
+        });
+        assert!(diff_runtime_apis(&ApiNames::from(&old), &ApiNames::from(&old)).is_empty());
+    }
+
+    #[test]
+    fn runtime_api_1_1_names() {
+        // Abridged from the 1.1.110 runtime API, which uses documentation format version 4:
+        // methods have takes_table instead of format, attributes have type, read and write
+        let json = r#"{"application": "factorio", "application_version": "1.1.110", "api_version": 4, "stage": "runtime",
+            "classes": [{"name": "LuaEntity", "order": 0, "description": "", "notes": [], "base_classes": ["LuaControl"],
+                "methods": [{"name": "destroy", "order": 0, "description": "", "parameters": [], "takes_table": true, 
+                    "table_is_optional": true, "return_values": [{"order": 0, "description": "", "type": "boolean", "optional": false}]}],
+                "attributes": [{"name": "name", "order": 0, "description": "", "type": "string", "optional": false, "read": true, "write": false}],
+                "operators": [], "abstract": false}],
+            "events": [{"name": "on_tick", "order": 0, "description": "", "data": [{"name": "tick", "order": 0, "description": "", "type": "uint", "optional": false}]}],
+            "defines": [], "builtin_types": [{"name": "uint", "order": 0, "description": ""}],
+            "concepts": [{"name": "Vector", "order": 0, "description": "", "type": {"complex_type": "tuple", "parameters": []}}],
+            "global_objects": [], "global_functions": []}"#;
+        let names: ApiNames = serde_json::from_str(json).unwrap();
+        assert_eq!(names.application_version, "1.1.110");
+        assert_eq!(method_names(&names), ["LuaEntity::destroy"]);
+        let new = ApiNames::from(&test_api(&test_class("LuaEntity", &["destroy", "clone"]), &test_event("on_tick")));
+        assert_eq!(diff_runtime_apis(&names, &new).added_methods, ["LuaEntity::clone"]);
+    }
+
+    #[test]
+    fn class_operators() {
+        let index = r#"{"name": "index", "order": 0, "description": "", "read_type": "LuaEntity", "optional": false}"#;
+        let length = r#"{"name": "length", "order": 1, "description": "", "read_type": "uint", "optional": false}"#;
+        let call = r#"{"name": "call", "order": 2, "description": "", "parameters": [], "format": {"takes_table": false}, "return_values": []}"#;
+        let class = format!(r#"{{"name": "LuaCustomThing", "order": 0, "description": "", "methods": [], "attributes": [], 
+            "operators": [{index}, {length}, {call}], "abstract": false}}"#);
+        let api = test_api(&class, "");
+        let class = &api.classes[0];
+        assert!(matches!(class.find_operator("index"), Some(Operator::Attribute(_))));
+        assert!(matches!(class.find_operator("LENGTH"), Some(Operator::Attribute(_))));
+        assert!(matches!(class.find_operator("call"), Some(Operator::Method(_))));
+        assert!(class.find_operator("destroy").is_none());
+        let names = class.operators.iter().map(Operator::name).collect::<Vec<&str>>();
+        assert_eq!(names, ["index", "length", "call"]);
+    }
+
+    #[test]
+    fn example_formatting() {
+        let member = |examples: Option<Vec<String>>| BasicMember {
+            name: "test".to_owned(),
+            order: 0,
+            description: String::new(),
+            lists: None,
+            examples,
+            images: None,
+        };
+        assert_eq!(member(None).format_example(), None);
+        assert_eq!(member(Some(vec!["  ".to_owned()])).format_example(), None);
+        assert_eq!(
+            member(Some(vec!["game.print(\"hi\")".to_owned(), "second".to_owned()])).format_example(),
+            Some("```lua\ngame.print(\"hi\")\n```".to_owned())
+        );
+        let fenced = "Print a message:\n```\ngame.print(\"hi\")\n```".to_owned();
+        assert_eq!(member(Some(vec![fenced.clone()])).format_example(), Some(fenced));
+        let long = "x".repeat(2000);
+        assert_eq!(member(Some(vec![long])).format_example().map(|e| e.chars().count()), Some(1024));
+        let long_fenced = format!("```\n{}\n```", "x".repeat(2000));
+        assert_eq!(member(Some(vec![long_fenced])).format_example(), None);
+    }
+
+    #[test]
+    fn member_lists() {
+        let lists = ["- First note\n- Second note".to_owned(), "Third note".to_owned(), "* Fourth note\n\n".to_owned()];
+        assert_eq!(
+            format_member_lists(&lists), 
+            Some("- First note\n- Second note\n- Third note\n- Fourth note".to_owned())
+        );
+        assert_eq!(format_member_lists(&[]), None);
+        assert_eq!(format_member_lists(&["  ".to_owned()]), None);
+    }
+
+    #[test]
+    fn image_urls() {
+        assert_eq!(docs_image_url("pipe.png"), Some("https://lua-api.factorio.com/latest/static/images/pipe.png".to_owned()));
+        assert_eq!(docs_image_url("/pipe.png"), Some("https://lua-api.factorio.com/latest/static/images/pipe.png".to_owned()));
+        assert_eq!(docs_image_url("https://example.com/pipe.png"), Some("https://example.com/pipe.png".to_owned()));
+        assert_eq!(docs_image_url(""), None);
+        assert_eq!(docs_image_url("../secret.png"), None);
+        assert_eq!(docs_image_url("my image.png"), None);
+    }
+
+    #[test]
+    fn parameter_listing() {
+        let parameter = |name: &str, order: i32, optional: bool| Parameter {
+            name: name.to_owned(),
+            order,
+            description: String::new(),
+            r#type: Type::Simple("string".to_owned()),
+            optional,
+        };
+        let parameters = [parameter("second", 1, true), parameter("first", 0, false)];
+        assert_eq!(format_parameters(&parameters), "`first` :: `string`\n`second?` :: `string`");
+        assert_eq!(format_parameters(&[]), "");
+    }
+
+    #[test]
+    fn complex_type_display() {
+        let simple = |name: &str| Type::Simple(name.to_owned());
+        let complex = |ct: ComplexType| Type::Complex(Box::new(ct));
+        let parameter = |name: &str, order: i32, optional: bool| Parameter {
+            name: name.to_owned(),
+            order,
+            description: String::new(),
+            r#type: simple("uint"),
+            optional,
+        };
+        let cases = [
+            (ComplexType::Type { value: simple("LuaEntity"), description: String::new() }, "LuaEntity"),
+            (ComplexType::Builtin, "builtin"),
+            (ComplexType::Union { options: vec![simple("string"), simple("uint")], full_format: false }, "string or uint"),
+            (ComplexType::Array { value: simple("string") }, "array[string]"),
+            (ComplexType::Dictionary { key: simple("string"), value: simple("uint") }, "dictionary[string → uint]"),
+            (ComplexType::LuaCustomTable { key: simple("uint"), value: simple("LuaPlayer") }, "dictionary[uint → LuaPlayer]"),
+            (ComplexType::Function { parameters: vec![simple("uint"), simple("string")] }, "function(uint, string)"),
+            (ComplexType::Literal { value: serde_json::json!("north"), description: None }, r#""north""#),
+            (ComplexType::Literal { value: serde_json::json!(true), description: None }, "true"),
+            (ComplexType::Literal { value: serde_json::json!(4), description: None }, "4"),
+            (ComplexType::LuaLazyLoadedValue { value: simple("LuaEntity") }, "LuaLazyLoadedValue(LuaEntity)"),
+            (ComplexType::LuaStruct { attributes: vec![] }, "LuaStruct{}"),
+            (
+                ComplexType::Table { 
+                    parameters: vec![parameter("b", 1, true), parameter("a", 0, false)], 
+                    variant_parameter_groups: None, 
+                    variant_parameter_description: None,
+                }, 
+                "table{a, b?}"
+            ),
+            (ComplexType::Tuple { values: vec![simple("double"), complex(ComplexType::Array { value: simple("uint") })] }, "tuple[double, array[uint]]"),
+        ];
+        for (complex_type, expected) in cases {
+            assert_eq!(complex_type.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn lua_struct_attributes() {
+        let attribute = |name: &str, order: i32, read_type: Option<&str>, write_type: Option<&str>, optional: bool| Attribute {
+            common: BasicMember {
+                name: name.to_owned(),
+                order,
+                description: String::new(),
+                lists: None,
+                examples: None,
+                images: None,
+            },
+            visibility: None,
+            raises: None,
+            subclasses: None,
+            types: AttributeTypes {
+                read_type: read_type.map(|t| Type::Simple(t.to_owned())),
+                write_type: write_type.map(|t| Type::Simple(t.to_owned())),
+            },
+            optional,
+        };
+        let lua_struct = ComplexType::LuaStruct { attributes: vec![
+            attribute("speed", 1, None, Some("double"), true),
+            attribute("position", 0, Some("MapPosition"), Some("MapPosition"), false),
+        ]};
+        assert_eq!(lua_struct.to_string(), "LuaStruct{position, speed}");
+        assert_eq!(
+            format_struct_attributes(&Type::Complex(Box::new(lua_struct))), 
+            Some("`position` :: `MapPosition`\n`speed?` :: `double`".to_owned())
+        );
+        assert_eq!(format_struct_attributes(&Type::Complex(Box::new(ComplexType::LuaStruct { attributes: vec![] }))), None);
+        assert_eq!(format_struct_attributes(&Type::Simple("LuaStruct".to_owned())), None);
+    }
+
+    #[test]
+    fn event_filters() {
+        let filtered: Event = serde_json::from_str(
+            r#"{"name": "on_built_entity", "order": 0, "description": "", "data": [], "filter": "LuaPlayerBuiltEntityEventFilter"}"#
+        ).unwrap();
+        let unfiltered: Event = serde_json::from_str(r#"{"name": "on_tick", "order": 1, "description": "", "data": []}"#).unwrap();
+        assert_eq!(
+            filtered.format_filter(),
+            Some("Supports filtering with [LuaPlayerBuiltEntityEventFilter](https://lua-api.factorio.com/latest/concepts.html#LuaPlayerBuiltEntityEventFilter)".to_owned())
+        );
+        assert_eq!(unfiltered.format_filter(), None);
+    }
+
+    #[test]
+    fn api_version_validation() {
+        assert!(is_valid_api_version("latest"));
+        assert!(is_valid_api_version("1.1.110"));
+        assert!(is_valid_api_version("2.0"));
+        assert!(!is_valid_api_version(""));
+        assert!(!is_valid_api_version("1..1"));
+        assert!(!is_valid_api_version("../1.1"));
+    }
+
     #[tokio::test]
     async fn decode_api() {
         let file = std::fs::File::open("runtime-api-v5.json");
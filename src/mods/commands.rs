@@ -1,24 +1,32 @@
 use poise::serenity_prelude::{
     AutocompleteChoice, 
     CreateEmbed, 
+    CreateEmbedFooter, 
     Colour
 };
-use poise::CreateReply;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use poise::{ChoiceParameter, CreateReply};
 use log::error;
+use std::time::Duration;
 
-use crate::formatting_tools::DiscordFormat;
+use crate::formatting_tools::{format_thousands, relative_timestamp, rfc3339_to_relative_timestamp, truncate_with_read_more, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT, EMBED_TITLE_LIMIT, SELECT_OPTION_LIMIT};
 use crate::{
     Context, 
-    custom_errors::CustomError, 
     Data, 
+    database,
     Error, 
-    management::{get_server_id, checks::is_mod},
+    management::{get_bot_permissions, get_server_id, missing_update_permissions, checks::is_mod},
     mods::{
+        description,
+        ModError,
+        get_mod_version_filter,
         get_subscribed_authors,
         get_subscribed_mods,
         search_api, 
         update_notifications::{
             self, 
+            Category,
+            ModCacheEntry,
             SubCacheEntry, 
             SubscriptionType
         }
@@ -31,13 +39,33 @@ enum AutocompleteType{
     Author,
 }
 
-/// Set the channel to send mod update messages to. Bot will not work without one.
+/// Set the channel or thread to send mod update messages to. Bot will not work without one.
 #[allow(clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
 pub async fn set_updates_channel(
     ctx: Context<'_>,
     channel: poise::serenity_prelude::GuildChannel,
 ) -> Result<(), Error> {
+    let thread_parent = match channel.kind {
+        serenity::ChannelType::Text | serenity::ChannelType::News => None,
+        serenity::ChannelType::PublicThread | serenity::ChannelType::PrivateThread | serenity::ChannelType::NewsThread => {
+            if channel.thread_metadata.is_some_and(|t| t.locked) {
+                return Err(Box::new(ModError::LockedChannel(channel.to_string())));
+            }
+            channel.parent_id.map(|p| p.get() as i64)
+        },
+        serenity::ChannelType::Forum => {
+            return Err(Box::new(ModError::ForumChannel));
+        },
+        _ => {
+            return Err(Box::new(ModError::InvalidChannel(channel.to_string())));
+        },
+    };
+    let permissions = get_bot_permissions(ctx, &channel).await?;
+    let missing = missing_update_permissions(permissions, thread_parent.is_some());
+    if !missing.is_empty() {
+        return Err(Box::new(ModError::MissingPermissions{channel: channel.to_string(), permissions: missing.join(", ")}));
+    }
     let channel_id = channel.id.get() as i64;
     let server_id = channel.guild_id.get() as i64;
     let db = &ctx.data().database;
@@ -46,14 +74,14 @@ pub async fn set_updates_channel(
         .fetch_optional(db)
         .await?).is_some() {
         // Update server data if it does exist
-        sqlx::query!(r#"UPDATE servers SET updates_channel = $1 WHERE server_id = $2"#,
-        channel_id, server_id)
+        sqlx::query!(r#"UPDATE servers SET updates_channel = $1, updates_thread_parent = $2 WHERE server_id = $3"#,
+        channel_id, thread_parent, server_id)
             .execute(db)
             .await?;
     } else {
         // Add server and set setting if it does not exist
-        sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel) VALUES ($1, $2)"#,
-        server_id, channel_id)
+        sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel, updates_thread_parent) VALUES ($1, $2, $3)"#,
+        server_id, channel_id, thread_parent)
             .execute(db)
             .await?;
     }
@@ -63,6 +91,65 @@ pub async fn set_updates_channel(
     Ok(())
 }
 
+/// Send mod update messages through a webhook instead of the updates channel. Leave empty to stop using a webhook.
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_updates_webhook(
+    ctx: Context<'_>,
+    #[description = "Discord webhook URL"]
+    url: Option<String>,
+) -> Result<(), Error> {
+    // Webhook URLs grant posting access, so don't leave them visible in the channel
+    if let poise::Context::Prefix(prefix_ctx) = ctx {
+        if let Err(e) = prefix_ctx.msg.delete(ctx).await {
+            error!("Failed to delete message containing webhook URL: {e}");
+        }
+    }
+    let server_id = get_server_id(ctx)?;
+    let webhook_url = match url {
+        Some(url) => {
+            let url = url.trim().to_owned();
+            if !update_notifications::is_discord_webhook_url(&url) {
+                return Err(Box::new(ModError::InvalidWebhook));
+            }
+            let Ok(webhook) = serenity::Webhook::from_url(ctx, &url).await else {
+                return Err(Box::new(ModError::WebhookNotFound));
+            };
+            if webhook.guild_id.map(|g| g.get() as i64) != Some(server_id) {
+                return Err(Box::new(ModError::ForeignWebhook));
+            }
+            Some(url)
+        },
+        None => None,
+    };
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET updates_webhook = $1 WHERE server_id = $2"#, 
+            webhook_url, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, updates_webhook) VALUES ($1, $2)"#,
+            server_id, webhook_url)
+            .execute(db)
+            .await?;
+        },
+    };
+    let response = if webhook_url.is_some() {
+        "Mod updates will now be sent through the webhook."
+    } else {
+        "Mod updates will now be sent to the updates channel."
+    };
+    ctx.send(CreateReply::default().content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
 /// Set which role is allowed to edit bot settings. Admins can always edit settings.
 #[allow(clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
@@ -126,9 +213,148 @@ pub async fn show_changelogs(
     Ok(())
 }
 
+/// Set how many lines of the changelog are shown in the update feed
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_changelog_length(
+    ctx: Context<'_>,
+    #[description = "Maximum number of changelog lines in update messages"]
+    #[min = 1]
+    #[max = 60]
+    lines: u32,
+) -> Result<(), Error> {
+    if lines == 0 || lines as usize > update_notifications::MAX_CHANGELOG_LENGTH {
+        return Err(Box::new(ModError::ChangelogLengthOutOfRange(update_notifications::MAX_CHANGELOG_LENGTH)));
+    }
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let changelog_length = i64::from(lines);
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET changelog_length = $1 WHERE server_id = $2"#, 
+            changelog_length, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, changelog_length) VALUES ($1, $2)"#,
+            server_id, changelog_length)
+            .execute(db)
+            .await?;
+        },
+    };
+    ctx.say(format!("Update messages now show up to {lines} changelog lines.")).await?;
+    Ok(())
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum UpdateMode {
+    Live,
+    Digest,
+}
+
+/// Send a message for every mod update, or a periodic digest of all updates
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_update_mode(
+    ctx: Context<'_>,
+    #[description = "Send updates as they happen or as a periodic digest"]
+    mode: UpdateMode,
+    #[description = "Hours between digests (default 24)"]
+    #[min = 1]
+    #[max = 168]
+    interval: Option<u32>,
+) -> Result<(), Error> {
+    let digest_interval = match mode {
+        UpdateMode::Live => None,
+        UpdateMode::Digest => {
+            let hours = interval.unwrap_or(24);
+            if hours == 0 || hours > update_notifications::MAX_DIGEST_INTERVAL {
+                return Err(Box::new(ModError::DigestIntervalOutOfRange(update_notifications::MAX_DIGEST_INTERVAL)));
+            }
+            Some(i64::from(hours))
+        },
+    };
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET digest_interval = $1 WHERE server_id = $2"#, 
+            digest_interval, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, digest_interval) VALUES ($1, $2)"#,
+            server_id, digest_interval)
+            .execute(db)
+            .await?;
+        },
+    };
+    match digest_interval {
+        Some(hours) => ctx.say(format!("Mod updates will now be sent as a digest every {hours} hours.")).await?,
+        None => ctx.say("Mod updates will now be sent as they happen.").await?,
+    };
+    Ok(())
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum ModVersionFilter {
+    #[name = "All versions"]
+    All,
+    #[name = "1.1"]
+    V1_1,
+    #[name = "2.0"]
+    V2_0,
+}
+
+/// Only suggest mods for one Factorio version when searching mods on this server
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_mod_version(
+    ctx: Context<'_>,
+    #[description = "Factorio version to suggest mods for"]
+    version: ModVersionFilter,
+) -> Result<(), Error> {
+    let mod_version = match version {
+        ModVersionFilter::All => None,
+        ModVersionFilter::V1_1 | ModVersionFilter::V2_0 => Some(version.name()),
+    };
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET mod_version = $1 WHERE server_id = $2"#, 
+            mod_version, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, mod_version) VALUES ($1, $2)"#,
+            server_id, mod_version)
+            .execute(db)
+            .await?;
+        },
+    };
+    match mod_version {
+        Some(v) => ctx.say(format!("Mod suggestions are now limited to Factorio {v}.")).await?,
+        None => ctx.say("Mod suggestions now include mods for all Factorio versions.").await?,
+    };
+    Ok(())
+}
+
 /// Unsubscribe from a mod or author.
 #[allow(clippy::unused_async)]
-#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("unsubscribe_author", "unsubscribe_mod"), subcommand_required, category="Subscriptions")]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("unsubscribe_author", "unsubscribe_mod", "unsubscribe_all"), subcommand_required, category="Subscriptions")]
 pub async fn unsubscribe(
     _: Context<'_>
 ) -> Result<(), Error> {
@@ -152,34 +378,63 @@ pub async fn subscribe_mod(
     #[description = "Name of the mod to subscribe to"]
     #[autocomplete = "autocomplete_modname"]
     modname: String,
+    #[description = "Subscribe even if the mod is not known to the bot yet"]
+    force: Option<bool>,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
+        return Err(Box::new(ModError::NoServerId))
     };
     let server_id = server.get() as i64;
-    let db = &ctx.data().database;
 
-    sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_mods (server_id, mod_name) VALUES ($1, $2)"#, server_id, modname)
-        .execute(db)
-        .await?;
+    let modname = if force.unwrap_or(false) {
+        modname
+    } else {
+        let known_mods = match ctx.data().mod_cache.read() {
+            Ok(c) => c.iter().map(|m| m.name.clone()).collect::<Vec<String>>(),
+            Err(e) => {
+                return Err(Box::new(ModError::Cache(e.to_string())));
+            }
+        };
+        resolve_known_name(&modname, &known_mods, "mod")?
+    };
+
+    add_mod_subscription(ctx.data(), server_id, modname.clone()).await?;
     ctx.say(format!("Mod {modname} added to subscriptions")).await?;
+    Ok(())
+}
 
-    let cache = &ctx.data().mod_subscription_cache;
-    match cache.write() {
-        Ok(mut c) => c.push(
-            SubCacheEntry{
-                server_id,
-                subscription: SubscriptionType::Modname(modname),
+/// Store a mod subscription and add it to the subscription cache.
+async fn add_mod_subscription(data: &Data, server_id: i64, modname: String) -> Result<(), Error> {
+    database::add_mod_subscription(&data.database, server_id, &modname).await?;
+    let subscription = SubscriptionType::Modname(modname);
+    match data.mod_subscription_cache.write() {
+        Ok(mut c) => {
+            if !c.iter().any(|s| s.server_id == server_id && s.subscription == subscription) {
+                c.push(SubCacheEntry{server_id, subscription});
             }
-        ),
+        },
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ModError::Cache(e.to_string())));
         }
     }
     Ok(())
 }
 
 
+/// Finds `name` in `known_names`, ignoring case, and returns the name as it is known.
+/// If it is not found, the error suggests the closest known name.
+fn resolve_known_name(name: &str, known_names: &[String], kind: &str) -> Result<String, Error> {
+    if let Some(known) = known_names.iter().find(|n| n.eq_ignore_ascii_case(name)) {
+        return Ok(known.clone());
+    }
+    let names = known_names.iter().map(String::as_str).collect::<Vec<&str>>();
+    let suggestion = rust_fuzzy_search::fuzzy_search_best_n(name, &names, 1)
+        .first()
+        .filter(|m| m.1 > 0.5)
+        .map(|m| m.0.to_owned());
+    Err(Box::new(ModError::UnknownSubscription{kind: kind.to_owned(), name: name.to_owned(), suggestion}))
+}
+
 /// Unsubscribe from a mod
 #[allow(clippy::unused_async, clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="mod")]
@@ -190,7 +445,7 @@ pub async fn unsubscribe_mod(
     modname: String,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
+        return Err(Box::new(ModError::NoServerId))
     };
     let server_id = server.get() as i64;
     let db = &ctx.data().database;
@@ -199,6 +454,80 @@ pub async fn unsubscribe_mod(
         .await?;
     let response = format!("Mod {modname} removed from subscriptions");
     ctx.say(response).await?;
+
+    let cache = &ctx.data().mod_subscription_cache;
+    match cache.write() {
+        Ok(mut c) => update_notifications::remove_cached_subscription(&mut c, server_id, &SubscriptionType::Modname(modname)),
+        Err(e) => {
+            return Err(Box::new(ModError::Cache(e.to_string())));
+        }
+    }
+    Ok(())
+}
+
+/// Unsubscribe from all mods and authors
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="all")]
+pub async fn unsubscribe_all(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let button_yes = serenity::CreateButton::new("Yes").label("Yes").style(serenity::ButtonStyle::Danger);
+    let button_no = serenity::CreateButton::new("No").label("No").style(serenity::ButtonStyle::Primary);
+    let components = vec![serenity::CreateActionRow::Buttons(vec![button_yes, button_no])];
+    let confirmation = ctx.send(
+        CreateReply::default()
+            .content("Are you sure you want to remove all mod and author subscriptions for this server?")
+            .components(components)
+        ).await?;
+    let confirmation_message = confirmation
+        .message()
+        .await?;
+
+    let Some(response) = confirmation_message
+        .await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(60))
+        .await 
+    else {
+        let new_message = CreateReply::default()
+            .content("Timed out")
+            .components(Vec::default());
+        confirmation.edit(ctx, new_message).await?;
+        return Ok(());
+    };
+
+    if response.data.custom_id != "Yes" {
+        let new_message = CreateReply::default()
+            .content("No changes made")
+            .components(Vec::default());
+        confirmation.edit(ctx, new_message).await?;
+        return Ok(());
+    }
+
+    let mut transaction = db.begin().await?;
+    let removed_mods = sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    let removed_authors = sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1"#, server_id)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    transaction.commit().await?;
+
+    let cache = &ctx.data().mod_subscription_cache;
+    match cache.write() {
+        Ok(mut c) => c.retain(|entry| entry.server_id != server_id),
+        Err(e) => {
+            return Err(Box::new(ModError::Cache(e.to_string())));
+        }
+    }
+
+    let new_message = CreateReply::default()
+        .content(format!("Removed {removed_mods} mod subscriptions and {removed_authors} author subscriptions"))
+        .components(Vec::default());
+    confirmation.edit(ctx, new_message).await?;
     Ok(())
 }
 
@@ -218,13 +547,27 @@ pub async fn subscribe_author(
     #[description = "Name of the mod author to subscribe to"]
     #[autocomplete = "autocomplete_author"]
     author: String,
+    #[description = "Subscribe even if the author is not known to the bot yet"]
+    force: Option<bool>,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
+        return Err(Box::new(ModError::NoServerId))
     };
     let server_id = server.get() as i64;
     let db = &ctx.data().database;
 
+    let author = if force.unwrap_or(false) {
+        author
+    } else {
+        let known_authors = match ctx.data().mod_author_cache.read() {
+            Ok(c) => c.clone(),
+            Err(e) => {
+                return Err(Box::new(ModError::Cache(e.to_string())));
+            }
+        };
+        resolve_known_name(&author, &known_authors, "author")?
+    };
+
     sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_authors (server_id, author_name) VALUES ($1, $2)"#, server_id, author)
         .execute(db)
         .await?;
@@ -240,7 +583,7 @@ pub async fn subscribe_author(
             }
         ),
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ModError::Cache(e.to_string())));
         }
     }
     Ok(())
@@ -274,7 +617,7 @@ pub async fn unsubscribe_author(
     author: String,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
+        return Err(Box::new(ModError::NoServerId))
     };
     let server_id = server.get() as i64;
     let db = &ctx.data().database;
@@ -283,6 +626,14 @@ pub async fn unsubscribe_author(
         .await?;
     let response = format!("Author {author} removed from subscriptions");
     ctx.say(response).await?;
+
+    let cache = &ctx.data().mod_subscription_cache;
+    match cache.write() {
+        Ok(mut c) => update_notifications::remove_cached_subscription(&mut c, server_id, &SubscriptionType::Author(author)),
+        Err(e) => {
+            return Err(Box::new(ModError::Cache(e.to_string())));
+        }
+    }
     Ok(())
 }
 
@@ -345,7 +696,7 @@ pub async fn show_subscriptions(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
+        return Err(Box::new(ModError::NoServerId))
     };
     let server_id = server.get() as i64;
     let db = &ctx.data().database;
@@ -372,7 +723,7 @@ pub async fn show_subscriptions(
 }
 
 /// Find a mod on the mod portal. Can also be used inline with >>mod search<<.
-#[allow(clippy::unused_async)]
+#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, track_edits, 
     rename="mod", aliases("find-mod", "find_mod"), 
     install_context = "Guild|User", 
@@ -385,25 +736,221 @@ pub async fn find_mod(
     modname: String,
 ) -> Result<(), Error> {
     let command = modname.split(SEPARATOR).next().unwrap_or(&modname).trim();
-    let embed = match ctx {
-        poise::Context::Application(_) => mod_search(command, false, ctx.data()).await?,
-        poise::Context::Prefix(_) => mod_search(command, true, ctx.data()).await?,
+    let found_mod = match ctx {
+        poise::Context::Application(_) => match find_mod_result(command, false, ctx.data()).await {
+            Ok(found_mod) => found_mod,
+            Err(e) => return suggest_mod(ctx, command, e).await,
+        },
+        poise::Context::Prefix(_) => find_mod_result(command, true, ctx.data()).await?,
     };
-    let builder = CreateReply::default().embed(embed);
-    ctx.send(builder).await?;
+    let embed = mod_embed(found_mod.clone());
+    // Moderators can subscribe the server to the mod right away, if the bot is in this server.
+    // The mod is shown either way, failing to check this only leaves out the button.
+    let subscribable = match (ctx.guild_id(), ctx.guild().is_some()) {
+        _ if !ctx.data().mod_updates_enabled => false,
+        (Some(guild_id), true) => {
+            let check: Result<bool, Error> = async {
+                Ok(is_mod(ctx).await?
+                    && !get_subscribed_mods(&ctx.data().database, guild_id.get() as i64).await?.contains(&found_mod.name))
+            }.await;
+            check.unwrap_or_else(|e| {
+                error!("Error checking whether server {guild_id} can subscribe to {}: {e}", found_mod.name);
+                false
+            })
+        },
+        _ => false,
+    };
+    if !subscribable {
+        ctx.send(CreateReply::default().embed(embed)).await?;
+        return Ok(());
+    }
+    let button = serenity::CreateButton::new("mod_subscribe")
+        .label("Subscribe")
+        .style(serenity::ButtonStyle::Success);
+    let reply = ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .components(vec![serenity::CreateActionRow::Buttons(vec![button])])
+        ).await?;
+    let Some(interaction) = reply
+        .message()
+        .await?
+        .await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(120))
+        .await
+    else {
+        reply.edit(ctx, CreateReply::default().components(Vec::default())).await?;
+        return Ok(());
+    };
+    let server_id = get_server_id(ctx)?;
+    add_mod_subscription(ctx.data(), server_id, found_mod.name.clone()).await?;
+    let response = serenity::CreateInteractionResponseMessage::new()
+        .content(format!("Mod {} added to subscriptions by {}", found_mod.name, interaction.user.mention()));
+    interaction.create_response(ctx, serenity::CreateInteractionResponse::Message(response)).await?;
+    reply.edit(ctx, CreateReply::default().components(Vec::default())).await?;
+    Ok(())
+}
+
+/// Finds the cached mod whose name or title is closest to `modname`, if any is close enough.
+fn closest_cached_mod(modname: &str, mod_cache: &[ModCacheEntry]) -> Option<ModCacheEntry> {
+    let candidates = mod_cache.iter()
+        .flat_map(|m| [m.name.as_str(), m.title.as_str()])
+        .collect::<Vec<&str>>();
+    let best_match = rust_fuzzy_search::fuzzy_search_best_n(modname, &candidates, 1)
+        .first()
+        .filter(|m| m.1 > 0.5)
+        .map(|m| m.0)?;
+    mod_cache.iter().find(|m| m.name == best_match || m.title == best_match).cloned()
+}
+
+/// Offers the closest match from the mod cache when a mod name was not found.
+/// Returns the original error if nothing comes close.
+async fn suggest_mod(ctx: Context<'_>, modname: &str, error: Error) -> Result<(), Error> {
+    if !matches!(error.downcast_ref::<ModError>(), Some(ModError::ModNotInDatabase(_))) {
+        return Err(error);
+    }
+    let suggestion = match ctx.data().mod_cache.read() {
+        Ok(c) => closest_cached_mod(modname, &c),
+        Err(e) => {
+            return Err(Box::new(ModError::Cache(e.to_string())));
+        }
+    };
+    let Some(suggestion) = suggestion else {
+        return Err(error);
+    };
+    let button = serenity::CreateButton::new("mod_suggestion")
+        .label("Show mod")
+        .style(serenity::ButtonStyle::Primary);
+    let reply = ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "Could not find a mod named {}. Did you mean **{}** by {}?",
+                modname.escape_formatting(),
+                suggestion.title.clone().escape_formatting(),
+                suggestion.author.clone().escape_formatting()
+            ))
+            .components(vec![serenity::CreateActionRow::Buttons(vec![button])])
+        ).await?;
+
+    let Some(interaction) = reply
+        .message()
+        .await?
+        .await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(60))
+        .await
+    else {
+        reply.edit(ctx, CreateReply::default().components(Vec::default())).await?;
+        return Ok(());
+    };
+    interaction.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge).await?;
+    let embed = mod_search(&suggestion.name, false, ctx.data()).await?;
+    reply.edit(ctx, CreateReply::default().content("").embed(embed).components(Vec::default())).await?;
+    Ok(())
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum ModSearchSort {
+    Relevancy,
+    #[name = "Most downloaded"]
+    Downloads,
+    #[name = "Recently updated"]
+    Updated,
+}
+
+/// Search the mod portal and pick a mod from the results.
+#[poise::command(prefix_command, slash_command, 
+    rename="modsearch", aliases("mod-search", "mod_search"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn search_mods(
+    ctx: Context<'_>,
+    #[description = "Search term"]
+    query: String,
+    #[description = "How to sort the results (default relevancy)"]
+    sort: Option<ModSearchSort>,
+    #[description = "Number of results to show (default 5)"]
+    #[min = 1]
+    #[max = 10]
+    count: Option<u32>,
+) -> Result<(), Error> {
+    let sort = match sort {
+        None | Some(ModSearchSort::Relevancy) => search_api::SearchSort::Relevancy,
+        Some(ModSearchSort::Downloads) => search_api::SearchSort::Downloads,
+        Some(ModSearchSort::Updated) => search_api::SearchSort::Updated,
+    };
+    let count = count.unwrap_or(5).clamp(1, 10);
+    let results = search_api::search_mods(&ctx.data().http_client, &ctx.data().database, &query, ctx.data().mod_portal_credentials.as_deref(), sort, count).await?;
+    if results.is_empty() {
+        return Err(Box::new(ModError::NoSearchResults(query)));
+    }
+
+    let list = results.iter()
+        .enumerate()
+        .map(|(i, m)| format!("{}. [{}](https://mods.factorio.com/mod/{}) by {} ({} downloads)", 
+            i + 1, 
+            m.title.clone().escape_formatting(), 
+            m.name.replace(' ', "%20"), 
+            m.owner.clone().escape_formatting(), 
+            format_thousands(m.downloads_count)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let embed = CreateEmbed::new()
+        .title(format!("Mod portal results for {query}").truncate_for_embed(EMBED_TITLE_LIMIT))
+        .description(list.truncate_for_embed(EMBED_DESCRIPTION_LIMIT))
+        .color(Colour::from_rgb(0x2E, 0xCC, 0x71));
+    let options = results.iter()
+        .map(|m| serenity::CreateSelectMenuOption::new(m.title.truncate_for_embed(SELECT_OPTION_LIMIT), m.name.clone())
+            .description(format!("by {}", m.owner).truncate_for_embed(SELECT_OPTION_LIMIT)))
+        .collect::<Vec<serenity::CreateSelectMenuOption>>();
+    let menu = serenity::CreateSelectMenu::new("mod_search_result", serenity::CreateSelectMenuKind::String { options })
+        .placeholder("Show details of a mod");
+    let reply = ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .components(vec![serenity::CreateActionRow::SelectMenu(menu)])
+        ).await?;
+
+    let Some(interaction) = reply
+        .message()
+        .await?
+        .await_component_interaction(ctx)
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(120))
+        .await
+    else {
+        // Remove the menu once nobody can use it anymore
+        reply.edit(ctx, CreateReply::default().components(Vec::default())).await?;
+        return Ok(());
+    };
+    interaction.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge).await?;
+    let serenity::ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind else {
+        return Ok(());
+    };
+    let Some(modname) = values.first() else {
+        return Ok(());
+    };
+    let embed = mod_search(modname, false, ctx.data()).await?;
+    reply.edit(ctx, CreateReply::default().embed(embed).components(Vec::default())).await?;
     Ok(())
 }
 
 pub async fn mod_search(modname: &str, imprecise_search: bool, data: &Data) -> Result<CreateEmbed, Error> {
+    Ok(mod_embed(find_mod_result(modname, imprecise_search, data).await?))
+}
+
+/// Find a mod in the database, or on the mod portal for imprecise searches.
+async fn find_mod_result(modname: &str, imprecise_search: bool, data: &Data) -> Result<search_api::FoundMod, Error> {
     let mut search_result = if imprecise_search {
-        search_api::find_mod(modname, &data.mod_portal_credentials).await?
+        search_api::find_mod(&data.http_client, &data.database, modname, data.mod_portal_credentials.as_deref()).await?
 
     } else {
         let db = &data.database;
         let Ok(mod_data) = sqlx::query!(r#"SELECT * FROM mods WHERE name = $1"#, modname)
             .fetch_one(db)
             .await else {
-                    return Err(Box::new(CustomError::new( &format!("Failed to find mod {modname} in database"))));
+                    return Err(Box::new(ModError::ModNotInDatabase(modname.to_owned())));
         };
 
         search_api::FoundMod{
@@ -411,26 +958,317 @@ pub async fn mod_search(modname: &str, imprecise_search: bool, data: &Data) -> R
             name: mod_data.name.clone(),
             owner: mod_data.owner,
             summary: mod_data.summary.unwrap_or_default(),
-            thumbnail: update_notifications::get_mod_thumbnail(&mod_data.name).await.unwrap_or_else(|_| "https://assets-mod.factorio.com/assets/.thumb.png".to_owned()),
+            thumbnail: update_notifications::get_mod_thumbnail(&data.http_client, &mod_data.name).await.unwrap_or_else(|_| update_notifications::fallback_thumbnail_url()),
             title: mod_data.title.unwrap_or_else(|| mod_data.name.clone()),
-            factorio_version: mod_data.factorio_version.unwrap_or_default(),
+            factorio_version: if mod_data.factorio_versions.is_empty() {
+                mod_data.factorio_version.unwrap_or_default()
+            } else {
+                mod_data.factorio_versions.replace(',', ", ")
+            },
+            released_at: Some(mod_data.released_at),
+            deprecated: mod_data.deprecated,
         }
     };
-    
+    if search_result.released_at.is_none() {
+        search_result.released_at = sqlx::query!(r#"SELECT released_at FROM mods WHERE name = $1"#, search_result.name)
+            .fetch_optional(&data.database)
+            .await?
+            .map(|m| m.released_at);
+    }
+    Ok(search_result)
+}
+
+fn mod_embed(mut search_result: search_api::FoundMod) -> CreateEmbed {
     search_result.sanitize_for_embed();
     let url = format!("https://mods.factorio.com/mod/{}", search_result.name)
     .replace(' ', "%20");
 
-    let embed = CreateEmbed::new()
+    let mut embed = CreateEmbed::new()
         .title(&search_result.title)
         .url(url)
         .description(&search_result.summary)
         .color(Colour::from_rgb(0x2E, 0xCC, 0x71))
         .field("Author", &search_result.owner, true)
-        .field("Downloads", search_result.downloads_count.to_string(), true)
+        .field("Downloads", format_thousands(search_result.downloads_count), true)
         .field("Factorio version", &search_result.factorio_version, true)
         .thumbnail(&search_result.thumbnail);
-    Ok(embed)
+    if let Some(released_at) = search_result.released_at.filter(|&t| t > 0) {
+        embed = embed.field("Last updated", relative_timestamp(released_at), true);
+    }
+    if search_result.deprecated {
+        embed = embed
+            .color(Colour::DARK_ORANGE)
+            .field("⚠️ Deprecated", "This mod is marked as deprecated on the mod portal and is no longer maintained.", false);
+    }
+    embed
+}
+
+// Mods listed per page of the recent and top mods lists
+const MOD_LIST_PAGE_SIZE: usize = 10;
+
+/// Show the mods that were updated most recently
+#[poise::command(prefix_command, slash_command, 
+    rename="recent", aliases("recent-mods", "recent_mods"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn recent_mods(
+    ctx: Context<'_>,
+    #[description = "Number of mods to show (default 10)"]
+    #[min = 1]
+    #[max = 50]
+    count: Option<u32>,
+) -> Result<(), Error> {
+    let count = count.unwrap_or(10).clamp(1, 50);
+    let mods = database::get_recent_mods(&ctx.data().database, i64::from(count)).await?;
+    if mods.is_empty() {
+        ctx.say("No mods are known yet").await?;
+        return Ok(());
+    }
+    let lines = mods.into_iter()
+        .map(|m| {
+            let title = m.title.unwrap_or_else(|| m.name.clone());
+            format!("[{}](https://mods.factorio.com/mod/{}) {} by {} {}", 
+                title.escape_formatting(), 
+                m.name.replace(' ', "%20"), 
+                m.version.unwrap_or_default(), 
+                m.owner.escape_formatting(), 
+                relative_timestamp(m.released_at))
+        })
+        .collect::<Vec<String>>();
+    send_mod_list(ctx, "Recently updated mods", &lines).await
+}
+
+/// Sends a list of mods in an embed, with buttons to flip through pages if it is too long for one.
+async fn send_mod_list(ctx: Context<'_>, title: &str, lines: &[String]) -> Result<(), Error> {
+    let pages = lines.chunks(MOD_LIST_PAGE_SIZE)
+        .map(|page| page.join("\n").truncate_for_embed(EMBED_DESCRIPTION_LIMIT))
+        .collect::<Vec<String>>();
+    if let [page] = pages.as_slice() {
+        let embed = CreateEmbed::new()
+            .title(title)
+            .description(page)
+            .color(Colour::from_rgb(0x58, 0x65, 0xF2));
+        ctx.send(CreateReply::default().embed(embed)).await?;
+    } else {
+        poise::builtins::paginate(ctx, &pages.iter().map(String::as_str).collect::<Vec<&str>>()).await?;
+    }
+    Ok(())
+}
+
+/// Show the most downloaded mods
+#[poise::command(prefix_command, slash_command, 
+    rename="top", aliases("top-mods", "top_mods"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn top_mods(
+    ctx: Context<'_>,
+    #[description = "Only show mods for this Factorio version, e.g. 2.0"]
+    factorio_version: Option<String>,
+    #[description = "Only show mods of this category"]
+    category: Option<Category>,
+    #[description = "Number of mods to show (default 10)"]
+    #[min = 1]
+    #[max = 50]
+    count: Option<u32>,
+) -> Result<(), Error> {
+    let count = count.unwrap_or(10).clamp(1, 50);
+    let factorio_version = factorio_version.map(|v| v.trim().to_owned()).filter(|v| !v.is_empty());
+    let mods = database::get_top_mods(
+        &ctx.data().database, 
+        factorio_version.as_deref(), 
+        category.as_ref().map(ToString::to_string).as_deref(), 
+        i64::from(count)
+    ).await?;
+    if mods.is_empty() {
+        ctx.say("No mods found").await?;
+        return Ok(());
+    }
+    let lines = mods.into_iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let title = m.title.unwrap_or_else(|| m.name.clone());
+            format!("{}. [{}](https://mods.factorio.com/mod/{}) by {} ({} downloads)", 
+                i + 1, 
+                title.escape_formatting(), 
+                m.name.replace(' ', "%20"), 
+                m.owner.escape_formatting(), 
+                format_thousands(m.downloads_count))
+        })
+        .collect::<Vec<String>>();
+    let title = match (&factorio_version, &category) {
+        (Some(version), Some(category)) => format!("Most downloaded {} mods for {version}", category.name().to_lowercase()),
+        (Some(version), None) => format!("Most downloaded mods for {version}"),
+        (None, Some(category)) => format!("Most downloaded {} mods", category.name().to_lowercase()),
+        (None, None) => "Most downloaded mods".to_owned(),
+    };
+    send_mod_list(ctx, &title, &lines).await
+}
+
+/// Show detailed information about a mod.
+#[poise::command(prefix_command, slash_command, track_edits, 
+    rename="modinfo", aliases("mod-info", "mod_info"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn mod_info(
+    ctx: Context<'_>,
+    #[autocomplete = "autocomplete_modname"]
+    #[description = "Name of the mod"]
+    modname: String,
+) -> Result<(), Error> {
+    let mod_info = update_notifications::get_mod_info(&ctx.data().http_client, &modname).await?;
+    let url = format!("https://mods.factorio.com/mod/{}", mod_info.name)
+        .replace(' ', "%20");
+    let thumbnail = update_notifications::thumbnail_url(mod_info.thumbnail.as_deref());
+    let description = mod_info.description
+        .as_ref()
+        .filter(|d| !d.trim().is_empty())
+        .unwrap_or(&mod_info.summary)
+        .clone();
+    let latest_release = mod_info.releases
+        .as_ref()
+        .and_then(|releases| releases.last())
+        .or(mod_info.latest_release.as_ref());
+    let category = mod_info.category.as_ref().map_or_else(|| "No Category".to_owned(), ToString::to_string);
+    let author_link = format!("[{}](https://mods.factorio.com/user/{})", mod_info.owner.clone().escape_formatting(), mod_info.owner);
+
+    let mut embed = CreateEmbed::new()
+        .title(mod_info.title.clone().escape_formatting().truncate_for_embed(EMBED_TITLE_LIMIT))
+        // Only use half of the description limit to leave room for the fields below
+        .description(truncate_with_read_more(&description::portal_to_discord_markdown(&description), EMBED_DESCRIPTION_LIMIT / 2, &url))
+        .url(url)
+        .color(Colour::from_rgb(0x2E, 0xCC, 0x71))
+        .thumbnail(thumbnail)
+        .field("Author", author_link, true)
+        .field("Category", category, true)
+        .field("Downloads", format_thousands(i64::from(mod_info.downloads_count)), true);
+    if let Some(created) = mod_info.created_at.as_deref().and_then(rfc3339_to_relative_timestamp) {
+        embed = embed.field("Created", created, true);
+    }
+    if let Some(updated) = mod_info.updated_at.as_deref().and_then(rfc3339_to_relative_timestamp) {
+        embed = embed.field("Last updated", updated, true);
+    }
+    if let Some(release) = latest_release {
+        let dependencies = update_notifications::summarize_dependencies(
+            release.info_json.dependencies.as_deref().unwrap_or_default()
+        );
+        embed = embed
+            .field("Latest version", &release.version, true)
+            .field("Factorio version", &release.info_json.factorio_version, true)
+            .field("Dependencies", dependencies.truncate_for_embed(EMBED_FIELD_LIMIT), false);
+    }
+    let builder = CreateReply::default().embed(embed);
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Show the full size image of a mod.
+#[poise::command(prefix_command, slash_command, track_edits, 
+    rename="modimage", aliases("mod-image", "mod_image", "modthumbnail"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn mod_image(
+    ctx: Context<'_>,
+    #[autocomplete = "autocomplete_modname"]
+    #[description = "Name of the mod"]
+    modname: String,
+) -> Result<(), Error> {
+    let Some(image_url) = update_notifications::get_mod_image(&ctx.data().http_client, &modname).await? else {
+        ctx.say(format!("{} has no image", modname.escape_formatting())).await?;
+        return Ok(());
+    };
+    let url = format!("https://mods.factorio.com/mod/{modname}")
+        .replace(' ', "%20");
+    let embed = CreateEmbed::new()
+        .title(modname.escape_formatting().truncate_for_embed(EMBED_TITLE_LIMIT))
+        .url(url)
+        .image(image_url)
+        .color(Colour::from_rgb(0x2E, 0xCC, 0x71));
+    let builder = CreateReply::default().embed(embed);
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Show all changes made to a mod between two of its versions.
+#[poise::command(prefix_command, slash_command, track_edits, 
+    rename="modcompare", aliases("mod-compare", "mod_compare"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn compare_mod_versions(
+    ctx: Context<'_>,
+    #[autocomplete = "autocomplete_modname"]
+    #[description = "Name of the mod"]
+    modname: String,
+    #[description = "Version to compare from"]
+    from_version: String,
+    #[description = "Version to compare to"]
+    to_version: String,
+) -> Result<(), Error> {
+    let mod_info = update_notifications::get_mod_info(&ctx.data().http_client, &modname).await?;
+    let changelogs = update_notifications::get_mod_changelog(&mod_info);
+    let Some(changes) = update_notifications::get_changelog_range(&changelogs, &from_version, &to_version) else {
+        return Err(Box::new(ModError::ChangelogVersionsNotFound{modname, from_version, to_version}));
+    };
+    if changes.is_empty() {
+        return Err(Box::new(ModError::SameVersions));
+    }
+    let description = update_notifications::format_changelog_categories(
+        &update_notifications::merge_changelog_entries(&changes), 
+        40
+    );
+    let url = format!("https://mods.factorio.com/mod/{}/changelog", mod_info.name)
+        .replace(' ', "%20");
+    let embed = CreateEmbed::new()
+        .title(format!("{}: {from_version} → {to_version}", mod_info.title.escape_formatting()).truncate_for_embed(EMBED_TITLE_LIMIT))
+        .description(truncate_with_read_more(&description, EMBED_DESCRIPTION_LIMIT, &url))
+        .url(url)
+        .color(Colour::from_rgb(0x58, 0x65, 0xF2));
+    let builder = CreateReply::default().embed(embed);
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Show the changelog of a mod. Shows the latest version if no version is given.
+#[poise::command(prefix_command, slash_command, track_edits, 
+    rename="modchangelog", aliases("mod-changelog", "mod_changelog"), 
+    install_context = "Guild|User", 
+    interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn mod_changelog(
+    ctx: Context<'_>,
+    #[autocomplete = "autocomplete_modname"]
+    #[description = "Name of the mod"]
+    modname: String,
+    #[description = "Version to show the changelog of"]
+    version: Option<String>,
+) -> Result<(), Error> {
+    let db = &ctx.data().database;
+    let mut changelogs = update_notifications::get_stored_changelog(db, &modname).await?;
+    if changelogs.is_empty() {
+        // Changelog not stored yet, retrieve it from the mod portal once
+        let mod_info = update_notifications::get_mod_info(&ctx.data().http_client, &modname).await?;
+        changelogs = update_notifications::get_mod_changelog(&mod_info);
+        update_notifications::store_mod_changelog(db, &modname, &changelogs).await?;
+    }
+    let entry = match &version {
+        Some(v) => changelogs.iter().find(|c| &c.version == v),
+        None => changelogs.first(),
+    };
+    let Some(entry) = entry else {
+        return Err(Box::new(ModError::ChangelogNotFound{modname, version: version.unwrap_or_default()}));
+    };
+    let description = update_notifications::format_changelog_categories(&entry.categories, 40);
+    let url = format!("https://mods.factorio.com/mod/{modname}/changelog")
+        .replace(' ', "%20");
+    let mut embed = CreateEmbed::new()
+        .title(format!("{} {}", modname.clone().escape_formatting(), entry.version).truncate_for_embed(EMBED_TITLE_LIMIT))
+        .description(truncate_with_read_more(&description, EMBED_DESCRIPTION_LIMIT, &url))
+        .url(url)
+        .color(Colour::from_rgb(0x58, 0x65, 0xF2));
+    if let Some(date) = &entry.date {
+        embed = embed.footer(CreateEmbedFooter::new(date));
+    }
+    let builder = CreateReply::default().embed(embed);
+    ctx.send(builder).await?;
+    Ok(())
 }
 
 #[allow(clippy::unused_async)]
@@ -448,6 +1286,20 @@ async fn autocomplete_modname<'a>(
             return vec![]
         },
     }.clone();
+    let (tags, partial) = split_tag_filters(partial);
+    let partial = partial.as_str();
+    let mod_version = get_mod_version_filter(&ctx.data().database, ctx.guild_id()).await;
+    // Deprecated mods are only suggested when their name or title is typed out in full
+    let modcache = modcache.into_iter()
+        .filter(|f| 
+            !f.deprecated 
+            || f.name.eq_ignore_ascii_case(partial) 
+            || f.title.eq_ignore_ascii_case(partial)
+        )
+        .filter(|f| has_tags(f, &tags))
+        // Mods for other versions are only suggested when their name is typed out in full
+        .filter(|f| mod_version.as_ref().is_none_or(|v| f.factorio_versions.contains(v) || f.name.eq_ignore_ascii_case(partial)))
+        .collect::<Vec<ModCacheEntry>>();
     let mut list = modcache.clone().into_iter()
         .filter(move |f| 
             f.title.to_lowercase().starts_with(&partial.to_lowercase()) 
@@ -455,11 +1307,7 @@ async fn autocomplete_modname<'a>(
         )
         .map(|f| {
             listed_names.push(f.name.clone());
-            let title = f.title.truncate_for_embed(100 - 4 - f.author.len());
-            AutocompleteChoice::new(
-                "[".to_owned() + &f.factorio_version + "] " + &title + " by " + &f.author,
-                f.name,
-            )
+            mod_autocomplete_choice(&f)
         })
         .collect::<Vec<AutocompleteChoice>>();
     if list.len() >= 25 {
@@ -470,13 +1318,7 @@ async fn autocomplete_modname<'a>(
         .filter(|f| 
             !(listed_names.contains(&f.name))  // Exclude previously found names
             && f.title.to_lowercase().contains(&partial.to_lowercase()))
-        .map(|f| {
-            let title = f.title.clone().truncate_for_embed(100 - 4 - f.author.len());
-            AutocompleteChoice::new(
-                "[".to_owned() + &f.factorio_version + "] " + &title + " by " + &f.author,
-                f.name.clone(),
-            )
-        })
+        .map(mod_autocomplete_choice)
         .collect::<Vec<AutocompleteChoice>>();
     list.append(&mut title_contains);
     if list.len() >= 25 {
@@ -487,15 +1329,91 @@ async fn autocomplete_modname<'a>(
     .filter(|f| 
         !(listed_names.contains(&f.name))  // Exclude previously found names
         && f.name.to_lowercase().contains(&partial.to_lowercase()))
-    .map(|f| {
-        let title = f.title.clone().truncate_for_embed(100 - 4 - f.author.len());
-        AutocompleteChoice::new(
-            "[".to_owned() + &f.factorio_version + "] " + &title + " by " + &f.author,
-            f.name.clone(),
-        )
-    })
+    .map(mod_autocomplete_choice)
     .collect::<Vec<AutocompleteChoice>>();
     list.append(&mut name_contains);
 
     list
 }
+
+/// Separates `#tag` filters from the search text, e.g. `#trains signal` filters on the trains tag and searches for "signal".
+fn split_tag_filters(partial: &str) -> (Vec<String>, String) {
+    let (tags, words): (Vec<&str>, Vec<&str>) = partial.split_whitespace().partition(|w| w.starts_with('#'));
+    let tags = tags.into_iter()
+        .map(|t| t.trim_start_matches('#').to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    (tags, words.join(" "))
+}
+
+/// Whether the mod has all given tags. The last tag may still be being typed, so tags match by prefix.
+fn has_tags(f: &ModCacheEntry, tags: &[String]) -> bool {
+    tags.iter().all(|tag| f.tags.iter().any(|t| t.to_lowercase().starts_with(tag)))
+}
+
+fn mod_autocomplete_choice(f: &ModCacheEntry) -> AutocompleteChoice {
+    AutocompleteChoice::new(mod_autocomplete_label(f), f.name.clone())
+}
+
+/// Label of a mod in autocomplete, like `[1.1/2.0] Title by author`. Discord allows up to 100 characters.
+fn mod_autocomplete_label(f: &ModCacheEntry) -> String {
+    let marker = if f.deprecated { " [deprecated]" } else { "" };
+    let versions = format!("[{}] ", f.factorio_versions.join("/"));
+    let title_length = 100usize.saturating_sub(versions.chars().count() + 4 + f.author.chars().count() + marker.len());
+    let title = f.title.clone().truncate_for_embed(title_length);
+    versions + &title + " by " + &f.author + marker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_filters() {
+        assert_eq!(split_tag_filters("#Trains signal #logi"), (vec!["trains".to_owned(), "logi".to_owned()], "signal".to_owned()));
+        assert_eq!(split_tag_filters("space age"), (Vec::new(), "space age".to_owned()));
+        assert_eq!(split_tag_filters("# belts"), (Vec::new(), "belts".to_owned()));
+
+        let entry = ModCacheEntry {
+            name: "LTN".to_owned(),
+            title: "LTN - Logistic Train Network".to_owned(),
+            author: "Optera".to_owned(),
+            factorio_versions: vec!["2.0".to_owned()],
+            deprecated: false,
+            tags: vec!["trains".to_owned(), "logistic-network".to_owned()],
+        };
+        assert!(has_tags(&entry, &[]));
+        assert!(has_tags(&entry, &["trains".to_owned(), "logi".to_owned()]));
+        assert!(!has_tags(&entry, &["combat".to_owned()]));
+    }
+
+    #[test]
+    fn autocomplete_labels() {
+        let mut entry = ModCacheEntry {
+            name: "even-distribution".to_owned(),
+            title: "Even Distribution".to_owned(),
+            author: "Bilka".to_owned(),
+            factorio_versions: vec!["1.1".to_owned(), "2.0".to_owned()],
+            deprecated: false,
+            tags: Vec::new(),
+        };
+        assert_eq!(mod_autocomplete_label(&entry), "[1.1/2.0] Even Distribution by Bilka");
+        entry.title = "A".repeat(150);
+        entry.deprecated = true;
+        assert!(mod_autocomplete_label(&entry).chars().count() <= 100);
+    }
+
+    #[test]
+    fn closest_mod_suggestion() {
+        let cache = vec![ModCacheEntry {
+            name: "even-distribution".to_owned(),
+            title: "Even Distribution".to_owned(),
+            author: "Bilka".to_owned(),
+            factorio_versions: vec!["2.0".to_owned()],
+            deprecated: false,
+            tags: Vec::new(),
+        }];
+        assert_eq!(closest_cached_mod("even-distributon", &cache).map(|m| m.name).as_deref(), Some("even-distribution"));
+        assert!(closest_cached_mod("Krastorio2", &cache).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use std::fmt::Write;
+
+const MOD_PORTAL_URL: &str = "https://mods.factorio.com";
+
+/// Converts a mod portal description into markdown that Discord can display.
+///
+/// Headings become bold lines, images and HTML tags are removed, forum style tags are
+/// translated and relative links point to the mod portal. Code blocks are left untouched.
+pub fn portal_to_discord_markdown(description: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    for line in description.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            lines.push(line.to_owned());
+            continue;
+        }
+        if in_code_block {
+            lines.push(line.to_owned());
+            continue;
+        }
+        if let Some(converted) = convert_line(line) {
+            lines.push(converted);
+        }
+    }
+    collapse_blank_lines(&lines)
+}
+
+/// Converts a single line outside of code blocks. Returns None if the line should be dropped.
+fn convert_line(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    if is_horizontal_rule(trimmed) || is_table_separator(trimmed) {
+        return None;
+    }
+    let heading_text = trimmed.trim_start_matches('#');
+    if trimmed.starts_with('#') && (heading_text.is_empty() || heading_text.starts_with(' ')) {
+        let text = convert_inline(heading_text.trim().trim_end_matches('#').trim());
+        if text.trim().is_empty() {
+            return Some(String::new());
+        }
+        return Some(format!("**{}**", text.trim()));
+    }
+    let converted = convert_inline(line);
+    // Lines that only contained images or tags would otherwise leave stray whitespace
+    if converted.trim().is_empty() && !trimmed.is_empty() {
+        return None;
+    }
+    Some(converted.trim_end().to_owned())
+}
+
+/// Converts inline markup: images, links, HTML and forum style tags and underscore bold.
+fn convert_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            // Inline code is copied as is
+            let end = rest[1..].find('`').map_or(rest.len(), |i| i + 2);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with("![") {
+            if let Some((_, _, len)) = parse_link(&rest[1..]) {
+                rest = &rest[len + 1..];
+            } else {
+                out.push('!');
+                rest = &rest[1..];
+            }
+        } else if c == '[' {
+            if let Some((label, url, len)) = parse_link(rest) {
+                let _ = write!(out, "[{}]({})", convert_inline(label), absolute_url(url));
+                rest = &rest[len..];
+            } else if let Some((replacement, len)) = convert_bbcode(rest) {
+                out.push_str(&replacement);
+                rest = &rest[len..];
+            } else {
+                out.push(c);
+                rest = &rest[1..];
+            }
+        } else if c == '<' {
+            if let Some((replacement, len)) = convert_html_tag(rest) {
+                out.push_str(replacement);
+                rest = &rest[len..];
+            } else {
+                out.push(c);
+                rest = &rest[1..];
+            }
+        } else if rest.starts_with("__") {
+            // Double underscores are bold in markdown but underline on Discord
+            out.push_str("**");
+            rest = &rest[2..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// Parses a markdown link at the start of `text`, returning its label, url and length.
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = text.get(1..label_end)?;
+    if label.contains('[') || label.contains(']') {
+        return None;
+    }
+    let url_start = label_end + 2;
+    let url_len = text[url_start..].find(')')?;
+    let url = text[url_start..url_start + url_len].split_whitespace().next().unwrap_or_default();
+    Some((label, url, url_start + url_len + 1))
+}
+
+fn absolute_url(url: &str) -> String {
+    if url.starts_with('/') {
+        format!("{MOD_PORTAL_URL}{url}")
+    } else {
+        url.to_owned()
+    }
+}
+
+/// Translates a forum style tag at the start of `text`, returning the replacement and the length consumed.
+fn convert_bbcode(text: &str) -> Option<(String, usize)> {
+    let tag_end = text.find(']')?;
+    let tag = &text[1..tag_end];
+    let lower = tag.to_ascii_lowercase();
+    match lower.as_str() {
+        "b" | "/b" => Some(("**".to_owned(), tag_end + 1)),
+        "i" | "/i" => Some(("*".to_owned(), tag_end + 1)),
+        "u" | "/u" => Some(("__".to_owned(), tag_end + 1)),
+        "s" | "/s" => Some(("~~".to_owned(), tag_end + 1)),
+        "img" => {
+            // Images can't be shown inline, drop them with their url
+            let close = lower.len() + 2;
+            let end = text.to_ascii_lowercase().find("[/img]").map_or(close, |i| i + "[/img]".len());
+            Some((String::new(), end))
+        },
+        _ if lower.starts_with("url=") => {
+            let url = tag[4..].trim_matches(['"', '\'']);
+            let content_start = tag_end + 1;
+            let content_len = text[content_start..].to_ascii_lowercase().find("[/url]")?;
+            let label = &text[content_start..content_start + content_len];
+            Some((format!("[{}]({})", convert_inline(label), absolute_url(url)), content_start + content_len + "[/url]".len()))
+        },
+        "/url" | "/img" | "center" | "/center" | "quote" | "/quote" | "spoiler" | "/spoiler" => Some((String::new(), tag_end + 1)),
+        _ if lower.starts_with("color=") || lower.starts_with("size=") || lower == "/color" || lower == "/size" => Some((String::new(), tag_end + 1)),
+        _ => None,
+    }
+}
+
+/// Removes an HTML tag at the start of `text`, returning its replacement and the length consumed.
+fn convert_html_tag(text: &str) -> Option<(&'static str, usize)> {
+    let end = text.find('>')?;
+    let tag = &text[1..end];
+    let name = tag.trim_start_matches('/').split([' ', '/']).next().unwrap_or_default();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let replacement = match name.to_ascii_lowercase().as_str() {
+        "br" => "\n",
+        "b" | "strong" => "**",
+        "i" | "em" => "*",
+        _ => "",
+    };
+    Some((replacement, end + 1))
+}
+
+fn is_horizontal_rule(line: &str) -> bool {
+    let chars = line.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|r| chars.iter().all(|c| c == r))
+}
+
+fn is_table_separator(line: &str) -> bool {
+    line.starts_with('|') && line.contains('-') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// Joins lines, allowing at most one blank line in a row and none at the start or end.
+fn collapse_blank_lines(lines: &[String]) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    for line in lines.iter().flat_map(|l| l.split('\n')) {
+        if line.trim().is_empty() && out.last().is_none_or(|l| l.trim().is_empty()) {
+            continue;
+        }
+        out.push(line);
+    }
+    while out.last().is_some_and(|l| l.trim().is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_become_bold() {
+        let description = "# My Mod\nSome text\n\n## Features ##\n- Thing";
+        assert_eq!(portal_to_discord_markdown(description), "**My Mod**\nSome text\n\n**Features**\n- Thing");
+    }
+
+    #[test]
+    fn images_are_removed() {
+        let description = "![banner](https://example.com/banner.png)\nIntro ![icon](/icon.png) text\n[img]https://example.com/a.png[/img]";
+        assert_eq!(portal_to_discord_markdown(description), "Intro  text");
+    }
+
+    #[test]
+    fn links_are_kept() {
+        let description = "See [the wiki](https://wiki.factorio.com) and [my other mod](/mod/other-mod).\n[url=https://example.com]BBCode link[/url]";
+        assert_eq!(
+            portal_to_discord_markdown(description),
+            "See [the wiki](https://wiki.factorio.com) and [my other mod](https://mods.factorio.com/mod/other-mod).\n[BBCode link](https://example.com)"
+        );
+    }
+
+    #[test]
+    fn emphasis_is_converted() {
+        let description = "**bold** *italic* __also bold__ [b]bb bold[/b] [i]bb italic[/i] <strong>html</strong>";
+        assert_eq!(portal_to_discord_markdown(description), "**bold** *italic* **also bold** **bb bold** *bb italic* **html**");
+    }
+
+    #[test]
+    fn unsupported_constructs_are_stripped() {
+        let description = "Line one<br>Line two\n\n---\n\n\n<details><summary>Spoiler</summary>Hidden</details>\n| A | B |\n|---|:-:|\n| 1 | 2 |\n[center][color=red]Red[/color][/center]";
+        assert_eq!(portal_to_discord_markdown(description), "Line one\nLine two\n\nSpoilerHidden\n| A | B |\n| 1 | 2 |\nRed");
+    }
+
+    #[test]
+    fn code_is_untouched() {
+        let description = "Use `__init__` here\n```lua\n# not a heading\n![x](y)\n```";
+        assert_eq!(portal_to_discord_markdown(description), description);
+    }
+
+    #[test]
+    fn plain_brackets_survive() {
+        let description = "Compatible with [Krastorio 2] and 1 < 2 > 0";
+        assert_eq!(portal_to_discord_markdown(description), description);
+    }
+}
@@ -0,0 +1,327 @@
+use log::{error, info, warn};
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedAuthor};
+use poise::CreateReply;
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+
+use crate::{
+    Context,
+    Error,
+    custom_errors::request_failed,
+    formatting_tools::{rfc3339_to_relative_timestamp, truncate_with_read_more, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_TITLE_LIMIT},
+    management::{get_server_id, checks::is_mod},
+    mods::{update_notifications::UpdateDestination, ModError},
+};
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+// Name shown on release messages sent through a webhook
+const RELEASES_WEBHOOK_NAME: &str = "GitHub Releases";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub html_url: String,
+    pub body: Option<String>,
+    pub published_at: Option<String>,
+    pub author: Option<ReleaseAuthor>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseAuthor {
+    pub login: String,
+    pub avatar_url: String,
+    pub html_url: String,
+}
+
+/// Reads `owner/repository` from user input, which may also be a link to the repository.
+pub fn parse_repository(input: &str) -> Option<String> {
+    let trimmed = input.trim();
+    let path = ["https://github.com/", "http://github.com/", "github.com/"]
+        .iter()
+        .find_map(|prefix| trimmed.strip_prefix(prefix))
+        .unwrap_or(trimmed)
+        .trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let valid_part = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    match path.split('/').collect::<Vec<&str>>().as_slice() {
+        // GitHub ignores the case of names, so store them in one case to avoid watching a repository twice
+        [owner, repo] if valid_part(owner) && valid_part(repo) => Some(format!("{owner}/{repo}").to_lowercase()),
+        _ => None,
+    }
+}
+
+/// GitHub answers 403 or 429 once the rate limit is used up.
+fn is_rate_limited(status: reqwest::StatusCode, remaining: Option<&str>) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::FORBIDDEN && remaining == Some("0"))
+}
+
+/// Sends a request to the GitHub API, authenticated with `GITHUB_TOKEN` if it is set.
+/// With an `etag` from an earlier response, GitHub answers 304 Not Modified if nothing changed.
+async fn github_get(client: &reqwest::Client, token: Option<&str>, path: &str, etag: Option<&str>) -> Result<reqwest::Response, Error> {
+    let mut request = client.get(format!("{GITHUB_API_URL}{path}"))
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send()
+        .await
+        .map_err(|e| request_failed("GitHub API", e, ModError::Timeout))?;
+    let remaining = response.headers().get("x-ratelimit-remaining").and_then(|v| v.to_str().ok());
+    if is_rate_limited(response.status(), remaining) {
+        return Err(Box::new(ModError::RateLimited("GitHub API")));
+    }
+    Ok(response)
+}
+
+async fn repository_exists(client: &reqwest::Client, token: Option<&str>, repo: &str) -> Result<bool, Error> {
+    let response = github_get(client, token, &format!("/repos/{repo}"), None).await?;
+    match response.status() {
+        reqwest::StatusCode::OK => Ok(true),
+        reqwest::StatusCode::NOT_FOUND => Ok(false),
+        status => Err(Box::new(ModError::HttpStatus{api: "GitHub API", status: status.as_str().to_owned()})),
+    }
+}
+
+pub enum LatestRelease {
+    /// Same as at the last check, which doesn't count towards the rate limit
+    Unchanged,
+    NoReleases,
+    Found{release: Release, etag: Option<String>},
+}
+
+/// The latest published release of a repository. Drafts and prereleases are skipped by GitHub.
+pub async fn get_latest_release(client: &reqwest::Client, token: Option<&str>, repo: &str, etag: Option<&str>) -> Result<LatestRelease, Error> {
+    let response = github_get(client, token, &format!("/repos/{repo}/releases/latest"), etag).await?;
+    match response.status() {
+        reqwest::StatusCode::OK => (),
+        reqwest::StatusCode::NOT_MODIFIED => return Ok(LatestRelease::Unchanged),
+        reqwest::StatusCode::NOT_FOUND => return Ok(LatestRelease::NoReleases),
+        status => return Err(Box::new(ModError::HttpStatus{api: "GitHub API", status: status.as_str().to_owned()})),
+    };
+    let etag = response.headers().get(reqwest::header::ETAG).and_then(|v| v.to_str().ok()).map(str::to_owned);
+    let release = response.json::<Release>().await.map_err(|e| request_failed("GitHub API", e, ModError::Timeout))?;
+    Ok(LatestRelease::Found{release, etag})
+}
+
+fn release_embed(repo: &str, release: &Release) -> CreateEmbed {
+    let name = release.name.as_deref().filter(|n| !n.trim().is_empty()).unwrap_or(&release.tag_name);
+    let notes = release.body.as_deref().unwrap_or_default();
+    let mut embed = CreateEmbed::new()
+        .title(format!("New release of {repo}:\n{}", name.to_owned().escape_formatting()).truncate_for_embed(EMBED_TITLE_LIMIT))
+        .url(&release.html_url)
+        .description(truncate_with_read_more(notes, EMBED_DESCRIPTION_LIMIT, &release.html_url))
+        .field("**Tag**", &release.tag_name, true)
+        .color(Colour::from_rgb(0x24, 0x29, 0x2F));
+    if let Some(released) = release.published_at.as_deref().and_then(rfc3339_to_relative_timestamp) {
+        embed = embed.field("**Released**", released, true);
+    }
+    if let Some(author) = &release.author {
+        embed = embed.author(CreateEmbedAuthor::new(&author.login).url(&author.html_url).icon_url(&author.avatar_url));
+    }
+    embed
+}
+
+/// Checks all watched repositories for new releases and posts them to the servers watching them.
+/// The first release seen of a repository is only stored, so registering a repository doesn't post an old release.
+/// Releases are always posted right away, also on servers that receive mod updates in digests.
+pub async fn check_github_releases(
+        db: Pool<Sqlite>,
+        client: &reqwest::Client,
+        token: Option<&str>,
+        cache_http: &Arc<poise::serenity_prelude::Http>
+    ) -> Result<(), Error> {
+    sqlx::query!(r#"DELETE FROM github_releases WHERE repo NOT IN (SELECT repo FROM github_subscriptions)"#)
+        .execute(&db)
+        .await?;
+    let repos = sqlx::query!(r#"SELECT DISTINCT repo FROM github_subscriptions"#)
+        .fetch_all(&db)
+        .await?;
+    for repo in repos.into_iter().map(|r| r.repo) {
+        let last_seen = sqlx::query!(r#"SELECT tag, etag FROM github_releases WHERE repo = $1"#, repo)
+            .fetch_optional(&db)
+            .await?;
+        let etag = last_seen.as_ref().and_then(|r| r.etag.as_deref());
+        let (release, etag) = match get_latest_release(client, token, &repo, etag).await {
+            Ok(LatestRelease::Found{release, etag}) => (release, etag),
+            Ok(LatestRelease::Unchanged | LatestRelease::NoReleases) => continue,
+            Err(e) if e.downcast_ref::<ModError>() == Some(&ModError::RateLimited("GitHub API")) => {
+                warn!("GitHub API rate limit reached, checking the remaining repositories next time");
+                break;
+            },
+            Err(e) => {
+                error!("Error checking releases of {repo}: {e}");
+                continue;
+            },
+        };
+        let last_seen = last_seen.map(|r| r.tag);
+        sqlx::query!(r#"INSERT OR REPLACE INTO github_releases (repo, tag, etag) VALUES ($1, $2, $3)"#, repo, release.tag_name, etag)
+            .execute(&db)
+            .await?;
+        if last_seen.as_deref() == Some(release.tag_name.as_str()) {
+            continue;
+        }
+        if last_seen.is_none() {
+            info!("Started watching releases of {repo} at {}", release.tag_name);
+            continue;
+        }
+        info!("Sending release message for {repo} {}", release.tag_name);
+        send_release(&db, cache_http, &repo, &release).await?;
+    }
+    Ok(())
+}
+
+async fn send_release(
+        db: &Pool<Sqlite>,
+        cache_http: &Arc<poise::serenity_prelude::Http>,
+        repo: &str,
+        release: &Release
+    ) -> Result<(), Error> {
+    let servers = sqlx::query!(r#"SELECT s.server_id, s.updates_channel, s.updates_thread_parent, s.updates_webhook FROM servers s
+        JOIN github_subscriptions g ON g.server_id = s.server_id WHERE g.repo = $1"#, repo)
+        .fetch_all(db)
+        .await?;
+    let avatar = release.author.as_ref().map(|a| a.avatar_url.as_str());
+    for server in servers {
+        let Some(destination) = UpdateDestination::from_settings(server.updates_channel, server.updates_thread_parent, server.updates_webhook) else {
+            continue;
+        };
+        // One server with a broken updates channel shouldn't stop the others from receiving the release
+        if let Err(e) = destination.send_as(cache_http, RELEASES_WEBHOOK_NAME, release_embed(repo, release), avatar, Vec::new()).await {
+            error!("Error sending release message of {repo} to server {}: {e}", server.server_id);
+        }
+    }
+    Ok(())
+}
+
+/// Post new releases of GitHub repositories in the updates channel
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("add", "remove", "list"), subcommand_required, category="Subscriptions")]
+pub async fn github(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Watch a GitHub repository for new releases
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Repository as owner/repository, or a link to it"]
+    repository: String,
+) -> Result<(), Error> {
+    let Some(repo) = parse_repository(&repository) else {
+        return Err(Box::new(ModError::InvalidRepository(repository)));
+    };
+    if !repository_exists(&ctx.data().http_client, ctx.data().github_token.as_deref(), &repo).await? {
+        return Err(Box::new(ModError::RepositoryNotFound(repo)));
+    }
+    let server_id = get_server_id(ctx)?;
+    sqlx::query!(r#"INSERT OR REPLACE INTO github_subscriptions (server_id, repo) VALUES ($1, $2)"#, server_id, repo)
+        .execute(&ctx.data().database)
+        .await?;
+    ctx.say(format!("New releases of `{repo}` will be posted in the updates channel")).await?;
+    Ok(())
+}
+
+/// Stop watching a GitHub repository
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Repository as owner/repository, or a link to it"]
+    repository: String,
+) -> Result<(), Error> {
+    let repo = parse_repository(&repository).unwrap_or_else(|| repository.to_lowercase());
+    let server_id = get_server_id(ctx)?;
+    let removed = sqlx::query!(r#"DELETE FROM github_subscriptions WHERE server_id = $1 AND repo = $2"#, server_id, repo)
+        .execute(&ctx.data().database)
+        .await?
+        .rows_affected();
+    if removed == 0 {
+        ctx.say(format!("`{repo}` was not being watched")).await?;
+    } else {
+        ctx.say(format!("Stopped watching releases of `{repo}`")).await?;
+    }
+    Ok(())
+}
+
+/// Show the GitHub repositories watched for releases
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn list(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let repos = sqlx::query!(r#"SELECT repo FROM github_subscriptions WHERE server_id = $1 ORDER BY repo"#, server_id)
+        .fetch_all(&ctx.data().database)
+        .await?
+        .into_iter()
+        .map(|r| format!("- [{0}](https://github.com/{0})", r.repo))
+        .collect::<Vec<String>>();
+    let description = if repos.is_empty() {
+        "No repositories are watched. Add one with `/github add`.".to_owned()
+    } else {
+        repos.join("\n")
+    };
+    let embed = CreateEmbed::new()
+        .title("Watched GitHub repositories")
+        .description(description.truncate_for_embed(EMBED_DESCRIPTION_LIMIT))
+        .color(Colour::from_rgb(0x24, 0x29, 0x2F));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_names() {
+        assert_eq!(parse_repository("raiguard/flib").as_deref(), Some("raiguard/flib"));
+        assert_eq!(parse_repository(" https://github.com/raiguard/flib/ ").as_deref(), Some("raiguard/flib"));
+        assert_eq!(parse_repository("github.com/wube/factorio-data.git").as_deref(), Some("wube/factorio-data"));
+        assert_eq!(parse_repository("Raiguard/FLib").as_deref(), Some("raiguard/flib"));
+        assert_eq!(parse_repository("flib"), None);
+        assert_eq!(parse_repository("raiguard/flib/releases"), None);
+        assert_eq!(parse_repository("raiguard/fl ib"), None);
+        assert_eq!(parse_repository("https://gitlab.com/raiguard/flib"), None);
+    }
+
+    #[test]
+    fn rate_limits() {
+        assert!(is_rate_limited(reqwest::StatusCode::TOO_MANY_REQUESTS, None));
+        assert!(is_rate_limited(reqwest::StatusCode::FORBIDDEN, Some("0")));
+        assert!(!is_rate_limited(reqwest::StatusCode::FORBIDDEN, Some("12")));
+        assert!(!is_rate_limited(reqwest::StatusCode::OK, Some("0")));
+    }
+
+    #[test]
+    fn release_json() {
+        let release: Release = serde_json::from_str(r#"{
+            "tag_name": "v1.2.0",
+            "name": "",
+            "html_url": "https://github.com/raiguard/flib/releases/tag/v1.2.0",
+            "body": null,
+            "draft": false,
+            "published_at": "2024-12-20T12:00:00Z",
+            "author": {"login": "raiguard", "avatar_url": "https://avatars.githubusercontent.com/u/1", "html_url": "https://github.com/raiguard"}
+        }"#).unwrap();
+        assert_eq!(release.tag_name, "v1.2.0");
+        assert_eq!(release.author.map(|a| a.login).as_deref(), Some("raiguard"));
+    }
+
+    #[sqlx::test]
+    async fn subscriptions_are_cleared_with_server(db: Pool<Sqlite>) {
+        sqlx::query!(r#"INSERT INTO github_subscriptions (server_id, repo) VALUES (1, 'raiguard/flib'), (2, 'raiguard/flib')"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        crate::database::clear_server_data(&db, 1).await.unwrap();
+        let remaining = sqlx::query!(r#"SELECT server_id FROM github_subscriptions"#).fetch_all(&db).await.unwrap();
+        assert_eq!(remaining.iter().map(|r| r.server_id).collect::<Vec<i64>>(), vec![2]);
+    }
+}
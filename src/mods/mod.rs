@@ -1,10 +1,80 @@
 pub mod commands;
+pub mod description;
+pub mod github_releases;
 pub mod update_notifications;
 pub mod search_api;
 
+use std::fmt;
+use log::error;
+use poise::serenity_prelude::GuildId;
 use sqlx::{Pool, Sqlite};
 use crate::Error;
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModError {
+    Cache(String),
+    NoServerId,
+    HttpStatus{api: &'static str, status: String},
+    Timeout(&'static str),
+    RateLimited(&'static str),
+    NoSearchResults(String),
+    ModNotFound(String),
+    ModNotInDatabase(String),
+    ChangelogVersionsNotFound{modname: String, from_version: String, to_version: String},
+    SameVersions,
+    ChangelogNotFound{modname: String, version: String},
+    LockedChannel(String),
+    ForumChannel,
+    InvalidChannel(String),
+    MissingPermissions{channel: String, permissions: String},
+    InvalidWebhook,
+    WebhookNotFound,
+    ForeignWebhook,
+    ChangelogLengthOutOfRange(usize),
+    DigestIntervalOutOfRange(u32),
+    UnknownSubscription{kind: String, name: String, suggestion: Option<String>},
+    InvalidRepository(String),
+    RepositoryNotFound(String),
+}
+
+impl fmt::Display for ModError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cache(e) => write!(f, "Error acquiring cache: {e}"),
+            Self::NoServerId => write!(f, "Could not get server ID"),
+            Self::HttpStatus{api, status} => write!(f, "Received HTTP status code {status} while accessing {api}"),
+            Self::Timeout(api) => write!(f, "Timed out while accessing {api}, try again later"),
+            Self::RateLimited(api) => write!(f, "Too many requests to {api}, try again later"),
+            Self::NoSearchResults(query) => write!(f, "Did not find any mods matching {query}"),
+            Self::ModNotFound(name) => write!(f, "Did not find any mods named {name}"),
+            Self::ModNotInDatabase(name) => write!(f, "Failed to find mod {name} in database"),
+            Self::ChangelogVersionsNotFound{modname, from_version, to_version} => write!(f, "Could not find versions {from_version} and {to_version} in the changelog of {modname}"),
+            Self::SameVersions => write!(f, "Versions to compare must be different"),
+            Self::ChangelogNotFound{modname, version} => write!(f, "Could not find a changelog for {modname} {version}"),
+            Self::LockedChannel(channel) => write!(f, "{channel} is locked, the bot can not post there"),
+            Self::ForumChannel => write!(f, "Forum channels can not receive messages directly, pick a thread in the forum instead"),
+            Self::InvalidChannel(channel) => write!(f, "{channel} is not a text channel or thread"),
+            Self::MissingPermissions{channel, permissions} => write!(f, "The bot is missing the following permissions in {channel}: {permissions}"),
+            Self::InvalidWebhook => write!(f, "That is not a valid Discord webhook URL"),
+            Self::WebhookNotFound => write!(f, "Could not find a webhook with that URL"),
+            Self::ForeignWebhook => write!(f, "That webhook does not belong to this server"),
+            Self::ChangelogLengthOutOfRange(max) => write!(f, "Changelog length must be between 1 and {max}"),
+            Self::DigestIntervalOutOfRange(max) => write!(f, "Digest interval must be between 1 and {max} hours"),
+            Self::UnknownSubscription{kind, name, suggestion} => {
+                write!(f, "Could not find {kind} `{name}`.")?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " Did you mean `{suggestion}`?")?;
+                }
+                write!(f, " Use the `force` option to subscribe anyway.")
+            },
+            Self::InvalidRepository(repo) => write!(f, "`{repo}` is not a GitHub repository, use the form `owner/repository`"),
+            Self::RepositoryNotFound(repo) => write!(f, "Could not find the GitHub repository `{repo}`"),
+        }
+    }
+}
+
+impl std::error::Error for ModError {}
+
 #[allow(clippy::module_name_repetitions)]
 pub async fn get_subscribed_mods(db: &Pool<Sqlite>, server_id: i64) -> Result<Vec<String>, Error> {
     let subscribed_mods = sqlx::query!(r#"SELECT mod_name FROM subscribed_mods WHERE server_id = $1"#, server_id)
@@ -16,6 +86,21 @@ pub async fn get_subscribed_mods(db: &Pool<Sqlite>, server_id: i64) -> Result<Ve
     Ok(subscribed_mods)
 }
 
+/// The Factorio version mod suggestions are limited to on a server, if any.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn get_mod_version_filter(db: &Pool<Sqlite>, guild_id: Option<GuildId>) -> Option<String> {
+    let server_id = guild_id?.get() as i64;
+    match sqlx::query!(r#"SELECT mod_version FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await {
+        Ok(record) => record.and_then(|r| r.mod_version),
+        Err(e) => {
+            error!("Error reading mod version filter of server {server_id}: {e}");
+            None
+        },
+    }
+}
+
 pub async fn get_subscribed_authors(db: &Pool<Sqlite>, server_id: i64) -> Result<Vec<String>, Error> {
     let subscribed_authors = sqlx::query!(r#"SELECT author_name FROM subscribed_authors WHERE server_id = $1"#, server_id)
         .fetch_all(db)
@@ -24,4 +109,78 @@ pub async fn get_subscribed_authors(db: &Pool<Sqlite>, server_id: i64) -> Result
         .filter_map(|m| m.author_name)
         .collect::<Vec<String>>();
     Ok(subscribed_authors)
-}
\ No newline at end of file
+}
+
+/// Whether a server receives updates of a mod. Servers without any subscriptions receive all updates.
+pub fn is_subscribed(subscribed_mods: &[String], subscribed_authors: &[String], name: &str, author: &str) -> bool {
+    (subscribed_mods.is_empty() && subscribed_authors.is_empty()) ||
+        subscribed_mods.iter().any(|m| m == name) ||
+        subscribed_authors.iter().any(|a| a == author)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_errors::request_failed;
+
+    #[sqlx::test]
+    async fn subscriptions_per_server(db: Pool<Sqlite>) {
+        for (server_id, mod_name, author_name) in [(1, "Krastorio2", "raiguard"), (1, "flib", "Klonan"), (2, "Krastorio2", "Earendel")] {
+            sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name) VALUES ($1, $2)"#, server_id, mod_name)
+                .execute(&db)
+                .await
+                .unwrap();
+            sqlx::query!(r#"INSERT INTO subscribed_authors (server_id, author_name) VALUES ($1, $2)"#, server_id, author_name)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        let mut mods = get_subscribed_mods(&db, 1).await.unwrap();
+        mods.sort();
+        assert_eq!(mods, vec!["Krastorio2", "flib"]);
+        assert_eq!(get_subscribed_authors(&db, 2).await.unwrap(), vec!["Earendel"]);
+
+        sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1 AND mod_name = $2"#, 1, "flib")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(get_subscribed_mods(&db, 1).await.unwrap(), vec!["Krastorio2"]);
+        assert!(get_subscribed_mods(&db, 3).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_subscription_message() {
+        let error = ModError::UnknownSubscription{kind: "mod".to_owned(), name: "krastorio".to_owned(), suggestion: Some("Krastorio2".to_owned())};
+        assert_eq!(error.to_string(), "Could not find mod `krastorio`. Did you mean `Krastorio2`? Use the `force` option to subscribe anyway.");
+        let error = ModError::UnknownSubscription{kind: "author".to_owned(), name: "nobody".to_owned(), suggestion: None};
+        assert_eq!(error.to_string(), "Could not find author `nobody`. Use the `force` option to subscribe anyway.");
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        // Accepts connections but never answers them
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/mods", listener.local_addr().unwrap());
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let error = client.get(url).send().await.unwrap_err();
+        let error = request_failed("mod portal API", error, ModError::Timeout);
+        assert_eq!(error.downcast_ref::<ModError>(), Some(&ModError::Timeout("mod portal API")));
+        assert_eq!(error.to_string(), "Timed out while accessing mod portal API, try again later");
+    }
+
+    #[test]
+    fn subscription_filter() {
+        let none: Vec<String> = Vec::new();
+        assert!(is_subscribed(&none, &none, "flib", "raiguard"));
+        let mods = vec!["flib".to_owned()];
+        let authors = vec!["Earendel".to_owned()];
+        assert!(is_subscribed(&mods, &authors, "flib", "raiguard"));
+        assert!(is_subscribed(&mods, &authors, "space-exploration", "Earendel"));
+        assert!(!is_subscribed(&mods, &authors, "Krastorio2", "raiguard"));
+        assert!(!is_subscribed(&none, &authors, "flib", "raiguard"));
+    }
+}
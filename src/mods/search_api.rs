@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 use serde::Deserialize;
+use sqlx::SqlitePool;
 use crate::{
-    custom_errors::CustomError, 
+    custom_errors::request_failed,
+    database,
     Error, 
-    formatting_tools::DiscordFormat,
+    formatting_tools::{DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT, EMBED_TITLE_LIMIT},
+    mods::{update_notifications, ModError},
 };
 
 pub struct ModPortalCredentials {
@@ -33,6 +36,10 @@ pub struct FoundMod {
     pub title: String,
     #[serde(default = "default_version")]
     pub factorio_version: String,
+    #[serde(skip)]
+    pub released_at: Option<i64>,
+    #[serde(default)]
+    pub deprecated: bool,
 }
 
 fn default_version() -> String {
@@ -43,52 +50,121 @@ impl FoundMod {
     pub fn sanitize_for_embed(&mut self) {
         self.title = self.title
             .clone()
-            .truncate_for_embed(256)
+            .truncate_for_embed(EMBED_TITLE_LIMIT)
             .escape_formatting();
         self.summary = self.summary
             .clone()
-            .truncate_for_embed(4096)
+            .truncate_for_embed(EMBED_DESCRIPTION_LIMIT)
             .escape_formatting();
         self.owner = self.owner
             .clone()
-            .truncate_for_embed(1024)
+            .truncate_for_embed(EMBED_FIELD_LIMIT)
             .escape_formatting();
     }
 }
 
-pub async fn find_mod(name: &str, credentials: &ModPortalCredentials) -> Result<FoundMod, Error> {
-    let mut name_truncated = name.to_owned();
-    name_truncated.truncate(50);
+/// Sort orders supported by the mod portal search API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchSort {
+    #[default]
+    Relevancy,
+    Downloads,
+    Updated,
+}
+
+impl SearchSort {
+    const fn api_attribute(self) -> &'static str {
+        match self {
+            Self::Relevancy => "relevancy",
+            Self::Downloads => "downloads",
+            Self::Updated => "updated_at",
+        }
+    }
+}
+
+pub async fn find_mod(
+        client: &reqwest::Client, 
+        db: &SqlitePool, 
+        name: &str, 
+        credentials: Option<&ModPortalCredentials>
+    ) -> Result<FoundMod, Error> {
+    let results = search_mods(client, db, name, credentials, SearchSort::Relevancy, 1).await?;
+    let Some(mut mod_entry) = results.into_iter().next() else {
+        return Err(Box::new(ModError::ModNotFound(name.to_owned())))
+    };
+    if credentials.is_none() {
+        // The local database has no thumbnails, the public mod info does
+        if let Ok(thumbnail) = update_notifications::get_mod_thumbnail(client, &mod_entry.name).await {
+            mod_entry.thumbnail = thumbnail;
+        }
+    }
+    Ok(mod_entry)
+}
+
+/// Searches the mod portal, returning up to `count` mods in the given sort order.
+/// Without credentials the local copy of the mod list is searched instead.
+pub async fn search_mods(
+        client: &reqwest::Client, 
+        db: &SqlitePool, 
+        query: &str, 
+        credentials: Option<&ModPortalCredentials>, 
+        sort: SearchSort, 
+        count: u32
+    ) -> Result<Vec<FoundMod>, Error> {
+    let Some(credentials) = credentials else {
+        return search_local_mods(db, query, sort, count).await;
+    };
+    let query_truncated = query.chars().take(50).collect::<String>();
+    let page_size = count.to_string();
     let map = HashMap::from([
         ("username", credentials.username.as_str()),
         ("token", credentials.token.as_str()),
-        ("query", name_truncated.as_str()),
+        ("query", query_truncated.as_str()),
         ("version", "2.0"),
-        ("sort_attribute", "relevancy"),
+        ("sort_attribute", sort.api_attribute()),
         ("only_bookmarks", "false"),
         ("show_deprecated", "false"),
         ("page", "1"),
-        ("page_size", "1"),
+        ("page_size", page_size.as_str()),
         ("highlight_pre_tag", ""),
         ("highlight_post_tag", "")
     ]);
 
-    let client = reqwest::Client::new();
     let response = client.post("https://mods.factorio.com/api/search")
         .json(&map)
         .send()
-        .await?;
+        .await
+        .map_err(|e| request_failed("mod portal search API", e, ModError::Timeout))?;
     match response.status() {
         reqwest::StatusCode::OK => (),
-        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing mod search API", response.status().as_str())))),
+        _ => return Err(Box::new(ModError::HttpStatus{api: "mod search API", status: response.status().as_str().to_owned()})),
     };
     
-    let found_mod_details = response.json::<SearchApiResponse>().await.unwrap();
+    let found_mod_details = response.json::<SearchApiResponse>().await.map_err(|e| request_failed("mod portal search API", e, ModError::Timeout))?;
+    let results = found_mod_details.results
+        .into_iter()
+        .map(|mut mod_entry| {
+            mod_entry.thumbnail = update_notifications::thumbnail_url(Some(&mod_entry.thumbnail));
+            mod_entry
+        })
+        .collect::<Vec<FoundMod>>();
+    Ok(results)
+}
 
-    if found_mod_details.results.first().is_none() {
-        return Err(Box::new(CustomError::new(&format!("Did not find any mods named {name}"))))
-    };
-    let mut mod_entry = found_mod_details.results.first().unwrap().to_owned();
-    mod_entry.thumbnail = format!("https://assets-mod.factorio.com{}", mod_entry.thumbnail);
-    Ok(mod_entry)
-}
\ No newline at end of file
+async fn search_local_mods(db: &SqlitePool, query: &str, sort: SearchSort, count: u32) -> Result<Vec<FoundMod>, Error> {
+    let results = database::search_mods(db, query, sort.api_attribute(), i64::from(count)).await?
+        .into_iter()
+        .map(|m| FoundMod {
+            downloads_count: m.downloads_count,
+            title: m.title.unwrap_or_else(|| m.name.clone()),
+            name: m.name,
+            owner: m.owner,
+            summary: m.summary.unwrap_or_default(),
+            thumbnail: update_notifications::fallback_thumbnail_url(),
+            factorio_version: if m.factorio_versions.is_empty() { default_version() } else { m.factorio_versions.replace(',', ", ") },
+            released_at: None,
+            deprecated: false,
+        })
+        .collect::<Vec<FoundMod>>();
+    Ok(results)
+}
@@ -1,17 +1,20 @@
 use serde::{Deserialize, Serialize};
-use serenity::all::{Colour, CreateEmbed, CreateMessage};
+use serenity::all::{Colour, CreateActionRow, CreateButton, CreateEmbed, CreateMessage, ExecuteWebhook, Webhook};
 use sqlx::{Pool, Sqlite};
 use std::{fmt, sync::{Arc, RwLock}};
 use log::{error, info};
 
 use crate::{
-    custom_errors::CustomError,
     Error,
+    LARGE_DOWNLOAD_TIMEOUT,
+    custom_errors::request_failed,
     mods::{
+        ModError,
         get_subscribed_authors,
         get_subscribed_mods,
+        is_subscribed,
     },
-    formatting_tools::DiscordFormat,
+    formatting_tools::{relative_timestamp, truncate_with_read_more, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_TITLE_LIMIT},
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -48,31 +51,41 @@ pub struct Mod {
     pub category: Option<Category>,
     pub thumbnail: Option<String>,
     pub changelog: Option<String>,
+    pub description: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub releases: Option<Vec<Release>>,
+    pub deprecated: Option<bool>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Release {
-    info_json: InfoJson,
-    released_at: String,
-    version: String,
+    pub info_json: InfoJson,
+    pub released_at: String,
+    pub version: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InfoJson {
-    factorio_version: String
+    pub factorio_version: String,
+    pub dependencies: Option<Vec<String>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, poise::ChoiceParameter)]
 #[serde(rename_all = "kebab-case")]
 pub enum Category {
     #[serde(alias = "")]
     #[serde(alias = "no-category")]
+    #[name = "No category"]
     Uncategorized,
     Content,
     Overhaul,
     Tweaks,
     Utilities,
     Scenarios,
+    #[name = "Mod packs"]
     ModPacks,
     Localizations,
     Internal,
@@ -100,29 +113,34 @@ pub enum ModState{
 }
 
 #[allow(clippy::module_name_repetitions)]
-pub async fn get_mods(page: i32, initializing: bool) -> Result<ApiResponse, Error> {
+pub async fn get_mods(client: &reqwest::Client, page: i32, initializing: bool) -> Result<ApiResponse, Error> {
 
     let url = if initializing {     // Load entire database at once during initialization, use pagination when updating.
         "https://mods.factorio.com/api/mods?page_size=max".to_string()
     } else {
         format!("https://mods.factorio.com/api/mods?page_size=25&sort=updated_at&sort_order=desc&page={page}")};
-    let response = reqwest::get(url).await?;
+    let mut request = client.get(url);
+    if initializing {
+        request = request.timeout(LARGE_DOWNLOAD_TIMEOUT);
+    }
+    let response = request.send().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?;
     match response.status() {
         reqwest::StatusCode::OK => (),
-        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing mod portal API", response.status().as_str())))),
+        _ => return Err(Box::new(ModError::HttpStatus{api: "mod portal API", status: response.status().as_str().to_owned()})),
     };
-    Ok(response.json::<ApiResponse>().await?)
+    Ok(response.json::<ApiResponse>().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?)
 }
 
 pub async fn update_database(
         db: Pool<Sqlite>, 
+        client: &reqwest::Client, 
         cache_http: &Arc<poise::serenity_prelude::Http>, 
         initializing: bool
     ) -> Result<(), Error> {
     let mut page = 1;
     let mut old_mod_encountered = false;
     while !old_mod_encountered {
-        let mods = get_mods(page, initializing).await?;
+        let mods = get_mods(client, page, initializing).await?;
         page += 1;
         for result in mods.results {
 
@@ -134,7 +152,10 @@ pub async fn update_database(
             let timestamp = chrono::DateTime::parse_from_rfc3339(&released_at).map_or(0, |datetime| datetime.timestamp());
 
             let state;
-            let record = sqlx::query!(r#"SELECT released_at FROM mods WHERE name = $1"#, result.name).fetch_optional(&db).await?;
+            let record = sqlx::query!(r#"SELECT released_at, factorio_versions FROM mods WHERE name = $1"#, result.name).fetch_optional(&db).await?;
+            // The mod list only includes the latest release, so keep the versions of older releases that are already known
+            let known_versions = record.as_ref().map(|r| split_list(&r.factorio_versions)).unwrap_or_default();
+            let factorio_versions = merge_versions(known_versions, supported_versions(&result)).join(",");
 
             if let Some(rec) = record { // Mod found in database
                 if rec.released_at == timestamp {
@@ -149,9 +170,24 @@ pub async fn update_database(
                 info!("New mod found: {}", result.title);
             };
             
-            sqlx::query!(r#"INSERT OR REPLACE INTO mods 
-                    (name, title, owner, summary, category, downloads_count, factorio_version, version, released_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#, 
+            let deprecated = result.deprecated.unwrap_or(false);
+            let tags = result.tags.join(",");
+            // The mod list usually has no tags, so known tags are kept unless it lists some
+            sqlx::query!(r#"INSERT INTO mods 
+                    (name, title, owner, summary, category, downloads_count, factorio_version, version, released_at, deprecated, tags, factorio_versions)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    ON CONFLICT(name) DO UPDATE SET
+                        title = excluded.title,
+                        owner = excluded.owner,
+                        summary = excluded.summary,
+                        category = excluded.category,
+                        downloads_count = excluded.downloads_count,
+                        factorio_version = excluded.factorio_version,
+                        version = excluded.version,
+                        released_at = excluded.released_at,
+                        deprecated = excluded.deprecated,
+                        tags = CASE WHEN excluded.tags = '' THEN mods.tags ELSE excluded.tags END,
+                        factorio_versions = excluded.factorio_versions"#, 
                     result.name,
                     result.title,
                     result.owner,
@@ -160,25 +196,19 @@ pub async fn update_database(
                     result.downloads_count,
                     factorio_version,
                     version,
-                    timestamp)
+                    timestamp,
+                    deprecated,
+                    tags,
+                    factorio_versions)
                     .execute(&db)
                     .await?;
             
             if !initializing {  // Only send messages when not initializing database
-                let thumbnail = get_mod_thumbnail(&result.name).await?;
-                let mod_info = get_mod_info(&result.name).await?;
-                let changelogs = get_mod_changelog(&mod_info);
-                let changelog = format_mod_changelog(&changelogs, &version, 15).unwrap_or_default();
-                let updated_mod = UpdatedMod{
-                    name: result.name,
-                    title: result.title,
-                    author: result.owner,
-                    version,
-                    thumbnail,
-                    changelog,
-                    state
-                };
-                send_mod_update(updated_mod, db.clone(), cache_http).await?;
+                // One mod failing shouldn't stop the updates of the mods after it
+                let name = result.name.clone();
+                if let Err(e) = process_mod_update(&db, client, cache_http, result, version, timestamp, state).await {
+                    error!("Error processing update of mod {name}: {e}");
+                }
             }
         };
         if initializing {
@@ -189,23 +219,68 @@ pub async fn update_database(
     Ok(())
 }
 
+/// Stores the full info of a new or updated mod and notifies the servers subscribed to it.
+async fn process_mod_update(
+        db: &Pool<Sqlite>, 
+        client: &reqwest::Client, 
+        cache_http: &Arc<poise::serenity_prelude::Http>, 
+        result: Mod, 
+        version: String, 
+        timestamp: i64, 
+        state: ModState
+    ) -> Result<(), Error> {
+    let thumbnail = get_mod_thumbnail(client, &result.name).await?;
+    let mod_info = get_mod_info(client, &result.name).await?;
+    // The full mod info always lists tags, the mod list may not
+    store_mod_tags(db, &result.name, &mod_info.tags).await?;
+    // It also lists all releases, and with that all supported Factorio versions
+    if mod_info.releases.is_some() {
+        let factorio_versions = supported_versions(&mod_info).join(",");
+        sqlx::query!(r#"UPDATE mods SET factorio_versions = $1 WHERE name = $2"#, factorio_versions, result.name)
+            .execute(db)
+            .await?;
+    }
+    let changelogs = get_mod_changelog(&mod_info);
+    store_mod_changelog(db, &result.name, &changelogs).await?;
+    let updated_mod = UpdatedMod{
+        name: result.name,
+        title: result.title,
+        author: result.owner,
+        version,
+        thumbnail,
+        changelogs,
+        released_at: timestamp,
+        state
+    };
+    send_mod_update(updated_mod, db.clone(), cache_http).await
+}
+
 struct UpdatedMod{
     name: String,
     title: String,
     author: String,
     version: String,
     thumbnail: String,
-    changelog: String,
+    changelogs: Vec<ModChangelogEntry>,
+    released_at: i64,
     state: ModState,
 }
 
 struct Server {
     id: i64,
     updates_channel: Option<i64>,
+    updates_webhook: Option<String>,
+    updates_thread_parent: Option<i64>,
     show_changelog: bool,
+    changelog_length: usize,
+    digest_interval: Option<i64>,
 }
 
-#[allow(clippy::cast_sign_loss)]
+// Number of changelog lines shown in update messages when a server has not set a length
+pub const DEFAULT_CHANGELOG_LENGTH: usize = 15;
+// Highest number of changelog lines a server can choose to show in update messages
+pub const MAX_CHANGELOG_LENGTH: usize = 60;
+
 async fn send_mod_update(
         updated_mod: UpdatedMod, 
         db: Pool<Sqlite>, 
@@ -220,7 +295,13 @@ async fn send_mod_update(
             Ok(Server{
                 id: s.server_id,
                 updates_channel: s.updates_channel,
+                updates_webhook: s.updates_webhook,
+                updates_thread_parent: s.updates_thread_parent,
                 show_changelog: s.show_changelog.unwrap_or(true),
+                changelog_length: s.changelog_length
+                    .and_then(|l| usize::try_from(l).ok())
+                    .map_or(DEFAULT_CHANGELOG_LENGTH, |l| l.min(MAX_CHANGELOG_LENGTH)),
+                digest_interval: s.digest_interval,
             })
         })
         .collect::<Vec<Result<Server, Error>>>();
@@ -236,25 +317,210 @@ async fn send_mod_update(
         let subscribed_authors = get_subscribed_authors(&db, server.id).await?;
 
         
-        let updates_channel: poise::serenity_prelude::ChannelId = match server.updates_channel {
-            Some(ch) => poise::serenity_prelude::ChannelId::new(ch as u64),
-            None => continue,
+        let Some(destination) = UpdateDestination::from_settings(server.updates_channel, server.updates_thread_parent, server.updates_webhook) else {
+            continue;
         };
 
-        if (subscribed_mods.is_empty() && subscribed_authors.is_empty()) || // No subscriptions
-            subscribed_mods.contains(&updated_mod.name) ||      // Subscribed to mod
-            subscribed_authors.contains(&updated_mod.author)    // Subscribed to author
-        {
-            make_update_message(&updated_mod, updates_channel, server.show_changelog, cache_http).await?;
+        if is_subscribed(&subscribed_mods, &subscribed_authors, &updated_mod.name, &updated_mod.author) {
+            if server.digest_interval.is_some() {
+                queue_digest_update(&db, server.id, &updated_mod).await?;
+            } else {
+                make_update_message(&updated_mod, &destination, server.show_changelog, server.changelog_length, cache_http).await?;
+            }
         }
     }
     Ok(())
 }
 
+/// Where a server receives its update messages. A webhook takes priority over the updates channel.
+pub enum UpdateDestination {
+    Channel(poise::serenity_prelude::ChannelId),
+    Webhook {
+        url: String,
+        // Updates thread and its parent channel, webhooks can only post in threads of their own channel
+        thread: Option<(poise::serenity_prelude::ChannelId, poise::serenity_prelude::ChannelId)>,
+    },
+}
+
+impl UpdateDestination {
+    #[allow(clippy::cast_sign_loss)]
+    pub fn from_settings(
+            updates_channel: Option<i64>, 
+            updates_thread_parent: Option<i64>, 
+            updates_webhook: Option<String>
+        ) -> Option<Self> {
+        match (updates_webhook, updates_channel) {
+            (Some(url), _) => {
+                let thread = updates_channel.zip(updates_thread_parent)
+                    .map(|(ch, parent)| (poise::serenity_prelude::ChannelId::new(ch as u64), poise::serenity_prelude::ChannelId::new(parent as u64)));
+                Some(Self::Webhook { url, thread })
+            },
+            (None, Some(ch)) => Some(Self::Channel(poise::serenity_prelude::ChannelId::new(ch as u64))),
+            (None, None) => None,
+        }
+    }
+
+    /// Sends an embed to the destination, using `avatar_url` as the webhook avatar if given.
+    /// Components are left out for webhooks the bot doesn't own, Discord rejects them there.
+    async fn send(
+            &self, 
+            cache_http: &Arc<poise::serenity_prelude::Http>, 
+            embed: CreateEmbed, 
+            avatar_url: Option<&str>,
+            components: Vec<CreateActionRow>,
+        ) -> Result<(), Error> {
+        self.send_as(cache_http, UPDATES_WEBHOOK_NAME, embed, avatar_url, components).await
+    }
+
+    /// Like `send`, with `username` shown as the sender of webhook messages.
+    pub async fn send_as(
+            &self, 
+            cache_http: &Arc<poise::serenity_prelude::Http>, 
+            username: &str, 
+            embed: CreateEmbed, 
+            avatar_url: Option<&str>,
+            components: Vec<CreateActionRow>,
+        ) -> Result<(), Error> {
+        match self {
+            Self::Channel(channel) => {
+                channel.send_message(cache_http, CreateMessage::new().embed(embed).components(components)).await?;
+            },
+            Self::Webhook { url, thread } => {
+                let webhook = Webhook::from_url(cache_http, url).await?;
+                let mut builder = ExecuteWebhook::new()
+                    .username(username)
+                    .embed(embed);
+                if let Some(avatar) = avatar_url {
+                    builder = builder.avatar_url(avatar);
+                }
+                if webhook.application_id.is_some() {
+                    builder = builder.components(components);
+                }
+                if let Some((thread_id, parent_id)) = thread {
+                    if webhook.channel_id == Some(*parent_id) {
+                        builder = builder.in_thread(*thread_id);
+                    }
+                }
+                webhook.execute(cache_http, false, builder).await?;
+            },
+        };
+        Ok(())
+    }
+}
+
+// Name shown on update messages sent through a webhook
+const UPDATES_WEBHOOK_NAME: &str = "Factorio Mod Portal";
+
+/// Checks whether a URL looks like a Discord webhook URL.
+pub fn is_discord_webhook_url(url: &str) -> bool {
+    ["https://discord.com/api/webhooks/", "https://discordapp.com/api/webhooks/", 
+        "https://ptb.discord.com/api/webhooks/", "https://canary.discord.com/api/webhooks/"]
+        .iter()
+        .filter_map(|prefix| url.strip_prefix(prefix))
+        .any(|rest| {
+            let parts = rest.trim_end_matches('/').split('/').collect::<Vec<&str>>();
+            match parts.as_slice() {
+                [id, token] => !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) && !token.is_empty(),
+                _ => false,
+            }
+        })
+}
+
+// Highest number of hours a server can set between update digests
+pub const MAX_DIGEST_INTERVAL: u32 = 168;
+
+async fn queue_digest_update(db: &Pool<Sqlite>, server_id: i64, updated_mod: &UpdatedMod) -> Result<(), Error> {
+    let is_new = matches!(updated_mod.state, ModState::New);
+    let queued_at = chrono::Utc::now().timestamp();
+    // Keep the original state when a mod is updated again before the digest is sent
+    sqlx::query!(r#"INSERT INTO pending_updates (server_id, mod_name, title, author, version, is_new, queued_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (server_id, mod_name) DO UPDATE SET 
+            title = excluded.title, 
+            author = excluded.author, 
+            version = excluded.version, 
+            queued_at = excluded.queued_at"#,
+        server_id, updated_mod.name, updated_mod.title, updated_mod.author, updated_mod.version, is_new, queued_at)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Sends a summary of all queued mod updates to servers whose digest interval has passed.
+pub async fn send_update_digests(
+        db: Pool<Sqlite>, 
+        cache_http: &Arc<poise::serenity_prelude::Http>
+    ) -> Result<(), Error> {
+    let now = chrono::Utc::now().timestamp();
+    let servers = sqlx::query!(r#"SELECT server_id, updates_channel, updates_thread_parent, updates_webhook, digest_interval, last_digest FROM servers 
+        WHERE digest_interval IS NOT NULL AND (updates_channel IS NOT NULL OR updates_webhook IS NOT NULL)"#)
+        .fetch_all(&db)
+        .await?;
+    for server in servers {
+        let Some(interval) = server.digest_interval else {
+            continue;
+        };
+        let Some(destination) = UpdateDestination::from_settings(server.updates_channel, server.updates_thread_parent, server.updates_webhook) else {
+            continue;
+        };
+        if server.last_digest.unwrap_or(0) + interval * 3600 > now {
+            continue;
+        }
+        let pending = sqlx::query!(r#"SELECT mod_name, title, author, version, is_new FROM pending_updates 
+            WHERE server_id = $1 AND queued_at <= $2 ORDER BY queued_at ASC"#, server.server_id, now)
+            .fetch_all(&db)
+            .await?;
+        if !pending.is_empty() {
+            let lines = pending.iter()
+                .map(|p| {
+                    let new_marker = if p.is_new { " (new)" } else { "" };
+                    format!("[{}](https://mods.factorio.com/mod/{}) {} by {}{new_marker}", 
+                        p.title.clone().escape_formatting(), 
+                        p.mod_name.replace(' ', "%20"), 
+                        p.version, 
+                        p.author.clone().escape_formatting())
+                })
+                .collect::<Vec<String>>();
+            let embed = CreateEmbed::new()
+                .title(format!("Mod updates digest ({} mods)", lines.len()))
+                .color(Colour::from_rgb(0x58, 0x65, 0xF2))
+                .description(format_digest_lines(&lines, EMBED_DESCRIPTION_LIMIT));
+            if let Err(e) = destination.send(cache_http, embed, None, Vec::new()).await {
+                error!("Error sending update digest: {e}");
+                continue;   // Keep updates queued to retry on next run
+            }
+        }
+        sqlx::query!(r#"DELETE FROM pending_updates WHERE server_id = $1 AND queued_at <= $2"#, server.server_id, now)
+            .execute(&db)
+            .await?;
+        sqlx::query!(r#"UPDATE servers SET last_digest = $1 WHERE server_id = $2"#, now, server.server_id)
+            .execute(&db)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Joins digest lines up to `max_len` characters, noting how many lines did not fit.
+fn format_digest_lines(lines: &[String], max_len: usize) -> String {
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let more = format!("...and {} more", lines.len() - i);
+        // Always leave room to note how many updates were left out
+        if out.chars().count() + line.chars().count() + more.chars().count() + 2 > max_len {
+            out.push_str(&more);
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim_end().to_owned()
+}
+
 async fn make_update_message(
         updated_mod: &UpdatedMod, 
-        updates_channel: serenity::model::prelude::ChannelId,
+        destination: &UpdateDestination,
         show_changelog: bool,
+        changelog_length: usize,
         cache_http: &Arc<serenity::all::Http>
     ) -> Result<(), Error> {
     let mut url = String::new();
@@ -268,110 +534,368 @@ async fn make_update_message(
         ModState::Updated => format!("Updated mod:\n{}", updated_mod.title.clone().escape_formatting()),
         ModState::New => format!("New mod:\n{}", updated_mod.title.clone().escape_formatting()),
     };
-    let changelog = if show_changelog { updated_mod.changelog.clone() } else { String::new() };
+    let changelog = if show_changelog {
+        format_mod_changelog(&updated_mod.changelogs, &updated_mod.version, changelog_length).unwrap_or_default()
+    } else { 
+        String::new() 
+    };
     let author_link = format!("{} ([more](https://mods.factorio.com/user/{}))", updated_mod.author.clone().escape_formatting(), &updated_mod.author);
-    let embed = CreateEmbed::new()
-        .title(title.truncate_for_embed(256))
+    let mut embed = CreateEmbed::new()
+        .title(title.truncate_for_embed(EMBED_TITLE_LIMIT))
+        .description(truncate_with_read_more(&changelog, EMBED_DESCRIPTION_LIMIT, &format!("{url}/changelog")))
         .url(url)
         .color(color)
-        .description(changelog.truncate_for_embed(4096))
         .field("**Author**", &author_link, true)
         .field("**Version**", &updated_mod.version, true)
         .thumbnail(&updated_mod.thumbnail);
-    let builder = CreateMessage::new().embed(embed);
-    match updates_channel.send_message(cache_http, builder).await {
+    if updated_mod.released_at > 0 {
+        embed = embed.field("**Released**", relative_timestamp(updated_mod.released_at), true);
+    }
+    let components = updated_mod.changelogs.iter()
+        .any(|c| c.version == updated_mod.version)
+        .then(|| changelog_button_id(&updated_mod.name, &updated_mod.version))
+        .flatten()
+        .map(|id| vec![CreateActionRow::Buttons(vec![CreateButton::new(id).label("Full changelog")])])
+        .unwrap_or_default();
+    match destination.send(cache_http, embed, Some(&updated_mod.thumbnail), components).await {
         Ok(_) => {},
         Err(e) => error!("Error sending message: {e}"),
     };
     Ok(())
 }
 
-pub async fn get_mod_thumbnail(name: &String) -> Result<String, Error> {
+// Custom ids of "Full changelog" buttons are this prefix followed by `version:mod name`
+const CHANGELOG_BUTTON_PREFIX: &str = "full_changelog:";
+// Longest custom id Discord accepts
+const CUSTOM_ID_LIMIT: usize = 100;
+
+fn changelog_button_id(name: &str, version: &str) -> Option<String> {
+    let id = format!("{CHANGELOG_BUTTON_PREFIX}{version}:{name}");
+    (id.chars().count() <= CUSTOM_ID_LIMIT).then_some(id)
+}
+
+/// Reads the mod name and version from the custom id of a "Full changelog" button.
+pub fn parse_changelog_button_id(id: &str) -> Option<(&str, &str)> {
+    let (version, name) = id.strip_prefix(CHANGELOG_BUTTON_PREFIX)?.split_once(':')?;
+    Some((name, version))
+}
+
+/// Embed with the complete changelog of one version of a mod.
+pub async fn full_changelog_embed(client: &reqwest::Client, name: &str, version: &str) -> Result<CreateEmbed, Error> {
+    let mod_info = get_mod_info(client, name).await?;
+    let changelogs = get_mod_changelog(&mod_info);
+    let Some(entry) = changelogs.iter().find(|c| c.version == version) else {
+        return Err(Box::new(ModError::ChangelogNotFound{modname: name.to_owned(), version: version.to_owned()}));
+    };
+    let url = format!("https://mods.factorio.com/mod/{}/changelog", name.replace(' ', "%20"));
+    let changelog = format_changelog_categories(&entry.categories, usize::MAX);
+    Ok(CreateEmbed::new()
+        .title(format!("{} {version}", mod_info.title).truncate_for_embed(EMBED_TITLE_LIMIT))
+        .url(&url)
+        .description(truncate_with_read_more(&changelog, EMBED_DESCRIPTION_LIMIT, &url))
+        .color(Colour::from_rgb(0x58, 0x65, 0xF2)))
+}
+
+const MOD_ASSETS_URL: &str = "https://assets-mod.factorio.com";
+/// Thumbnail path the mod portal gives mods without an image
+const PLACEHOLDER_THUMBNAIL: &str = "/assets/.thumb.png";
+
+/// Thumbnail shown for mods without an image.
+/// Set `FALLBACK_THUMBNAIL_URL` to use another image than the mod portal placeholder.
+pub fn fallback_thumbnail_url() -> String {
+    std::env::var("FALLBACK_THUMBNAIL_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| format!("{MOD_ASSETS_URL}{PLACEHOLDER_THUMBNAIL}"))
+}
+
+/// Turns a thumbnail path from the mod portal into a url, using the fallback thumbnail for mods without an image.
+pub fn thumbnail_url(thumbnail: Option<&str>) -> String {
+    thumbnail
+        .filter(|t| !t.is_empty() && *t != PLACEHOLDER_THUMBNAIL)
+        .map_or_else(fallback_thumbnail_url, |t| format!("{MOD_ASSETS_URL}{t}"))
+}
+
+/// Factorio versions the mod has releases for, oldest first.
+/// Mods from the mod list only include their latest release.
+pub fn supported_versions(mod_info: &Mod) -> Vec<String> {
+    let versions = mod_info.releases.as_ref()
+        .map_or_else(
+            || mod_info.latest_release.iter().map(|r| r.info_json.factorio_version.clone()).collect(),
+            |releases| releases.iter().map(|r| r.info_json.factorio_version.clone()).collect()
+        );
+    merge_versions(versions, Vec::new())
+}
+
+/// Combines two lists of Factorio versions, sorted and without duplicates.
+fn merge_versions(mut a: Vec<String>, b: Vec<String>) -> Vec<String> {
+    a.extend(b);
+    a.retain(|v| !v.is_empty());
+    a.sort_by_key(|v| v.split('.').map(|n| n.parse::<u32>().unwrap_or_default()).collect::<Vec<u32>>());
+    a.dedup();
+    a
+}
+
+/// Splits a comma separated list as stored in the database.
+pub fn split_list(list: &str) -> Vec<String> {
+    list.split(',').filter(|s| !s.is_empty()).map(str::to_owned).collect()
+}
+
+pub async fn get_mod_thumbnail(client: &reqwest::Client, name: &String) -> Result<String, Error> {
     let url = format!("https://mods.factorio.com/api/mods/{name}");
-    let response = reqwest::get(url).await?;
+    let response = client.get(url).send().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?;
     match response.status() {
         reqwest::StatusCode::OK => (),
-        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing mod portal API", response.status().as_str())))),
+        _ => return Err(Box::new(ModError::HttpStatus{api: "mod portal API", status: response.status().as_str().to_owned()})),
     };
-    let mod_info = response.json::<Mod>().await?;
-    let thumbnail_url = format!("https://assets-mod.factorio.com{}", mod_info.thumbnail.unwrap_or_else(|| "/assets/.thumb.png".to_owned()));
-    Ok(thumbnail_url)
+    let mod_info = response.json::<Mod>().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?;
+    Ok(thumbnail_url(mod_info.thumbnail.as_deref()))
 }
 
-#[derive(Debug, Clone, Default, PartialEq)]
-struct ModChangelogEntry {
-    version: String,
-    date: Option<String>,
-    categories: Vec<ModChangelogCategory>,
+/// Get the full size version of a mod's thumbnail. Returns None if the mod has no image.
+pub async fn get_mod_image(client: &reqwest::Client, name: &str) -> Result<Option<String>, Error> {
+    let url = format!("https://mods.factorio.com/api/mods/{name}");
+    let response = client.get(url).send().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?;
+    match response.status() {
+        reqwest::StatusCode::OK => (),
+        _ => return Err(Box::new(ModError::HttpStatus{api: "mod portal API", status: response.status().as_str().to_owned()})),
+    };
+    let mod_info = response.json::<Mod>().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?;
+    Ok(full_size_image_url(mod_info.thumbnail.as_deref()))
+}
+
+/// Turns a thumbnail path from the mod portal into the url of the full size image.
+/// The placeholder thumbnail used for mods without an image gives None.
+pub fn full_size_image_url(thumbnail: Option<&str>) -> Option<String> {
+    let thumbnail = thumbnail.filter(|t| !t.is_empty() && *t != PLACEHOLDER_THUMBNAIL)?;
+    let path = thumbnail.strip_suffix(".thumb.png").map_or_else(|| thumbnail.to_owned(), |p| format!("{p}.png"));
+    Some(format!("{MOD_ASSETS_URL}{path}"))
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
-struct ModChangelogCategory {
+pub struct ModChangelogEntry {
+    pub version: String,
+    pub date: Option<String>,
+    pub categories: Vec<ModChangelogCategory>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ModChangelogCategory {
     name: String,
     entries: Vec<String>,
 }
 
-async fn get_mod_info(name: &str) -> Result<Mod, Error> {
+async fn store_mod_tags(db: &Pool<Sqlite>, name: &str, tags: &[String]) -> Result<(), Error> {
+    let tags = tags.join(",");
+    sqlx::query!(r#"UPDATE mods SET tags = $1, tags_checked = TRUE WHERE name = $2"#, tags, name)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Mods whose tags are fetched per backfill run, to spread the requests over time
+const TAG_BACKFILL_BATCH: i64 = 50;
+
+/// Fetches the tags of mods that were only seen in the mod list, most downloaded first.
+pub async fn backfill_mod_tags(db: &Pool<Sqlite>, client: &reqwest::Client) -> Result<(), Error> {
+    let names = sqlx::query!(r#"SELECT name FROM mods WHERE tags_checked = FALSE ORDER BY downloads_count DESC LIMIT $1"#, TAG_BACKFILL_BATCH)
+        .fetch_all(db)
+        .await?;
+    for record in names {
+        match get_mod_info(client, &record.name).await {
+            Ok(mod_info) => store_mod_tags(db, &record.name, &mod_info.tags).await?,
+            Err(e) => error!("Error getting tags of mod {}: {e}", record.name),
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_mod_info(client: &reqwest::Client, name: &str) -> Result<Mod, Error> {
     let url = format!("https://mods.factorio.com/api/mods/{name}/full");
-    let response = reqwest::get(url).await?;
+    let response = client.get(url).send().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?;
     match response.status() {
         reqwest::StatusCode::OK => (),
-        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing mod portal API", response.status().as_str())))),
+        _ => return Err(Box::new(ModError::HttpStatus{api: "mod portal API", status: response.status().as_str().to_owned()})),
     };
-    Ok(response.json::<Mod>().await?)
+    Ok(response.json::<Mod>().await.map_err(|e| request_failed("mod portal API", e, ModError::Timeout))?)
+}
+
+/// Summarizes the dependencies listed in a mod's info.json. The base game is left out.
+pub fn summarize_dependencies(dependencies: &[String]) -> String {
+    let mut required = Vec::new();
+    let mut optional = 0;
+    let mut incompatible = Vec::new();
+    for dependency in dependencies {
+        let dependency = dependency.trim();
+        let (kind, rest) = ["(?)", "?", "!", "~"].iter()
+            .find_map(|prefix| dependency.strip_prefix(prefix).map(|rest| (*prefix, rest)))
+            .unwrap_or(("", dependency));
+        // Version requirements start at the first comparison operator
+        let name = rest.split(['<', '>', '=']).next().unwrap_or_default().trim();
+        if name.is_empty() || name == "base" {
+            continue;
+        }
+        match kind {
+            "!" => incompatible.push(name.to_owned().escape_formatting()),
+            "?" | "(?)" => optional += 1,
+            _ => required.push(name.to_owned().escape_formatting()),
+        }
+    }
+    let mut lines = Vec::new();
+    if !required.is_empty() {
+        lines.push(format!("**Required:** {}", required.join(", ")));
+    }
+    if optional > 0 {
+        lines.push(format!("**Optional:** {optional} mods"));
+    }
+    if !incompatible.is_empty() {
+        lines.push(format!("**Incompatible:** {}", incompatible.join(", ")));
+    }
+    if lines.is_empty() {
+        return "None".to_owned();
+    }
+    lines.join("\n")
 }
 
-fn get_mod_changelog(mod_info: &Mod) -> Vec<ModChangelogEntry> {
-    let versionsplit = "-".repeat(99);
+/// Replaces the stored changelog of a mod with the given parsed changelog entries.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn store_mod_changelog(db: &Pool<Sqlite>, name: &str, changelogs: &[ModChangelogEntry]) -> Result<(), Error> {
+    let mut transaction = db.begin().await?;
+    sqlx::query!(r#"DELETE FROM mod_changelogs WHERE mod_name = $1"#, name)
+        .execute(&mut *transaction)
+        .await?;
+    for (order, entry) in changelogs.iter().enumerate() {
+        if entry.version.is_empty() {
+            continue;
+        }
+        let entry_order = order as i64;
+        let categories = serde_json::to_string(&entry.categories)?;
+        sqlx::query!(r#"INSERT OR REPLACE INTO mod_changelogs (mod_name, version, entry_order, date, categories)
+            VALUES ($1, $2, $3, $4, $5)"#, name, entry.version, entry_order, entry.date, categories)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
 
-    if mod_info.changelog.is_none() {
-        return Vec::new()
+/// Reads the stored changelog of a mod, newest version first.
+pub async fn get_stored_changelog(db: &Pool<Sqlite>, name: &str) -> Result<Vec<ModChangelogEntry>, Error> {
+    let records = sqlx::query!(r#"SELECT version, date, categories FROM mod_changelogs WHERE mod_name = $1 ORDER BY entry_order ASC"#, name)
+        .fetch_all(db)
+        .await?;
+    let mut out = Vec::new();
+    for rec in records {
+        out.push(ModChangelogEntry {
+            version: rec.version,
+            date: rec.date,
+            categories: serde_json::from_str(&rec.categories)?,
+        });
     }
-    let ch = mod_info.changelog.as_ref().unwrap();
-    let version_entries = ch.split(&versionsplit);
+    Ok(out)
+}
+
+// Shortest line of dashes that is recognized as a separator between changelog versions.
+const CHANGELOG_SEPARATOR_MIN_LENGTH: usize = 20;
+
+fn is_changelog_separator(line: &str) -> bool {
+    let trimmed = line.trim_end();
+    trimmed.len() >= CHANGELOG_SEPARATOR_MIN_LENGTH && trimmed.chars().all(|c| c == '-')
+}
+
+pub fn get_mod_changelog(mod_info: &Mod) -> Vec<ModChangelogEntry> {
+    let Some(ch) = mod_info.changelog.as_ref() else {
+        return Vec::new()
+    };
     let mut out = Vec::new();
-    for changelog in version_entries {
-        let mut entry = ModChangelogEntry::default();
-        let mut current_category = ModChangelogCategory::default();
-
-        let lines = changelog.lines();
-        for line in lines {
-            if line.starts_with("Version: ") {
-                if !entry.version.is_empty() {
-                    entry.categories.push(current_category.clone());
-                    out.push(entry.clone());
-                };
-                current_category = ModChangelogCategory::default();
-                entry = ModChangelogEntry::default();
-                line.strip_prefix("Version: ").unwrap().clone_into(&mut entry.version);
-            } else if line.starts_with("Date: ") {
-                entry.date = Some(line.strip_prefix("Date: ").unwrap().to_owned());
-            } else if line.starts_with("    ") {
-                current_category.entries.push(line.strip_prefix("    ").unwrap().to_owned());
-            } else if line.starts_with("  ") {
-                if !current_category.name.is_empty() {
-                    entry.categories.push(current_category.clone());
-                };
-                current_category = ModChangelogCategory::default();
-                line.strip_prefix("  ").unwrap().clone_into(&mut current_category.name);
+    let mut entry = ModChangelogEntry::default();
+    let mut current_category = ModChangelogCategory::default();
+
+    // Separator lines and `Version:` headers both end the previous entry, 
+    // so changelogs with missing or differently sized separators are still split correctly.
+    for line in ch.lines() {
+        if is_changelog_separator(line) {
+            finish_changelog_entry(&mut out, &mut entry, &mut current_category);
+        } else if let Some(version) = line.strip_prefix("Version: ") {
+            finish_changelog_entry(&mut out, &mut entry, &mut current_category);
+            version.trim().clone_into(&mut entry.version);
+        } else if let Some(date) = line.strip_prefix("Date: ") {
+            entry.date = Some(date.trim().to_owned());
+        } else if let Some(text) = line.strip_prefix("    ") {
+            // Lines that don't start a new bullet continue the previous entry
+            match current_category.entries.last_mut() {
+                Some(previous) if !text.trim_start().starts_with('-') => {
+                    previous.push(' ');
+                    previous.push_str(text.trim());
+                },
+                _ => current_category.entries.push(text.to_owned()),
             }
+        } else if let Some(name) = line.strip_prefix("  ") {
+            if !current_category.name.is_empty() {
+                entry.categories.push(current_category.clone());
+            }
+            current_category = ModChangelogCategory::default();
+            name.clone_into(&mut current_category.name);
         }
-        entry.categories.push(current_category.clone());
-        out.push(entry);
     }
+    finish_changelog_entry(&mut out, &mut entry, &mut current_category);
     out
+}
 
+fn finish_changelog_entry(out: &mut Vec<ModChangelogEntry>, entry: &mut ModChangelogEntry, current_category: &mut ModChangelogCategory) {
+    let category = std::mem::take(current_category);
+    if !category.name.is_empty() || !category.entries.is_empty() {
+        entry.categories.push(category);
+    }
+    let finished = std::mem::take(entry);
+    if !finished.version.is_empty() {
+        out.push(finished);
+    }
 }
 
 fn format_mod_changelog(changelogs: &[ModChangelogEntry], version: &str, max_lines: usize) -> Option<String> {
     let right_changelog = changelogs.iter().find(|c| c.version == version)?;
-    
+    Some(format_changelog_categories(&right_changelog.categories, max_lines))
+}
+
+/// Returns all changelog entries newer than the older of the two versions, up to and including the newer one.
+///
+/// Changelogs are ordered newest first, so the order in which the versions are given does not matter.
+pub fn get_changelog_range(changelogs: &[ModChangelogEntry], version_a: &str, version_b: &str) -> Option<Vec<ModChangelogEntry>> {
+    let index_a = changelogs.iter().position(|c| c.version == version_a)?;
+    let index_b = changelogs.iter().position(|c| c.version == version_b)?;
+    let (newer, older) = if index_a < index_b { (index_a, index_b) } else { (index_b, index_a) };
+    Some(changelogs[newer..older].to_vec())
+}
+
+/// Combines the categories of multiple changelog entries, keeping entries of the same category together.
+pub fn merge_changelog_entries(changelogs: &[ModChangelogEntry]) -> Vec<ModChangelogCategory> {
+    let mut merged: Vec<ModChangelogCategory> = Vec::new();
+    for category in changelogs.iter().flat_map(|c| c.categories.iter()) {
+        if let Some(existing) = merged.iter_mut().find(|m| m.name == category.name) {
+            existing.entries.extend(category.entries.iter().cloned());
+        } else {
+            merged.push(category.clone());
+        }
+    }
+    merged
+}
+
+/// Formats a changelog category name as a bold header. Returns `None` for unnamed categories.
+fn format_category_header(name: &str) -> Option<String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(format!("**{}**", trimmed.escape_formatting()))
+}
+
+pub fn format_changelog_categories(categories: &[ModChangelogCategory], max_lines: usize) -> String {
     let mut lines = Vec::new();
-    for category in right_changelog
-        .categories.clone() 
+    for category in categories.to_owned()
     {
-        if !category.name.is_empty() {
-            lines.push(format!("**{}**", category.name.escape_formatting()));
+        if let Some(header) = format_category_header(&category.name) {
+            lines.push(header);
         }
         lines.append(&mut category.entries
             .iter()
@@ -383,7 +907,7 @@ fn format_mod_changelog(changelogs: &[ModChangelogEntry], version: &str, max_lin
         lines.truncate(max_lines);
         lines.push("<Trimmed>".to_owned());
     }
-    Some(lines.join("\n"))
+    lines.join("\n")
 }
 
 #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
@@ -399,10 +923,13 @@ pub struct ModCacheEntry {
     pub name: String,
     pub title: String,
     pub author: String,
-    pub factorio_version: String,
+    /// Factorio versions the mod has releases for, oldest first
+    pub factorio_versions: Vec<String>,
+    pub deprecated: bool,
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscriptionType {
     Author(String),
     Modname(String),
@@ -414,15 +941,21 @@ pub struct SubCacheEntry{
     pub subscription: SubscriptionType 
 }
 
+/// Removes a single subscription of a server from the subscription cache.
+pub fn remove_cached_subscription(cache: &mut Vec<SubCacheEntry>, server_id: i64, subscription: &SubscriptionType) {
+    cache.retain(|entry| !(entry.server_id == server_id && &entry.subscription == subscription));
+}
+
+const CACHED_FACTORIO_VERSIONS: [&str; 2] = ["1.1", "2.0"];
+
 pub async fn update_mod_cache(
     cache: Arc<RwLock<Vec<ModCacheEntry>>>, 
     db: Pool<Sqlite>
 ) -> Result<(), Error> {
     let records = sqlx::query!(r#"
-        SELECT name, title, owner, downloads_count, factorio_version 
+        SELECT name, title, owner, downloads_count, factorio_versions, deprecated, tags 
         FROM mods 
-        WHERE (factorio_version = $1 OR factorio_version = $2) 
-        ORDER BY downloads_count DESC"#, "1.1", "2.0"
+        ORDER BY downloads_count DESC"#
     )
         .fetch_all(&db)
         .await?
@@ -432,14 +965,18 @@ pub async fn update_mod_cache(
                 name: rec.name.clone(),
                 title: rec.title.clone().unwrap_or_default(), // Default if mod has no name (title)
                 author: rec.owner.clone(),
-                factorio_version: rec.factorio_version.clone().unwrap(), // Unwrap should be safe due to filters in sql query
+                factorio_versions: split_list(&rec.factorio_versions),
+                deprecated: rec.deprecated,
+                tags: split_list(&rec.tags),
             }
         })
+        // Only mods for current versions are suggested
+        .filter(|m| m.factorio_versions.iter().any(|v| CACHED_FACTORIO_VERSIONS.contains(&v.as_str())))
         .collect::<Vec<ModCacheEntry>>();
     match cache.write() {
         Ok(mut c) => *c = records,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ModError::Cache(e.to_string())));
         },
     };
     Ok(())
@@ -476,7 +1013,7 @@ pub async fn update_sub_cache(
     match cache.write() {
         Ok(mut c) => *c = mod_records,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ModError::Cache(e.to_string())));
         },
     };
 
@@ -499,7 +1036,7 @@ pub async fn update_author_cache(
     match cache.write() {
         Ok(mut c) => *c = author_records,
         Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
+            return Err(Box::new(ModError::Cache(e.to_string())));
         },
     };
     Ok(())
@@ -521,6 +1058,12 @@ mod tests{
             title: String::from("Title here"),
             category: None,
             thumbnail: None,
+            description: None,
+            created_at: None,
+            updated_at: None,
+            releases: None,
+            deprecated: None,
+            tags: Vec::new(),
             changelog: Some(r"
 Version: 1.0.1
 Date: 06. 07. 2024
@@ -573,6 +1116,101 @@ Version: 1.0.0
         assert_eq!(changelog, expected);
     }
 
+    #[test]
+    fn changelog_short_separator() {
+        let separator = "-".repeat(80);
+        let mod_info = mod_with_changelog(format!("{separator}
+Version: 1.0.1
+  Bugfixes:
+    - Fixed a crash.
+{separator}
+Version: 1.0.0
+  Features:
+    - Initial release.
+{separator}
+"));
+        let changelog = get_mod_changelog(&mod_info);
+        assert_eq!(changelog.len(), 2);
+        assert_eq!(changelog[0].version, "1.0.1");
+        assert_eq!(changelog[0].categories, vec![ModChangelogCategory {
+            name: "Bugfixes:".to_owned(),
+            entries: vec!["- Fixed a crash.".to_owned()],
+        }]);
+        assert_eq!(changelog[1].version, "1.0.0");
+        assert_eq!(changelog[1].categories, vec![ModChangelogCategory {
+            name: "Features:".to_owned(),
+            entries: vec!["- Initial release.".to_owned()],
+        }]);
+    }
+
+    #[test]
+    fn changelog_multiline_entries() {
+        let changelog = get_mod_changelog(&mod_with_changelog(r"Version: 1.1.0
+  Changes:
+    - Reworked the recipes of all intermediate products so that they
+      can be made in an assembling machine 1.
+    - Short entry.
+    - Another long entry that
+      spans
+      three lines.
+  Bugfixes:
+    Fixed a crash without a bullet.
+    - Fixed another crash.".to_owned()));
+        assert_eq!(changelog[0].categories, vec![
+            ModChangelogCategory {
+                name: "Changes:".to_owned(),
+                entries: vec![
+                    "- Reworked the recipes of all intermediate products so that they can be made in an assembling machine 1.".to_owned(),
+                    "- Short entry.".to_owned(),
+                    "- Another long entry that spans three lines.".to_owned(),
+                ]
+            },
+            ModChangelogCategory {
+                name: "Bugfixes:".to_owned(),
+                entries: vec![
+                    "Fixed a crash without a bullet.".to_owned(),
+                    "- Fixed another crash.".to_owned(),
+                ]
+            },
+        ]);
+    }
+
+    #[test]
+    fn changelog_without_separators() {
+        let mod_info = mod_with_changelog(r"Version: 0.2.0
+Date: 2024-01-02
+  Changes:
+    - Rebalanced recipes.
+Version: 0.1.0
+Date: 2024-01-01
+  Features:
+    - Initial release.".to_owned());
+        let changelog = get_mod_changelog(&mod_info);
+        let expected = [
+            ModChangelogEntry{
+                version: "0.2.0".to_owned(),
+                date: Some("2024-01-02".to_owned()),
+                categories: vec![
+                    ModChangelogCategory {
+                        name: "Changes:".to_owned(),
+                        entries: vec!["- Rebalanced recipes.".to_owned()]
+                    }
+                ]
+            },
+            ModChangelogEntry{
+                version: "0.1.0".to_owned(),
+                date: Some("2024-01-01".to_owned()),
+                categories: vec![
+                    ModChangelogCategory {
+                        name: "Features:".to_owned(),
+                        entries: vec!["- Initial release.".to_owned()]
+                    }
+                ]
+            },
+        ];
+        assert_eq!(changelog, expected);
+    }
+
     #[test]
     fn test_format_changelog() {
         let changelog = [
@@ -617,4 +1255,273 @@ r"**Bugfixes:**
 - Add new entities.".to_owned());
         assert_eq!(formatted_changelog, expected_output);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_format_changelog_markdown() {
+        let changelog = [
+            ModChangelogEntry{
+                version: "1.0.0".to_owned(),
+                date: None,
+                categories: vec![
+                    ModChangelogCategory {
+                        name: r"**Bug*fixes\".to_owned(),
+                        entries: vec![
+                            "- Fixed `code` and ||spoilers||.".to_owned(),
+                            "- Renamed __init__ to ~~old~~ name.".to_owned(),
+                        ]
+                    },
+                    ModChangelogCategory {
+                        name: "   ".to_owned(),
+                        entries: vec![
+                            "- Pinged @everyone.".to_owned(),
+                        ]
+                    }
+                ]
+            },
+        ];
+        let formatted_changelog = format_mod_changelog(&changelog, "1.0.0", 15);
+        let expected_output = Some([
+            r"**\*\*Bug\*fixes\\**",
+            r"- Fixed \`code\` and \|\|spoilers\|\|.",
+            r"- Renamed \_\_init\_\_ to \~\~old\~\~ name.",
+            "- Pinged @\u{200b}everyone.",
+        ].join("\n"));
+        assert_eq!(formatted_changelog, expected_output);
+    }
+
+    #[test]
+    fn unsubscribe_updates_cache() {
+        let mut cache = vec![
+            SubCacheEntry { server_id: 1, subscription: SubscriptionType::Modname("Krastorio2".to_owned()) },
+            SubCacheEntry { server_id: 1, subscription: SubscriptionType::Author("Krastorio2".to_owned()) },
+            SubCacheEntry { server_id: 2, subscription: SubscriptionType::Modname("Krastorio2".to_owned()) },
+            SubCacheEntry { server_id: 1, subscription: SubscriptionType::Modname("space-exploration".to_owned()) },
+        ];
+        remove_cached_subscription(&mut cache, 1, &SubscriptionType::Modname("Krastorio2".to_owned()));
+        assert_eq!(cache.len(), 3);
+        assert!(!cache.iter().any(|e| e.server_id == 1 && e.subscription == SubscriptionType::Modname("Krastorio2".to_owned())));
+        // Author subscriptions with the same name and other servers are untouched
+        assert!(cache.iter().any(|e| e.server_id == 1 && e.subscription == SubscriptionType::Author("Krastorio2".to_owned())));
+        assert!(cache.iter().any(|e| e.server_id == 2 && e.subscription == SubscriptionType::Modname("Krastorio2".to_owned())));
+
+        remove_cached_subscription(&mut cache, 1, &SubscriptionType::Author("Krastorio2".to_owned()));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn digest_lines_are_limited() {
+        let lines = (1..=5).map(|i| format!("Mod {i}")).collect::<Vec<String>>();
+        assert_eq!(format_digest_lines(&lines, 4096), "Mod 1\nMod 2\nMod 3\nMod 4\nMod 5");
+        assert_eq!(format_digest_lines(&lines, 35), "Mod 1\nMod 2\nMod 3\n...and 2 more");
+        assert_eq!(format_digest_lines(&lines, 30), "Mod 1\nMod 2\n...and 3 more");
+        assert_eq!(format_digest_lines(&[], 4096), "");
+    }
+
+    #[test]
+    fn dependency_summary() {
+        let dependencies = [
+            "base >= 2.0", 
+            "flib >= 0.15.0", 
+            "? space-age", 
+            "(?) hidden mod", 
+            "! bad_mod", 
+            "~ mod with spaces = 1.0.0",
+        ].map(String::from);
+        let expected = "**Required:** flib, mod with spaces\n**Optional:** 2 mods\n**Incompatible:** bad\\_mod";
+        assert_eq!(summarize_dependencies(&dependencies), expected);
+        assert_eq!(summarize_dependencies(&["base".to_owned()]), "None");
+        assert_eq!(summarize_dependencies(&[]), "None");
+    }
+
+    #[test]
+    fn webhook_url_validation() {
+        assert!(is_discord_webhook_url("https://discord.com/api/webhooks/123456789/abcDEF-ghi_jkl"));
+        assert!(is_discord_webhook_url("https://discordapp.com/api/webhooks/123456789/token/"));
+        assert!(!is_discord_webhook_url("https://discord.com/api/webhooks/123456789"));
+        assert!(!is_discord_webhook_url("https://discord.com/api/webhooks/abc/token"));
+        assert!(!is_discord_webhook_url("https://example.com/api/webhooks/123456789/token"));
+        assert!(!is_discord_webhook_url("http://discord.com/api/webhooks/123456789/token"));
+    }
+
+    #[test]
+    fn test_changelog_range() {
+        let changelog = [
+            ModChangelogEntry{
+                version: "1.0.2".to_owned(),
+                date: None,
+                categories: vec![
+                    ModChangelogCategory {
+                        name: "Bugfixes:".to_owned(),
+                        entries: vec!["- Fix crash on load.".to_owned()]
+                    }
+                ]
+            },
+            ModChangelogEntry{
+                version: "1.0.1".to_owned(),
+                date: None,
+                categories: vec![
+                    ModChangelogCategory {
+                        name: "Features:".to_owned(),
+                        entries: vec!["- Add new entities.".to_owned()]
+                    },
+                    ModChangelogCategory {
+                        name: "Bugfixes:".to_owned(),
+                        entries: vec!["- Fix typo.".to_owned()]
+                    }
+                ]
+            },
+            ModChangelogEntry{
+                version: "1.0.0".to_owned(),
+                date: None,
+                categories: vec![
+                    ModChangelogCategory {
+                        name: "Features:".to_owned(),
+                        entries: vec!["- Initial release.".to_owned()]
+                    }
+                ]
+            },
+        ];
+        let range = get_changelog_range(&changelog, "1.0.0", "1.0.2");
+        assert_eq!(range, get_changelog_range(&changelog, "1.0.2", "1.0.0"));
+        let range = range.unwrap();
+        assert_eq!(range.iter().map(|c| c.version.as_str()).collect::<Vec<&str>>(), vec!["1.0.2", "1.0.1"]);
+        assert!(get_changelog_range(&changelog, "1.0.0", "0.9.0").is_none());
+
+        let formatted = format_changelog_categories(&merge_changelog_entries(&range), 15);
+        let expected = r"**Bugfixes:**
+- Fix crash on load.
+- Fix typo.
+**Features:**
+- Add new entities.";
+        assert_eq!(formatted, expected);
+    }
+
+    #[test]
+    fn full_size_images() {
+        assert_eq!(
+            full_size_image_url(Some("/assets/0123abcd.thumb.png")),
+            Some("https://assets-mod.factorio.com/assets/0123abcd.png".to_owned())
+        );
+        assert_eq!(full_size_image_url(Some("/assets/.thumb.png")), None);
+        assert_eq!(full_size_image_url(Some("")), None);
+        assert_eq!(full_size_image_url(None), None);
+    }
+
+    #[test]
+    fn multiple_factorio_versions() {
+        let release = |version: &str, factorio_version: &str| Release {
+            info_json: InfoJson { factorio_version: factorio_version.to_owned(), dependencies: None },
+            released_at: String::new(),
+            version: version.to_owned(),
+        };
+        let mut mod_info = mod_with_changelog(String::new());
+        mod_info.latest_release = Some(release("2.0.0", "2.0"));
+        assert_eq!(supported_versions(&mod_info), vec!["2.0"]);
+        mod_info.releases = Some(vec![release("1.0.0", "1.1"), release("1.1.0", "1.1"), release("0.1.0", "0.18"), release("2.0.0", "2.0")]);
+        assert_eq!(supported_versions(&mod_info), vec!["0.18", "1.1", "2.0"]);
+
+        let known = split_list("1.1,2.0");
+        assert_eq!(merge_versions(known, vec!["2.0".to_owned()]), vec!["1.1", "2.0"]);
+        assert_eq!(merge_versions(split_list(""), vec!["0.17".to_owned(), "0.9".to_owned()]), vec!["0.9", "0.17"]);
+    }
+
+    #[test]
+    fn changelog_button_ids() {
+        let id = changelog_button_id("Krastorio2", "1.3.24").unwrap();
+        assert_eq!(parse_changelog_button_id(&id), Some(("Krastorio2", "1.3.24")));
+        let id = changelog_button_id("Mod: with colon", "0.1.0").unwrap();
+        assert_eq!(parse_changelog_button_id(&id), Some(("Mod: with colon", "0.1.0")));
+        assert_eq!(changelog_button_id(&"a".repeat(90), "1.0.0"), None);
+        assert_eq!(parse_changelog_button_id("mod_subscribe"), None);
+    }
+
+    #[test]
+    fn thumbnail_fallback() {
+        assert_eq!(thumbnail_url(Some("/assets/abc.thumb.png")), "https://assets-mod.factorio.com/assets/abc.thumb.png");
+        assert_eq!(thumbnail_url(Some(PLACEHOLDER_THUMBNAIL)), fallback_thumbnail_url());
+        assert_eq!(thumbnail_url(Some("")), fallback_thumbnail_url());
+        assert_eq!(thumbnail_url(None), fallback_thumbnail_url());
+    }
+
+    fn mod_with_changelog(changelog: String) -> Mod {
+        Mod {
+            downloads_count: 0,
+            latest_release: None,
+            name: String::from("Modname"),
+            owner: String::from("Ownername"),
+            summary: String::new(),
+            title: String::from("Title here"),
+            category: None,
+            thumbnail: None,
+            description: None,
+            created_at: None,
+            updated_at: None,
+            releases: None,
+            deprecated: None,
+            tags: Vec::new(),
+            changelog: Some(changelog),
+        }
+    }
+
+    /// A line that may or may not follow the changelog format
+    fn changelog_line() -> impl proptest::strategy::Strategy<Value = String> {
+        proptest::prop_oneof![
+            "Version: [0-9. a-z]{0,10}",
+            "Date: [0-9. -]{0,12}",
+            " {1,6}[^\\n]{0,20}",
+            "-{0,30}",
+            "[^\\n]{0,30}",
+        ]
+    }
+
+    type GeneratedEntry = (String, Option<String>, Vec<(String, Vec<String>)>);
+
+    fn generated_changelog() -> impl proptest::strategy::Strategy<Value = Vec<GeneratedEntry>> {
+        let category = ("[A-Z][a-z ]{0,15}:", proptest::collection::vec("- [a-zA-Z0-9 .,!()-]{1,40}", 0..4));
+        let entry = ("[0-9]{1,2}\\.[0-9]{1,2}\\.[0-9]{1,2}", proptest::option::of("[0-9]{2}\\. [0-9]{2}\\. [0-9]{4}"), proptest::collection::vec(category, 0..4));
+        proptest::collection::vec(entry, 0..6)
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn changelog_parser_never_panics(lines in proptest::collection::vec(changelog_line(), 0..40)) {
+            let changelog = get_mod_changelog(&mod_with_changelog(lines.join("\n")));
+            for entry in changelog {
+                proptest::prop_assert!(!entry.version.is_empty());
+                proptest::prop_assert_eq!(entry.version.trim(), entry.version.as_str());
+            }
+        }
+
+        #[test]
+        fn changelog_parser_keeps_structure(entries in generated_changelog(), separators: bool) {
+            let separator = "-".repeat(99);
+            let mut text = String::new();
+            for (version, date, categories) in &entries {
+                if separators {
+                    text.push_str(&separator);
+                    text.push('\n');
+                }
+                text.push_str(&format!("Version: {version}\n"));
+                if let Some(date) = date {
+                    text.push_str(&format!("Date: {date}\n"));
+                }
+                for (name, lines) in categories {
+                    text.push_str(&format!("  {name}\n"));
+                    for line in lines {
+                        text.push_str(&format!("    {line}\n"));
+                    }
+                }
+            }
+            let expected = entries.into_iter()
+                .map(|(version, date, categories)| ModChangelogEntry {
+                    version,
+                    date,
+                    categories: categories.into_iter()
+                        .map(|(name, entries)| ModChangelogCategory { name, entries })
+                        .collect(),
+                })
+                .collect::<Vec<ModChangelogEntry>>();
+            proptest::prop_assert_eq!(get_mod_changelog(&mod_with_changelog(text)), expected);
+        }
+    }
+}
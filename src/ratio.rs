@@ -0,0 +1,208 @@
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter, Colour};
+use poise::CreateReply;
+
+use crate::{
+    Context,
+    custom_errors::CustomError,
+    Error,
+    wiki_commands::{wiki_page_url, wiki_title},
+};
+
+/// Crafting speed of an assembling machine 2, used when no speed is given.
+const DEFAULT_CRAFTING_SPEED: f64 = 0.75;
+const BELTS: [(&str, f64); 4] = [("Yellow", 15.0), ("Red", 30.0), ("Blue", 45.0), ("Turbo", 60.0)];
+
+#[derive(Debug)]
+struct Recipe {
+    name: &'static str,
+    /// Crafting time in seconds at crafting speed 1
+    time: f64,
+    output: u32,
+    ingredients: &'static [(&'static str, u32)],
+}
+
+// Normal difficulty recipes from the base game (2.0), covering the most common intermediates.
+// Fluid ingredients are left out, they can't be put on a belt. Copied by hand from the wiki, so
+// recipes changed by mods or future updates will not be reflected here.
+const RECIPES: &[Recipe] = &[
+    Recipe { name: "iron-plate", time: 3.2, output: 1, ingredients: &[("iron-ore", 1)] },
+    Recipe { name: "copper-plate", time: 3.2, output: 1, ingredients: &[("copper-ore", 1)] },
+    Recipe { name: "stone-brick", time: 3.2, output: 1, ingredients: &[("stone", 2)] },
+    Recipe { name: "steel-plate", time: 16.0, output: 1, ingredients: &[("iron-plate", 5)] },
+    Recipe { name: "iron-gear-wheel", time: 0.5, output: 1, ingredients: &[("iron-plate", 2)] },
+    Recipe { name: "iron-stick", time: 0.5, output: 2, ingredients: &[("iron-plate", 1)] },
+    Recipe { name: "copper-cable", time: 0.5, output: 2, ingredients: &[("copper-plate", 1)] },
+    Recipe { name: "pipe", time: 0.5, output: 1, ingredients: &[("iron-plate", 1)] },
+    Recipe { name: "electronic-circuit", time: 0.5, output: 1, ingredients: &[("iron-plate", 1), ("copper-cable", 3)] },
+    Recipe { name: "advanced-circuit", time: 6.0, output: 1, ingredients: &[("plastic-bar", 2), ("copper-cable", 4), ("electronic-circuit", 2)] },
+    Recipe { name: "processing-unit", time: 10.0, output: 1, ingredients: &[("electronic-circuit", 20), ("advanced-circuit", 2)] },
+    Recipe { name: "plastic-bar", time: 1.0, output: 2, ingredients: &[("coal", 1)] },
+    Recipe { name: "sulfur", time: 1.0, output: 2, ingredients: &[] },
+    Recipe { name: "battery", time: 4.0, output: 1, ingredients: &[("iron-plate", 1), ("copper-plate", 1)] },
+    Recipe { name: "engine-unit", time: 10.0, output: 1, ingredients: &[("steel-plate", 1), ("iron-gear-wheel", 1), ("pipe", 2)] },
+    Recipe { name: "electric-engine-unit", time: 10.0, output: 1, ingredients: &[("engine-unit", 1), ("electronic-circuit", 2)] },
+    Recipe { name: "flying-robot-frame", time: 20.0, output: 1, ingredients: &[("electric-engine-unit", 1), ("battery", 2), ("steel-plate", 1), ("electronic-circuit", 3)] },
+    Recipe { name: "low-density-structure", time: 15.0, output: 1, ingredients: &[("steel-plate", 2), ("copper-plate", 20), ("plastic-bar", 5)] },
+    Recipe { name: "rail", time: 0.5, output: 2, ingredients: &[("stone", 1), ("iron-stick", 1), ("steel-plate", 1)] },
+    Recipe { name: "transport-belt", time: 0.5, output: 2, ingredients: &[("iron-plate", 1), ("iron-gear-wheel", 1)] },
+    Recipe { name: "inserter", time: 0.5, output: 1, ingredients: &[("electronic-circuit", 1), ("iron-gear-wheel", 1), ("iron-plate", 1)] },
+    Recipe { name: "electric-furnace", time: 5.0, output: 1, ingredients: &[("steel-plate", 10), ("advanced-circuit", 5), ("stone-brick", 10)] },
+    Recipe { name: "productivity-module", time: 15.0, output: 1, ingredients: &[("advanced-circuit", 5), ("electronic-circuit", 5)] },
+    Recipe { name: "automation-science-pack", time: 5.0, output: 1, ingredients: &[("copper-plate", 1), ("iron-gear-wheel", 1)] },
+    Recipe { name: "logistic-science-pack", time: 6.0, output: 1, ingredients: &[("inserter", 1), ("transport-belt", 1)] },
+    Recipe { name: "chemical-science-pack", time: 24.0, output: 2, ingredients: &[("engine-unit", 2), ("advanced-circuit", 3), ("sulfur", 1)] },
+    Recipe { name: "production-science-pack", time: 21.0, output: 3, ingredients: &[("electric-furnace", 1), ("productivity-module", 1), ("rail", 30)] },
+    Recipe { name: "utility-science-pack", time: 21.0, output: 3, ingredients: &[("processing-unit", 2), ("flying-robot-frame", 1), ("low-density-structure", 3)] },
+];
+
+#[derive(Debug, PartialEq)]
+struct Ratio {
+    ingredient: &'static str,
+    product: &'static str,
+    /// Machines making the ingredient
+    ingredient_machines: u64,
+    /// Machines making the product
+    product_machines: u64,
+    /// Ingredient used per second by one set of machines at crafting speed 1
+    items_per_second: f64,
+}
+
+fn find_recipe(name: &str) -> Option<&'static Recipe> {
+    let internal_name = name.trim().to_lowercase().replace(' ', "-");
+    RECIPES.iter().find(|r| r.name == internal_name)
+}
+
+const fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {a} else {gcd(b, a % b)}
+}
+
+/// Crafting times are multiples of 0.1 seconds, so working in tenths keeps the ratio exact.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn time_in_tenths(time: f64) -> u64 {
+    (time * 10.0).round() as u64
+}
+
+/// Compute how many machines making `ingredient` are needed per machine making `product`.
+#[allow(clippy::cast_precision_loss)]
+fn compute_ratio(ingredient: &'static Recipe, product: &'static Recipe) -> Option<Ratio> {
+    let &(_, amount) = product.ingredients.iter().find(|(name, _)| *name == ingredient.name)?;
+    // Ingredient machines per product machine: (amount / product time) / (output / ingredient time)
+    let numerator = u64::from(amount) * time_in_tenths(ingredient.time);
+    let denominator = time_in_tenths(product.time) * u64::from(ingredient.output);
+    let divisor = gcd(numerator, denominator);
+    let product_machines = denominator / divisor;
+    Some(Ratio {
+        ingredient: ingredient.name,
+        product: product.name,
+        ingredient_machines: numerator / divisor,
+        product_machines,
+        items_per_second: product_machines as f64 * f64::from(amount) / product.time,
+    })
+}
+
+fn ratio_embed(ratio: &Ratio, crafting_speed: f64) -> CreateEmbed {
+    let ingredient = wiki_title(ratio.ingredient);
+    let product = wiki_title(ratio.product);
+    let items_per_second = ratio.items_per_second * crafting_speed;
+    let belts = BELTS.iter()
+        .map(|(belt, speed)| format!("{belt}: {:.1} sets", speed / items_per_second))
+        .collect::<Vec<String>>()
+        .join("\n");
+    CreateEmbed::new()
+        .title(format!("{ingredient} → {product}"))
+        .description(format!(
+            "**{}** [{ingredient}]({}) : **{}** [{product}]({})",
+            ratio.ingredient_machines,
+            wiki_page_url(&ingredient),
+            ratio.product_machines,
+            wiki_page_url(&product),
+        ))
+        .field("Per set", format!("{items_per_second:.2} {ingredient}/s"), true)
+        .field("Sets per belt", belts, true)
+        .footer(CreateEmbedFooter::new(format!("Assumes all machines have crafting speed {crafting_speed} and no modules")))
+        .color(Colour::ORANGE)
+}
+
+#[allow(clippy::unused_async)]
+async fn autocomplete_recipe<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> Vec<String> {
+    let partial = partial.to_lowercase().replace(' ', "-");
+    RECIPES.iter()
+        .filter(|r| r.name.contains(&partial))
+        .map(|r| wiki_title(r.name))
+        .collect::<Vec<String>>()
+}
+
+/// Calculate the ratio of machines needed to make an ingredient for another item
+#[poise::command(prefix_command, slash_command, track_edits, install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn ratio(
+    ctx: Context<'_>,
+    #[description = "Ingredient, e.g. copper cable"]
+    #[autocomplete = "autocomplete_recipe"]
+    ingredient: String,
+    #[description = "Item made from the ingredient, e.g. electronic circuit"]
+    #[autocomplete = "autocomplete_recipe"]
+    product: String,
+    #[description = "Crafting speed of the machines (default 0.75, assembling machine 2)"]
+    #[min = 0.01]
+    crafting_speed: Option<f64>,
+) -> Result<(), Error> {
+    let Some(ingredient_recipe) = find_recipe(&ingredient) else {
+        return Err(Box::new(CustomError::new(&format!("No recipe known for {ingredient}"))));
+    };
+    let Some(product_recipe) = find_recipe(&product) else {
+        return Err(Box::new(CustomError::new(&format!("No recipe known for {product}"))));
+    };
+    // Accept the items in either order
+    let Some(ratio) = compute_ratio(ingredient_recipe, product_recipe)
+        .or_else(|| compute_ratio(product_recipe, ingredient_recipe))
+    else {
+        return Err(Box::new(CustomError::new(&format!("{ingredient} is not an ingredient of {product} or the other way around"))));
+    };
+    let crafting_speed = crafting_speed.filter(|s| *s > 0.0).unwrap_or(DEFAULT_CRAFTING_SPEED);
+    ctx.send(CreateReply::default().embed(ratio_embed(&ratio, crafting_speed))).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio_between(ingredient: &str, product: &str) -> Option<Ratio> {
+        compute_ratio(find_recipe(ingredient)?, find_recipe(product)?)
+    }
+
+    #[test]
+    fn green_circuits() {
+        let ratio = ratio_between("Copper cable", "electronic-circuit").unwrap();
+        assert_eq!((ratio.ingredient_machines, ratio.product_machines), (3, 2));
+        assert!((ratio.items_per_second - 12.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn slow_products() {
+        let ratio = ratio_between("iron gear wheel", "automation science pack").unwrap();
+        assert_eq!((ratio.ingredient_machines, ratio.product_machines), (1, 10));
+        let ratio = ratio_between("steel plate", "engine unit").unwrap();
+        assert_eq!((ratio.ingredient_machines, ratio.product_machines), (8, 5));
+    }
+
+    #[test]
+    fn utility_science_pack() {
+        let recipe = find_recipe("utility science pack").unwrap();
+        assert_eq!(recipe.ingredients, &[("processing-unit", 2), ("flying-robot-frame", 1), ("low-density-structure", 3)]);
+        assert!((recipe.time - 21.0).abs() < f64::EPSILON);
+        assert_eq!(recipe.output, 3);
+        let ratio = ratio_between("low density structure", "utility science pack").unwrap();
+        assert_eq!((ratio.ingredient_machines, ratio.product_machines), (15, 7));
+    }
+
+    #[test]
+    fn unrelated_items() {
+        assert!(ratio_between("pipe", "electronic circuit").is_none());
+        assert!(ratio_between("electronic circuit", "copper cable").is_none());
+        assert!(find_recipe("nuclear reactor").is_none());
+    }
+}
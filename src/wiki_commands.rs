@@ -1,16 +1,17 @@
-use parse_wiki_text::{Node, Configuration};
-use poise::serenity_prelude::{CreateEmbed, Colour};
+use parse_wiki_text::{Node, Configuration, DefinitionListItemType};
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Colour, GuildId};
 use poise::CreateReply;
 use std::fmt::Debug;
 use std::{fmt, fmt::Write};
 use serde::Deserialize;
-use log::error;
+use log::{debug, error};
 
-use crate::formatting_tools::DiscordFormat;
+use crate::formatting_tools::{truncate_with_read_more, DiscordFormat, EMBED_DESCRIPTION_LIMIT, EMBED_TITLE_LIMIT, MESSAGE_CONTENT_LIMIT};
 use crate::{
     Context, 
-    custom_errors::CustomError, 
+    custom_errors::{request_failed, CustomError}, 
     Error, 
+    management::{get_server_id, lookup_is_private, checks::is_mod},
     SEPARATOR, 
 };
 
@@ -35,6 +36,20 @@ impl fmt::Display for NodeWrap<'_> {
                 let node_strs = nodes.iter().map(|node| format!("{}", NodeWrap{n: node})).collect::<Vec<String>>();
                 write!(f, "\n||HEADING||{} {}\n", "#".repeat(*level as usize), node_strs.join(" "))
             },
+            Node::CharacterEntity { character, .. } => write!(f, "{character}"),
+            Node::Comment { .. } => Ok(()),
+            Node::DefinitionList { items, .. } => {
+                let node_str = items.iter().fold(String::new(), |mut node_output, item| {
+                    let text = nodes_to_string(&item.nodes);
+                    let _ = match item.type_ {
+                        DefinitionListItemType::Term => write!(node_output, "\n**{}**", text.trim()),
+                        // Discord ignores leading spaces, an em space keeps the indent
+                        DefinitionListItemType::Details => write!(node_output, "\n\u{2003}{}", text.trim()),
+                    };
+                    node_output
+                });
+                write!(f, "{node_str}")
+            },
             Node::HorizontalDivider { .. } => write!(f, "\n---\n"),
             Node::Italic { .. } => write!(f, "*"),
             Node::Link { target, text, .. } => {
@@ -61,7 +76,7 @@ impl fmt::Display for NodeWrap<'_> {
                     let _ = write!(output, "{}", NodeWrap{n: node});
                     output
                 });
-                writeln!(f, "```{node_str}```",)
+                writeln!(f, "{}", node_str.to_block_code(""))
             },
             Node::StartTag { name: std::borrow::Cow::Borrowed("code"), .. } | 
             Node::EndTag { name: std::borrow::Cow::Borrowed("code"), ..} => {
@@ -87,9 +102,6 @@ impl fmt::Display for NodeWrap<'_> {
             },
             // Node::Parameter { default, end, name, start } => todo!(),
             // Node::Category { end, ordinal, start, target } => todo!(),
-            // Node::CharacterEntity { character, end, start } => todo!(),
-            // Node::Comment { end, start } => todo!(),
-            // Node::DefinitionList { end, items, start } => todo!(),
             // Node::Image { end, start, target, text } => todo!(),
             // Node::MagicWord { end, start } => todo!(),
             // Node::Redirect { end, target, start } => todo!(),
@@ -109,15 +121,55 @@ fn format_template(name: &[Node<'_>], parameters: &[parse_wiki_text::Parameter<'
                 return Ok(());
             };
             // Assumes imagelinks never have a custom caption.
-            write!(f, "[{value}](https://wiki.factorio.com/{})", value.replace(' ', "_"))
+            write!(f, "[{value}]({})", wiki_page_url(value))
         },
         Some(Node::Text{value: "About/Space age", ..}) => {
             writeln!(f, "_[Space Age](https://wiki.factorio.com/Space_Age) expansion exclusive feature._")
         }
+        Some(Node::Text{value, ..}) if value.trim().eq_ignore_ascii_case("infobox") => {
+            format_infobox(parameters, f)
+        },
+        Some(Node::Text{value, ..}) if value.trim().eq_ignore_ascii_case("icon") => {
+            // {{Icon|Iron plate|20}} shows an item icon with an optional amount
+            let mut values = parameters.iter().map(|par| nodes_to_string(&par.value));
+            let Some(item) = values.next() else {
+                return Ok(());
+            };
+            match values.next() {
+                Some(amount) if !amount.trim().is_empty() => write!(f, "{} ×{}", item.trim(), amount.trim()),
+                _ => write!(f, "{}", item.trim()),
+            }
+        },
         _ => Ok(())
     }
 }
 
+// Infobox parameters that only affect how the infobox looks
+const INFOBOX_SKIPPED_KEYS: [&str; 4] = ["image", "map-color", "tech-image", "title"];
+
+/// Renders the named parameters of an infobox as "Key: Value" lines.
+fn format_infobox(parameters: &[parse_wiki_text::Parameter<'_>], f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+    for par in parameters {
+        let Some(key) = par.name.as_deref().map(nodes_to_string) else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = nodes_to_string(&par.value);
+        if INFOBOX_SKIPPED_KEYS.contains(&key.as_str()) || value.trim().is_empty() {
+            continue;
+        }
+        writeln!(f, "**{}:** {}", key.replace(['-', '_'], " ").capitalize(), value.trim())?;
+    }
+    Ok(())
+}
+
+fn nodes_to_string(nodes: &[Node<'_>]) -> String {
+    nodes.iter().fold(String::new(), |mut output, node| {
+        let _ = write!(output, "{}", NodeWrap{n: node});
+        output
+    })
+}
+
 fn format_tag(name: &str, nodes: &[Node<'_>], f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
     match name {
         "syntaxhighlight" => {
@@ -125,7 +177,7 @@ fn format_tag(name: &str, nodes: &[Node<'_>], f: &mut fmt::Formatter<'_>) -> Res
                 let _ = write!(output, "{}", NodeWrap{n: node});
                 output
             });
-            writeln!(f, "```lua\n{node_str}```",)
+            writeln!(f, "{}", node_str.to_block_code("lua"))
         },
         "nowiki" => {
             let node_str = nodes.iter().fold(String::new(), |mut output, node| {
@@ -135,20 +187,41 @@ fn format_tag(name: &str, nodes: &[Node<'_>], f: &mut fmt::Formatter<'_>) -> Res
             write!(f, "{node_str}")
         },
         _ => {
-            let node_str = nodes.iter().fold(String::new(), |mut output, node| {
-                let _ = write!(output, "{}", NodeWrap{n: node});
-                output
-            });
-            write!(f, "TAG {name}: {node_str}")
+            debug!("Unhandled wiki tag: {name}");
+            write!(f, "{}", nodes_to_string(nodes))
         },
     }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 struct PageResponse {
-    parse: Parse,
+    parse: Option<Parse>,
+    error: Option<ApiError>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+struct ApiError {
+    code: String,
+    info: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WikiError {
+    PageNotFound(String),
+    Timeout(&'static str),
+}
+
+impl fmt::Display for WikiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PageNotFound(name) => write!(f, "The wiki page {name} does not exist. Try searching for it with `/wiki` instead."),
+            Self::Timeout(api) => write!(f, "Timed out while accessing {api}, try again later"),
+        }
+    }
+}
+
+impl std::error::Error for WikiError {}
+
 #[derive(Deserialize, Debug, Clone)]
 struct Parse {
     title: String,
@@ -178,7 +251,7 @@ const LANG_CODES: [&str; 284] = ["/aa", "/ab", "/ae", "/af", "/ak", "/am", "/an"
 "/tt", "/tw", "/ty", "/ug", "/uk", "/ur", "/us", "/uz", "/ve", "/vi", "/vo", "/wa", "/wo", "/xh", "/yi", "/yo", 
 "/za", "/zh", "/zh-cn", "/zh-hk", "/zh-mo", "/zh-sg", "/zh-tw", "/zu"];
 
-async fn get_mediawiki_page(name: &str) -> Result<Parse, Error> {
+async fn get_mediawiki_page(client: &reqwest::Client, name: &str) -> Result<Parse, Error> {
     let url = reqwest::Url::parse_with_params("https://wiki.factorio.com/api.php?", &[
             ("action", "parse"),
             ("format", "json"),
@@ -187,9 +260,22 @@ async fn get_mediawiki_page(name: &str) -> Result<Parse, Error> {
             ("prop", "wikitext"),
             ("formatversion", "2"),
             ])?;
-    let response = reqwest::get(url).await?;
-    let page: PageResponse = response.json().await?;
-    Ok(page.parse)
+    let response = client.get(url).send().await.map_err(|e| request_failed("the wiki", e, WikiError::Timeout))?;
+    let page: PageResponse = response.json().await.map_err(|e| request_failed("the wiki", e, WikiError::Timeout))?;
+    page_from_response(name, page)
+}
+
+fn page_from_response(name: &str, response: PageResponse) -> Result<Parse, Error> {
+    if let Some(parse) = response.parse {
+        return Ok(parse);
+    }
+    match response.error {
+        Some(error) if error.code == "missingtitle" || error.code == "invalidtitle" => {
+            Err(Box::new(WikiError::PageNotFound(name.to_owned())))
+        },
+        Some(error) => Err(Box::new(CustomError::new(&format!("Wiki API returned an error: {}", error.info)))),
+        None => Err(Box::new(CustomError::new("Wiki API returned an empty response"))),
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -200,7 +286,49 @@ struct WikiData {
     _urls: Vec<String>,
 }
 
-pub async fn opensearch_mediawiki(name: &str) -> Result<Vec<String>, Error> {
+/// Builds the URL of a wiki page from its title.
+pub fn wiki_page_url(title: &str) -> String {
+    format!("https://wiki.factorio.com/{}", title.replace(' ', "_"))
+}
+
+/// Builds the URL of an icon uploaded to the wiki, e.g. `Iron plate` becomes `Iron_plate.png`.
+pub fn wiki_image_url(name: &str) -> String {
+    wiki_page_url(&format!("images/{name}.png"))
+}
+
+/// Turn an internal name into a wiki page title, e.g. `assembling-machine-1` becomes `Assembling machine 1`.
+pub fn wiki_title(name: &str) -> String {
+    let title = name.replace('-', " ");
+    let mut chars = title.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// Check whether a page title is a translation into a language other than the given one.
+fn is_other_language(title: &str, language: Option<&str>) -> bool {
+    LANG_CODES.iter().any(|&langcode| title.ends_with(langcode) && language.is_none_or(|l| langcode[1..] != *l))
+}
+
+/// Check whether a language code is one the wiki could have translations in.
+fn is_language_code(code: &str) -> bool {
+    LANG_CODES.iter().any(|&langcode| langcode[1..] == *code)
+}
+
+/// Get the wiki language a server has chosen. Servers without a preference use English.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn get_wiki_language(db: &sqlx::SqlitePool, guild_id: Option<GuildId>) -> Option<String> {
+    let server_id = guild_id?.get() as i64;
+    match sqlx::query!(r#"SELECT wiki_language FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await {
+        Ok(record) => record.and_then(|r| r.wiki_language),
+        Err(e) => {
+            error!("Error reading wiki language of server {server_id}: {e}");
+            None
+        },
+    }
+}
+
+pub async fn opensearch_mediawiki(client: &reqwest::Client, name: &str, language: Option<&str>) -> Result<Vec<String>, Error> {
     let url = reqwest::Url::parse_with_params("https://wiki.factorio.com/api.php", &[
         ("action", "opensearch"),
         ("format", "json"),
@@ -209,8 +337,8 @@ pub async fn opensearch_mediawiki(name: &str) -> Result<Vec<String>, Error> {
         ("limit", "100"),
         ("formatversion", "2")
     ])?;
-    let response = reqwest::get(url).await?;
-    let json: WikiData = response.json().await?;
+    let response = client.get(url).send().await.map_err(|e| request_failed("the wiki", e, WikiError::Timeout))?;
+    let json: WikiData = response.json().await.map_err(|e| request_failed("the wiki", e, WikiError::Timeout))?;
     if json.titles.is_empty() {
         return Ok(vec![]);
     };
@@ -218,7 +346,7 @@ pub async fn opensearch_mediawiki(name: &str) -> Result<Vec<String>, Error> {
     let mut output = Vec::new();
 
     for name in json.titles {
-        if LANG_CODES.iter().any(|&langcode| name.ends_with(langcode)) {
+        if is_other_language(&name, language) {
             continue
         };
         output.push(name);
@@ -236,10 +364,11 @@ pub async fn wiki(
     name: String,
 ) -> Result<(), Error> {
     let command = name.split(SEPARATOR).next().unwrap_or(&name).trim();
+    let language = get_wiki_language(&ctx.data().database, ctx.guild_id()).await;
     let search_result: String = match ctx {
         poise::Context::Application(_) => command.to_owned(),
         poise::Context::Prefix(_) => {
-            let results = opensearch_mediawiki(command).await?;
+            let results = opensearch_mediawiki(&ctx.data().http_client, command, language.as_deref()).await?;
             let Some(res) = results.first() else {
                 return Err(Box::new(CustomError::new("Wiki search returned no results")))
             };
@@ -247,13 +376,151 @@ pub async fn wiki(
         },
     };
     
-    let embed = get_wiki_page(&search_result).await?;
-    let builder = CreateReply::default().embed(embed);
+    let embed = get_wiki_page(&ctx.data().http_client, &search_result, language.as_deref()).await?;
+    let builder = CreateReply::default()
+        .embed(embed)
+        .ephemeral(lookup_is_private(ctx).await);
     ctx.send(builder).await?;
     Ok(())
 
 }
 
+// Longest message that is used as a wiki search query
+const WIKI_QUERY_LIMIT: usize = 100;
+
+/// Get a wiki search query from the contents of a message.
+fn message_wiki_query(content: &str) -> Result<&str, Error> {
+    let query = content.trim();
+    if query.is_empty() {
+        return Err(Box::new(CustomError::new("This message has no text to search the wiki for")));
+    }
+    if query.chars().count() > WIKI_QUERY_LIMIT {
+        return Err(Box::new(CustomError::new(&format!("Message too long to search the wiki for (must be {WIKI_QUERY_LIMIT} characters or shorter)"))));
+    }
+    Ok(query)
+}
+
+/// Search the wiki for the contents of a message
+#[poise::command(context_menu_command = "Search wiki", ephemeral, install_context = "Guild|User", interaction_context = "Guild|BotDm|PrivateChannel")]
+pub async fn wiki_search_message(
+    ctx: Context<'_>,
+    #[description = "Message to search the wiki for"]
+    message: serenity::Message,
+) -> Result<(), Error> {
+    let query = message_wiki_query(&message.content)?;
+    let language = get_wiki_language(&ctx.data().database, ctx.guild_id()).await;
+    let results = opensearch_mediawiki(&ctx.data().http_client, query, language.as_deref()).await?;
+    let Some(search_result) = results.first() else {
+        return Err(Box::new(CustomError::new("Wiki search returned no results")))
+    };
+    let embed = get_wiki_page(&ctx.data().http_client, search_result, language.as_deref()).await?;
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Show the raw wikitext and parsed nodes of a wiki page, for debugging the wiki formatter.
+#[poise::command(prefix_command, slash_command, owners_only, hide_in_help, rename="wikiraw", category="Management")]
+pub async fn wiki_raw(
+    ctx: Context<'_>,
+    #[description = "Wiki page name"]
+    #[autocomplete = "autocomplete_wiki"]
+    #[rest]
+    name: String,
+) -> Result<(), Error> {
+    let article = get_mediawiki_page(&ctx.data().http_client, name.trim()).await?;
+    let nodes = get_factorio_wiki_parser_config()
+        .parse(&article.wikitext)
+        .nodes
+        .iter()
+        .map(describe_node)
+        .collect::<Vec<String>>()
+        .join("\n");
+    let output = format!("Wikitext:\n{}\n\nNodes:\n{nodes}", article.wikitext);
+    let block = output.as_str().to_block_code("");
+    let builder = if block.chars().count() > MESSAGE_CONTENT_LIMIT {
+        let file = serenity::CreateAttachment::bytes(output, format!("{}.txt", article.title.replace(' ', "_")));
+        CreateReply::default()
+            .content(format!("Parsed {}:", article.title))
+            .attachment(file)
+    } else {
+        CreateReply::default().content(block)
+    };
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Describes a node by its type, and its name for templates and tags.
+fn describe_node(node: &Node<'_>) -> String {
+    match node {
+        Node::Bold { .. } => "Bold".to_owned(),
+        Node::BoldItalic { .. } => "BoldItalic".to_owned(),
+        Node::Category { .. } => "Category".to_owned(),
+        Node::CharacterEntity { .. } => "CharacterEntity".to_owned(),
+        Node::Comment { .. } => "Comment".to_owned(),
+        Node::DefinitionList { .. } => "DefinitionList".to_owned(),
+        Node::EndTag { name, .. } => format!("EndTag {name}"),
+        Node::ExternalLink { .. } => "ExternalLink".to_owned(),
+        Node::Heading { level, .. } => format!("Heading {level}"),
+        Node::HorizontalDivider { .. } => "HorizontalDivider".to_owned(),
+        Node::Image { target, .. } => format!("Image {target}"),
+        Node::Italic { .. } => "Italic".to_owned(),
+        Node::Link { target, .. } => format!("Link {target}"),
+        Node::MagicWord { .. } => "MagicWord".to_owned(),
+        Node::OrderedList { .. } => "OrderedList".to_owned(),
+        Node::ParagraphBreak { .. } => "ParagraphBreak".to_owned(),
+        Node::Parameter { .. } => "Parameter".to_owned(),
+        Node::Preformatted { .. } => "Preformatted".to_owned(),
+        Node::Redirect { target, .. } => format!("Redirect {target}"),
+        Node::StartTag { name, .. } => format!("StartTag {name}"),
+        Node::Table { .. } => "Table".to_owned(),
+        Node::Tag { name, .. } => format!("Tag {name}"),
+        Node::Template { name, .. } => format!("Template {}", nodes_to_string(name).trim()),
+        Node::Text { .. } => "Text".to_owned(),
+        Node::UnorderedList { .. } => "UnorderedList".to_owned(),
+    }
+}
+
+/// Set the language of wiki pages on this server. Pages without a translation are shown in English.
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_wiki_language(
+    ctx: Context<'_>,
+    #[description = "Language code, for example de or pt-br. Leave empty to reset to English."]
+    language: Option<String>,
+) -> Result<(), Error> {
+    let language = language.map(|l| l.trim().trim_start_matches('/').to_lowercase()).filter(|l| l != "en");
+    if let Some(l) = &language {
+        if !is_language_code(l) {
+            return Err(Box::new(CustomError::new(&format!("{l} is not a valid language code"))));
+        }
+    }
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await? {
+        Some(_) => {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET wiki_language = $1 WHERE server_id = $2"#,
+            language, server_id)
+            .execute(db)
+            .await?;
+        },
+        None => {
+            // Add server and set setting if it does not exist
+            sqlx::query!(r#"INSERT INTO servers (server_id, wiki_language) VALUES ($1, $2)"#,
+            server_id, language)
+            .execute(db)
+            .await?;
+        },
+    };
+    ctx.data().inline_settings_cache.remove(&server_id);
+    match language {
+        Some(l) => ctx.say(format!("Wiki pages will be shown in `{l}` when a translation exists.")).await?,
+        None => ctx.say("Wiki pages will be shown in English.").await?,
+    };
+    Ok(())
+}
+
 fn get_factorio_wiki_parser_config() -> Configuration {
     // Parser configuration for wiki.factorio.com
     Configuration::new(&parse_wiki_text::ConfigurationSource { 
@@ -273,9 +540,21 @@ fn get_factorio_wiki_parser_config() -> Configuration {
     })
 }
 
-pub async fn get_wiki_page(search_result: &str) -> Result<CreateEmbed, Error> {
-    let article = match get_mediawiki_page(search_result).await{
+pub async fn get_wiki_page(client: &reqwest::Client, search_result: &str, language: Option<&str>) -> Result<CreateEmbed, Error> {
+    // Prefer the translated page, which the wiki stores as a subpage named after the language code
+    let translated = match language {
+        Some(l) if !LANG_CODES.iter().any(|&langcode| search_result.ends_with(langcode)) => {
+            get_mediawiki_page(client, &format!("{search_result}/{l}")).await.ok()
+        },
+        _ => None,
+    };
+    let article = match translated {
+        Some(page) => Ok(page),
+        None => get_mediawiki_page(client, search_result).await,
+    };
+    let article = match article {
         Ok(page) => page,
+        Err(e) if e.is::<WikiError>() => return Err(e),
         Err(e) => {
             return Err(Box::new(CustomError::new(&format!("Failed to parse page. The page you searched for may not exist.\nOriginal error: {e}"))));
         },
@@ -303,26 +582,111 @@ pub async fn get_wiki_page(search_result: &str) -> Result<CreateEmbed, Error> {
             }
         },
     };
+    let url = wiki_page_url(&article.title);
     let embed = CreateEmbed::new()
-        .title(article.title.truncate_for_embed(256))
-        .url(format!("https://wiki.factorio.com/{}", &article.title.replace(' ', "_")))
-        .description(formatted_text.truncate_for_embed(2048))
+        .title(article.title.truncate_for_embed(EMBED_TITLE_LIMIT))
+        .description(truncate_with_read_more(&formatted_text, EMBED_DESCRIPTION_LIMIT, &url))
+        .url(url)
         .color(Colour::ORANGE);
     Ok(embed)
 }
 
 async fn autocomplete_wiki<'a>(
-    _ctx: Context<'_>,
+    ctx: Context<'_>,
     partial: &'a str,
 ) -> Vec<String> {
     if partial.is_empty() {
         return vec!["Main Page".to_owned()]
     }
-    match opensearch_mediawiki(partial).await {
+    let language = get_wiki_language(&ctx.data().database, ctx.guild_id()).await;
+    match opensearch_mediawiki(&ctx.data().http_client, partial, language.as_deref()).await {
         Ok(r) => r,
         Err(e) => {
             error!("Error searching wiki: {e}");
             vec![]
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_wikitext(wikitext: &str) -> String {
+        nodes_to_string(&get_factorio_wiki_parser_config().parse(wikitext).nodes)
+    }
+
+    #[test]
+    fn infobox_template() {
+        let wikitext = "{{Infobox\n|image = Iron gear wheel\n|stack-size = 100\n|internal-name = iron-gear-wheel\n|recipe = Time, 0.5 + Iron plate, 2\n|health =\n}}";
+        assert_eq!(
+            format_wikitext(wikitext),
+            "**Stack size:** 100\n**Internal name:** iron-gear-wheel\n**Recipe:** Time, 0.5 + Iron plate, 2\n"
+        );
+    }
+
+    #[test]
+    fn icon_template() {
+        assert_eq!(format_wikitext("Needs {{Icon|Iron plate|20}} and {{Icon|Coal}}"), "Needs Iron plate ×20 and Coal");
+    }
+
+    #[test]
+    fn unknown_template() {
+        assert_eq!(format_wikitext("Before {{Some template|a=b}}after"), "Before after");
+    }
+
+    #[test]
+    fn definition_list() {
+        assert_eq!(
+            format_wikitext("; Throughput\n: Items per second\n; Latency\n: Ticks"),
+            "\n**Throughput**\n\u{2003}Items per second\n**Latency**\n\u{2003}Ticks"
+        );
+    }
+
+    #[test]
+    fn unknown_tag() {
+        assert_eq!(format_wikitext("Start <section>inner '''text'''</section> end"), "Start inner **text** end");
+    }
+
+    #[test]
+    fn character_entities() {
+        assert_eq!(format_wikitext("Fish &amp; chips &lt;3 &gt; &quot;x&quot;"), "Fish & chips <3 > \"x\"");
+        assert_eq!(format_wikitext("A&nbsp;B"), "A\u{a0}B");
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(format_wikitext("Visible<!-- hidden note -->text"), "Visibletext");
+    }
+
+    #[test]
+    fn titles_from_internal_names() {
+        assert_eq!(wiki_title("assembling-machine-1"), "Assembling machine 1");
+        assert_eq!(wiki_title("signal-A"), "Signal A");
+        assert_eq!(wiki_title(""), "");
+    }
+
+    #[test]
+    fn missing_page() {
+        let json = r#"{"error":{"code":"missingtitle","info":"The page you specified doesn't exist.","docref":"See https://wiki.factorio.com/api.php for API usage."},"servedby":"wiki"}"#;
+        let response: PageResponse = serde_json::from_str(json).unwrap();
+        let error = page_from_response("Nonexistent page", response).unwrap_err();
+        assert_eq!(error.downcast_ref::<WikiError>(), Some(&WikiError::PageNotFound("Nonexistent page".to_owned())));
+    }
+
+    #[test]
+    fn existing_page() {
+        let json = r#"{"parse":{"title":"Iron plate","pageid":1,"wikitext":"Some text"}}"#;
+        let response: PageResponse = serde_json::from_str(json).unwrap();
+        let page = page_from_response("Iron plate", response).unwrap();
+        assert_eq!(page.title, "Iron plate");
+        assert_eq!(page.wikitext, "Some text");
+    }
+
+    #[test]
+    fn message_queries() {
+        assert_eq!(message_wiki_query("  Iron plate \n").unwrap(), "Iron plate");
+        assert!(message_wiki_query(" \n ").is_err());
+        assert!(message_wiki_query(&"a".repeat(WIKI_QUERY_LIMIT + 1)).is_err());
+    }
+}